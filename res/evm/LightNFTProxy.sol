@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: GPL-3.0
+pragma solidity >=0.8.8 <0.9.0;
+
+/// Transparent proxy (EIP-1967) in front of LightNFTUpgradeable. Calls from
+/// the admin manage the proxy, every other call runs the implementation on
+/// the proxy storage. The admin is a LightNFTProxyAdmin, so the contract
+/// owner, who mints, never is.
+contract LightNFTProxy {
+    // bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)
+    bytes32 private constant IMPLEMENTATION_SLOT =
+        0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc;
+    // bytes32(uint256(keccak256("eip1967.proxy.admin")) - 1)
+    bytes32 private constant ADMIN_SLOT =
+        0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103;
+
+    event Upgraded(address indexed implementation);
+    event AdminChanged(address previousAdmin, address newAdmin);
+
+    constructor(address logic, address admin_, bytes memory data) payable {
+        _setImplementation(logic);
+        _setAdmin(admin_);
+        if (data.length > 0) {
+            _delegate(logic, data);
+        }
+    }
+
+    modifier ifAdmin() {
+        if (msg.sender == _admin()) {
+            _;
+        } else {
+            _fallback();
+        }
+    }
+
+    function admin() external ifAdmin returns (address) {
+        return _admin();
+    }
+
+    function implementation() external ifAdmin returns (address) {
+        return _implementation();
+    }
+
+    function changeAdmin(address newAdmin) external ifAdmin {
+        require(newAdmin != address(0), "No null admin is allowed");
+        emit AdminChanged(_admin(), newAdmin);
+        _setAdmin(newAdmin);
+    }
+
+    function upgradeTo(address newImplementation) external ifAdmin {
+        _setImplementation(newImplementation);
+    }
+
+    function upgradeToAndCall(address newImplementation, bytes calldata data)
+        external
+        payable
+        ifAdmin
+    {
+        _setImplementation(newImplementation);
+        _delegate(newImplementation, data);
+    }
+
+    fallback() external payable {
+        _fallback();
+    }
+
+    receive() external payable {
+        _fallback();
+    }
+
+    function _fallback() private {
+        require(msg.sender != _admin(), "admin cannot fallback to proxy target");
+        address impl = _implementation();
+        assembly {
+            calldatacopy(0, 0, calldatasize())
+            let result := delegatecall(gas(), impl, 0, calldatasize(), 0, 0)
+            returndatacopy(0, 0, returndatasize())
+            switch result
+            case 0 {
+                revert(0, returndatasize())
+            }
+            default {
+                return(0, returndatasize())
+            }
+        }
+    }
+
+    function _delegate(address impl, bytes memory data) private {
+        (bool ok, bytes memory reason) = impl.delegatecall(data);
+        if (!ok) {
+            assembly {
+                revert(add(reason, 32), mload(reason))
+            }
+        }
+    }
+
+    function _implementation() private view returns (address impl) {
+        bytes32 slot = IMPLEMENTATION_SLOT;
+        assembly {
+            impl := sload(slot)
+        }
+    }
+
+    function _setImplementation(address newImplementation) private {
+        require(newImplementation.code.length > 0, "implementation is not a contract");
+        bytes32 slot = IMPLEMENTATION_SLOT;
+        assembly {
+            sstore(slot, newImplementation)
+        }
+        emit Upgraded(newImplementation);
+    }
+
+    function _admin() private view returns (address adm) {
+        bytes32 slot = ADMIN_SLOT;
+        assembly {
+            adm := sload(slot)
+        }
+    }
+
+    function _setAdmin(address newAdmin) private {
+        bytes32 slot = ADMIN_SLOT;
+        assembly {
+            sstore(slot, newAdmin)
+        }
+    }
+}
+
+/// Admin of LightNFTProxy instances, owned by the contract owner: the owner
+/// upgrades through it and mints through the proxy with the same key.
+contract LightNFTProxyAdmin {
+    address private _owner;
+
+    event OwnershipTransferred(address previousOwner, address newOwner);
+
+    constructor() {
+        _owner = msg.sender;
+    }
+
+    modifier isOwner() {
+        require(msg.sender == _owner, "Caller is not owner");
+        _;
+    }
+
+    function owner() public view returns (address) {
+        return _owner;
+    }
+
+    function getProxyImplementation(LightNFTProxy proxy) public view returns (address) {
+        (bool ok, bytes memory result) = address(proxy).staticcall(
+            abi.encodeWithSelector(LightNFTProxy.implementation.selector)
+        );
+        require(ok, "proxy implementation unavailable");
+        return abi.decode(result, (address));
+    }
+
+    function getProxyAdmin(LightNFTProxy proxy) public view returns (address) {
+        (bool ok, bytes memory result) = address(proxy).staticcall(
+            abi.encodeWithSelector(LightNFTProxy.admin.selector)
+        );
+        require(ok, "proxy admin unavailable");
+        return abi.decode(result, (address));
+    }
+
+    function upgrade(LightNFTProxy proxy, address implementation) public isOwner {
+        proxy.upgradeTo(implementation);
+    }
+
+    function upgradeAndCall(
+        LightNFTProxy proxy,
+        address implementation,
+        bytes memory data
+    ) public payable isOwner {
+        proxy.upgradeToAndCall{value: msg.value}(implementation, data);
+    }
+
+    function changeProxyAdmin(LightNFTProxy proxy, address newAdmin) public isOwner {
+        proxy.changeAdmin(newAdmin);
+    }
+
+    function transferOwnership(address newOwner) public isOwner {
+        require(newOwner != address(0), "No null owner is allowed");
+        emit OwnershipTransferred(_owner, newOwner);
+        _owner = newOwner;
+    }
+}
@@ -52,16 +52,13 @@ module truly::hasher {
     //     &self.creator
     // }
 
-    entry fun add_hash(hash_value: vector<u8>, algorithm: vector<u8>, truly_id: vector<u8>, ctx: &mut sui::tx_context::TxContext) {
-
+    fun new_keeper(hash_value: vector<u8>, algorithm: vector<u8>, truly_id: vector<u8>, ctx: &mut sui::tx_context::TxContext): HashKeeper {
 
         assert!( std::vector::length(&hash_value) != 0  , EHashEmpty );
         assert!( std::vector::length(&algorithm) != 0  , EHashEmpty );
         assert!( std::vector::length(&truly_id) != 0  , EHashEmpty );
 
         let id_new = object::new(ctx);
-        //let sender = tx_context::sender(ctx);
-
 
         let new_hash = HashKeeper {
             id: id_new,
@@ -70,13 +67,23 @@ module truly::hasher {
             truly_id: string::utf8(truly_id), 
         };
 
-
          event::emit( HashAdded { 
              truly_id: string::utf8(truly_id), 
          });
+
+        new_hash
+    }
+
+    // the keeper becomes immutable, nobody owns it
+    entry fun add_hash(hash_value: vector<u8>, algorithm: vector<u8>, truly_id: vector<u8>, ctx: &mut sui::tx_context::TxContext) {
+        let new_hash = new_keeper(hash_value, algorithm, truly_id, ctx);
         transfer::freeze_object(new_hash);
-        //transfer::transfer(new_hash,sender);
+    }
 
+    // the keeper is owned by the user, so they can prove the registration is theirs
+    entry fun add_hash_for(hash_value: vector<u8>, algorithm: vector<u8>, truly_id: vector<u8>, recipient: address, ctx: &mut sui::tx_context::TxContext) {
+        let new_hash = new_keeper(hash_value, algorithm, truly_id, ctx);
+        transfer::transfer(new_hash, recipient);
     }
 
 
@@ -161,5 +168,32 @@ module truly::hasher {
 
     }
 
+    #[test]
+    public fun test_create_for_user() {
+
+        use sui::test_scenario;
+
+        let admin = @0xABC;
+        let user = @0xA00;
+        let scenario_val = test_scenario::begin(admin);
+        let scenario = &mut scenario_val;
+        {
+            add_hash_for(b"HashHashHash333333", b"MD5", b"777-1234-123-1", user, test_scenario::ctx(scenario));
+        };
+        test_scenario::next_tx(scenario, user);
+        {
+            let keep: HashKeeper = test_scenario::take_from_address<HashKeeper>(scenario, user);
+            assert!(*hash(&keep) == string::utf8(b"HashHashHash333333"), 1);
+            assert!(*truly_id(&keep) == string::utf8(b"777-1234-123-1"), 1);
+            test_scenario::return_to_address(user, keep);
+        };
+        test_scenario::next_tx(scenario, admin);
+        {
+            assert!(!test_scenario::has_most_recent_for_address<HashKeeper>(admin), 1);
+        };
+
+        test_scenario::end(scenario_val);
+    }
+
 
 }
\ No newline at end of file
@@ -1,11 +1,15 @@
 use std::fmt;
+use std::str::FromStr;
 
 use async_trait::async_trait;
+use lib_config::{config::Config, environment::DEV_ENV};
 use serde::{Deserialize, Serialize};
+use url::Url;
 use uuid::Uuid;
 
 use crate::models::block_tx::BlockchainTx;
-use crate::models::keypair::KeyPair;
+use crate::models::blockchain::Blockchain;
+use crate::models::keypair::{ChainFamily, KeyPair};
 
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
@@ -23,7 +27,13 @@ pub trait NFTsRepository: Send + Sync + CloneBoxNFTsRepository {
 
     //async fn get(&self, asset_id: &Uuid) -> ResultE<ContractContentInfo>;
     async fn get(&self, token: &String) -> ResultE<ContractContentInfo>;
+    // every chain identifies the minted content differently, tx record -> on-chain token
+    fn token(&self, tx: &BlockchainTx) -> Option<String>;
+    // asset ids minted by this contract, read from the chain itself (events)
+    async fn minted_asset_ids(&self) -> ResultE<Vec<String>>;
     fn contract_id(&self) -> u16;
+    // kind of user keys this backend signs with
+    fn chain_family(&self) -> ChainFamily;
     async fn create_keypair(&self, user_id: &String) -> ResultE<(KeyPair, bool)>;
 }
 
@@ -52,6 +62,23 @@ impl Clone for Box<dyn NFTsRepository + Send + Sync> {
     }
 }
 
+/// Endpoint of the node of `blockchain`, local nodes take no api key.
+pub fn node_url(blockchain: &Blockchain, conf: &Config) -> Url {
+    if conf.env_vars().environment().unwrap() == DEV_ENV {
+        blockchain.url().to_owned()
+    } else {
+        Url::from_str(
+            format!(
+                "{}/{}",
+                blockchain.url().to_owned(),
+                blockchain.api_key().to_owned()
+            )
+            .as_str(),
+        )
+        .unwrap()
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 #[allow(non_snake_case)]
 pub struct ContractContentInfo {
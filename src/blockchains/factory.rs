@@ -0,0 +1,96 @@
+use std::str::FromStr;
+
+use lib_config::config::Config;
+use sui_types::base_types::ObjectID;
+use web3::types::H160;
+
+use crate::errors::blockchain::BlockchainBackendError;
+use crate::models::blockchain::Blockchain;
+use crate::models::contract::Contract;
+use crate::models::keypair::ChainFamily;
+use crate::repositories::blockchain::{BlockchainRepo, BlockchainRepository};
+use crate::repositories::contract::{ContractRepo, ContractRepository};
+
+use super::chain::NFTsRepository;
+use super::ganache::GanacheBlockChain;
+use super::sui::SuiBlockChain;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+/// The backend serving `contract_id`, picked by the family of its
+/// blockchain record. Moving a contract to another chain is a change in
+/// the records, callers don't need to know which backend they get.
+pub async fn nfts_backend(
+    contract_id: &u16,
+    conf: &Config,
+    contracts_repo: &ContractRepo,
+    blockchains_repo: &BlockchainRepo,
+) -> ResultE<Box<dyn NFTsRepository + Sync + Send>> {
+    let contract = contracts_repo.get_by_id(contract_id).await?;
+    let blockchain = blockchains_repo.get_by_id(contract.blockchain()).await?;
+
+    match check_contract_fits(&contract, &blockchain)? {
+        ChainFamily::Evm => {
+            let backend = GanacheBlockChain::for_contract(
+                contract_id,
+                conf,
+                contracts_repo,
+                blockchains_repo,
+            )
+            .await?;
+            Ok(Box::new(backend))
+        }
+        ChainFamily::Sui => {
+            let backend =
+                SuiBlockChain::for_contract(contract_id, conf, contracts_repo, blockchains_repo)
+                    .await?;
+            Ok(Box::new(backend))
+        }
+    }
+}
+
+/// Checks `contract` holds what a backend for `blockchain` needs, before
+/// building one: the backends assume those fields are there.
+pub fn check_contract_fits(contract: &Contract, blockchain: &Blockchain) -> ResultE<ChainFamily> {
+    let unfit = |what: &str| -> ResultE<ChainFamily> {
+        let mssag = format!("contract {} {}", contract.id(), what);
+        Err(BlockchainBackendError(mssag).into())
+    };
+    let family = match blockchain.family() {
+        None => {
+            return unfit(&format!(
+                "is at {}, a chain of no known kind",
+                blockchain.id()
+            ))
+        }
+        Some(family) => family.to_owned(),
+    };
+    let address = match contract.address() {
+        None => return unfit("has no address"),
+        Some(address) => address,
+    };
+    let has_owner = contract.owner_signer_key_id().is_some()
+        || (contract.owner_secret().is_some() && contract.owner_address().is_some());
+    if !has_owner {
+        return unfit("has neither an owner secret and address nor a kms signer key");
+    }
+
+    match family {
+        ChainFamily::Evm => {
+            if H160::from_str(address).is_err() {
+                return unfit("address isn't an evm address");
+            }
+        }
+        ChainFamily::Sui => {
+            if ObjectID::from_str(address).is_err() {
+                return unfit("address isn't a sui package id");
+            }
+            //gas for every registration is paid from this coin
+            match contract.owner_cash() {
+                Some(cash) if ObjectID::from_str(cash).is_ok() => {}
+                _ => return unfit("needs an owner cash coin on sui"),
+            }
+        }
+    }
+    Ok(family)
+}
@@ -1,38 +1,61 @@
 use async_trait::async_trait;
 use chrono::{DateTime, NaiveDateTime, Utc};
-use lib_config::infra::{cypher_with_secret_key, uncypher_with_secret_key};
-use lib_config::{config::Config, environment::DEV_ENV};
+use lib_config::config::Config;
 use log::debug;
-use secp256k1::SecretKey;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 use url::Url;
 use uuid::Uuid;
 
 use web3::{
-    contract::{tokens::Detokenize, Contract, Options},
+    contract::{
+        tokens::{Detokenize, Tokenize},
+        Contract, Options,
+    },
     transports::Http,
-    types::{Address, Block, BlockId, BlockNumber, H160, H256, U256},
+    ethabi::{Function, RawLog},
+    signing::keccak256,
+    types::{
+        Address, Block, BlockId, BlockNumber, Bytes, FilterBuilder, TransactionParameters, H160,
+        H256, U256,
+    },
     Web3, //, signing::SecretKey,
 };
 
+use crate::errors::amount::AmountConversionError;
 use crate::errors::asset::AssetBlockachainError;
+use crate::errors::contract::ContractNotWritableError;
+use crate::errors::deployment::DeploymentError;
+use crate::models::amount::Amount;
 use crate::models::block_tx::MintingStatus;
+use crate::models::secret::Secret;
 use crate::{
-    errors::nft::HydrateMasterSecretKeyError,
     models::block_tx::BlockchainTx,
     repositories::{
         blockchain::BlockchainRepo, blockchain::BlockchainRepository, contract::ContractRepo,
-        contract::ContractRepository,
+        contract::ContractRepository, user_data_keys::UserDataKeyRepo,
     },
 };
-use crate::{errors::nft::NftUserAddressMalformedError, models::keypair::KeyPair};
+use crate::{
+    errors::nft::NftUserAddressMalformedError,
+    models::keypair::{ChainFamily, KeyPair},
+};
 
 const CONTRACT_METHOD_MINTING: &'static str = "mint";
 const CONTRACT_METHOD_GET_CONTENT_BY_TOKEN: &'static str = "getContentByToken";
+const CONTRACT_EVENT_MINTED: &'static str = "Minted";
+const CONTRACT_EVENT_MINTED_TOKEN: &'static str = "token";
+//blocks per eth_getLogs request, within what public providers accept
+const LOGS_BLOCK_RANGE: u64 = 5000;
 
 //use lib_licenses::errors::asset::AssetBlockachainError;
 
-use super::chain::{ContentState, ContractContentInfo, NFTsRepository};
+use super::chain::{node_url, ContentState, ContractContentInfo, NFTsRepository};
+use super::key_cache::KeyCache;
+use super::signer::{contract_owner_signer, sign_evm_transaction, Signer};
+use super::user_keys::UserDataKeys;
 
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
@@ -41,12 +64,16 @@ pub struct GanacheBlockChain {
     url: Url,
     contract_address: Address,
     contract_owner_address: Address,
-    contract_owner_secret: String,
+    signer: Box<dyn Signer + Sync + Send>,
+    key_cache: KeyCache,
+    user_keys: UserDataKeys,
+    contracts_repo: ContractRepo,
     kms_key_id: String,
     //aws: SdkConfig,
     config: Config,
     blockhain_node_confirmations: u16,
     contract_id: u16,
+    deployment_block: u64,
 }
 
 impl GanacheBlockChain {
@@ -56,41 +83,66 @@ impl GanacheBlockChain {
         blockchains_repo: &BlockchainRepo,
     ) -> ResultE<GanacheBlockChain> {
         let aux = conf.env_vars().contract_id().unwrap();
+        GanacheBlockChain::for_contract(&aux, conf, contracts_repo, blockchains_repo).await
+    }
+
+    /// Backend for `contract_id` rather than the one in the env config.
+    pub async fn for_contract(
+        contract_id: &u16,
+        conf: &Config,
+        contracts_repo: &ContractRepo,
+        blockchains_repo: &BlockchainRepo,
+    ) -> ResultE<GanacheBlockChain> {
+        let aux = contract_id.to_owned();
         let contract = contracts_repo.get_by_id(&aux).await?;
+        if !contract.status().accepts_reads() {
+            let mssag = format!("contract {} is {}", aux, contract.status());
+            return Err(ContractNotWritableError(mssag).into());
+        }
         let blockchain = blockchains_repo.get_by_id(contract.blockchain()).await?;
 
-        let blockchain_url;
-        if conf.env_vars().environment().unwrap() == DEV_ENV {
-            blockchain_url = blockchain.url().to_owned()
-        } else {
-            blockchain_url = Url::from_str(
-                format!(
-                    "{}/{}",
-                    blockchain.url().to_owned(),
-                    blockchain.api_key().to_owned()
-                )
-                .as_str(),
-            )
-            .unwrap();
-        }
+        let blockchain_url = node_url(&blockchain, conf);
 
         let contract_address =
             H160::from_str(contract.address().clone().unwrap().as_str()).unwrap();
-        let contract_owner_address =
-            H160::from_str(contract.owner_address().clone().unwrap().as_str()).unwrap();
+
+        let kms_key_id = conf.env_vars().kms_key_id().unwrap();
+        let key_cache = KeyCache::default();
+        let signer = contract_owner_signer(&contract, &ChainFamily::Evm, conf, &key_cache).await?;
+        let contract_owner_address = H160::from_str(signer.address().as_str()).unwrap();
 
         Ok(GanacheBlockChain {
             url: blockchain_url.to_owned(),
             contract_address,       //contract_address_position,
             contract_owner_address, //contract_owner_position,
-            contract_owner_secret: contract.owner_secret().clone().unwrap().to_owned(), //contract_owner_position,
-            kms_key_id: conf.env_vars().kms_key_id().unwrap(),
+            signer,
+            key_cache,
+            user_keys: UserDataKeys::new(UserDataKeyRepo::new(conf), conf),
+            contracts_repo: contracts_repo.clone(),
+            kms_key_id,
             //aws: conf.aws_config().to_owned(),
             config: conf.clone(),
             blockhain_node_confirmations: blockchain.confirmations().to_owned(), //conf.env_vars().blockchain_confirmations().to_owned(),
             contract_id: aux.to_owned(), //contract.to_owned(),
+            //contracts recorded before the block was kept are read from genesis
+            deployment_block: contract.deployment_block().unwrap_or(0),
         })
     }
+
+    /// Decrypted owner secrets, shared by the clones of this backend.
+    pub fn key_cache(&self) -> &KeyCache {
+        &self.key_cache
+    }
+
+    /// Signs mints with `signer` instead of the one recorded at the contract.
+    pub fn with_signer(
+        mut self,
+        signer: Box<dyn Signer + Sync + Send>,
+    ) -> ResultE<GanacheBlockChain> {
+        self.contract_owner_address = H160::from_str(signer.address().as_str())?;
+        self.signer = signer;
+        Ok(self)
+    }
 }
 
 #[async_trait]
@@ -98,15 +150,22 @@ impl NFTsRepository for GanacheBlockChain {
     fn contract_id(&self) -> u16 {
         self.contract_id
     }
+    fn chain_family(&self) -> ChainFamily {
+        ChainFamily::Evm
+    }
     async fn add(
         &self,
         asset_id: &Uuid,
         user_key: &KeyPair,
         hash_file: &String,
-        _hash_algorithm: &String,
+        hash_algorithm: &String,
         prc: &Option<u64>,
         _cntr: &u64,
     ) -> ResultE<BlockchainTx> {
+        //the status may have changed since this backend was built
+        let stored = self.contracts_repo.get_by_id(&self.contract_id).await?;
+        stored.check_mintable()?;
+
         let transport = web3::transports::Http::new(self.url.as_str()).unwrap();
         let web3 = web3::Web3::new(transport);
 
@@ -162,62 +221,52 @@ impl NFTsRepository for GanacheBlockChain {
             Ok(gas) => gas,
         };
 
-        //let nonce = U256::from(1); //
-        //let nonce = generate_nonce(&self.counter )?;
-        // debug!("nonce calculated: {}", nonce);
-
-        let counter = web3
+        //every field is set, so the signer signs exactly what is sent
+        let nonce_op = web3
             .eth()
-            .transaction_count(self.contract_address, None)
-            .await
-            .unwrap();
-        debug!("{}", counter);
-        //counter += U256::from_str_radix("1", 10).unwrap();
-        //debug!("{}", counter);
-
-        let tx_options = Options {
-            gas: Some(cost_gas), // Some(U256::from_str("400000").unwrap()), //250.000 weis  30.000.000 //with 400.000 gas units works!
-            gas_price: Some(gas_price), // None,     // Some(U256::from_str("10000000").unwrap()), //10.000.000 weis
-            value: None,
-            condition: None,
-            transaction_type: None,
-            nonce: None, //Some(counter), // None
-            access_list: None,
-            max_fee_per_gas: None,
-            max_priority_fee_per_gas: None,
+            .transaction_count(self.contract_owner_address, Some(BlockNumber::Pending))
+            .await;
+        let nonce = match nonce_op {
+            Err(e) => {
+                return Err(AssetBlockachainError(e.to_string()).into());
+            }
+            Ok(value) => value,
         };
-        debug!("calling from {}", self.contract_address.to_string());
-
-        let contract_owner_private_key;
-        //let contract_owner_private_key_op = self.decrypt_contract_owner_secret_key().await;
-
-        let contract_owner_private_key_op = SecretKey::from_str(
-            uncypher_with_secret_key(
-                self.contract_owner_secret.to_owned(),
-                &self.kms_key_id,
-                &self.config,
-            )
-            .await
-            .unwrap()
-            .as_str(),
-        );
-
-        match contract_owner_private_key_op {
-            Err(_) => {
-                return Err(HydrateMasterSecretKeyError {}.into());
+        let chain_id_op = web3.eth().chain_id().await;
+        let chain_id = match chain_id_op {
+            Err(e) => {
+                return Err(AssetBlockachainError(e.to_string()).into());
             }
-            Ok(value) => contract_owner_private_key = value,
-        }
+            Ok(value) => value.as_u64(),
+        };
+        let data = contract
+            .abi()
+            .function(CONTRACT_METHOD_MINTING)?
+            .encode_input(
+                &(to.clone(), token.clone(), hash_file.clone(), price.clone()).into_tokens(),
+            )?;
+
+        let tx_params = TransactionParameters {
+            nonce: Some(nonce),
+            to: Some(self.contract_address),
+            gas: cost_gas,
+            gas_price: Some(gas_price),
+            value: U256::zero(),
+            data: Bytes(data),
+            chain_id: Some(chain_id),
+            ..Default::default()
+        };
+        debug!("calling from {}", self.contract_owner_address.to_string());
 
-        let caller = contract.signed_call_with_confirmations(
-            CONTRACT_METHOD_MINTING,
-            (to.clone(), token.clone(), hash_file.clone(), price.clone()),
-            tx_options,
-            self.blockhain_node_confirmations.into(),
-            &contract_owner_private_key,
-        );
+        let signed = sign_evm_transaction(&web3, self.signer.as_ref(), tx_params).await?;
 
-        let call_contract_op = caller.await;
+        let call_contract_op = web3
+            .send_raw_transaction_with_confirmation(
+                signed.raw_transaction,
+                Duration::from_secs(1),
+                self.blockhain_node_confirmations.into(),
+            )
+            .await;
         let tx = match call_contract_op {
             Err(e) => {
                 return Err(AssetBlockachainError(format!("{:?}", e)).into());
@@ -238,23 +287,44 @@ impl NFTsRepository for GanacheBlockChain {
         //     Some(bn) => Some(bn.as_u64())
         // };
 
+        let gas_used = match tx.gas_used {
+            None => None,
+            Some(gas) => Some(
+                u64::try_from(u256_to_u128(gas)?)
+                    .map_err(|e| AmountConversionError(e.to_string()))?,
+            ),
+        };
+        let effective_gas_price = match tx.effective_gas_price {
+            None => None,
+            Some(price) => Some(Amount::wei(u256_to_u128(price)?)),
+        };
+        let cost = match (tx.gas_used, tx.effective_gas_price) {
+            (Some(gas), Some(price)) => {
+                let total = gas.checked_mul(price).ok_or(AmountConversionError(format!(
+                    "gas {} * price {} overflows",
+                    gas, price
+                )))?;
+                Some(Amount::wei(u256_to_u128(total)?))
+            }
+            _ => None,
+        };
+
         let tx_paylaod = BlockchainTx::new(
             asset_id.to_owned(),
+            None,
             MintingStatus::CompletedSuccessfully,
             Utc::now(),
             Utc::now(),
             Some(tx.transaction_hash.to_string()),
             Some(tx.block_number.unwrap().as_u64()),
-            Some(tx.gas_used.unwrap().to_string()),
-            Some(tx.effective_gas_price.unwrap().to_string()),
-            Some(
-                wei_to_gwei(tx.gas_used.unwrap())
-                    * wei_to_gwei(tx.effective_gas_price.unwrap_or_default()),
-            ),
-            Some("gweis".to_string()),
+            gas_used,
+            effective_gas_price,
+            cost,
             Some(tx.from.to_string()),
             Some(tx.to.unwrap().to_string()),
             Some(self.contract_id),
+            Some(hash_file.to_owned()),
+            Some(hash_algorithm.to_owned()),
             None,
         );
         Ok(tx_paylaod)
@@ -296,9 +366,86 @@ impl NFTsRepository for GanacheBlockChain {
         Ok(cnt)
     }
 
+    fn token(&self, tx: &BlockchainTx) -> Option<String> {
+        //LightNFT is indexed by the asset id we passed as token when minting
+        Some(tx.asset_id().to_string())
+    }
+
+    async fn minted_asset_ids(&self) -> ResultE<Vec<String>> {
+        let transport = web3::transports::Http::new(self.url.as_str()).unwrap();
+        let web3 = web3::Web3::new(transport);
+
+        let contract_op = Contract::from_json(
+            web3.eth(),
+            self.contract_address.clone(),
+            include_bytes!("../../res/evm/LightNFT.abi"),
+        );
+        let contract = match contract_op {
+            Err(e) => {
+                return Err(AssetBlockachainError(e.to_string()).into());
+            }
+            Ok(cnt) => cnt,
+        };
+        let event = match contract.abi().event(CONTRACT_EVENT_MINTED) {
+            Err(e) => {
+                return Err(AssetBlockachainError(e.to_string()).into());
+            }
+            Ok(ev) => ev,
+        };
+
+        let latest = match web3.eth().block_number().await {
+            Err(e) => {
+                return Err(AssetBlockachainError(e.to_string()).into());
+            }
+            Ok(num) => num.as_u64(),
+        };
+
+        //providers cap eth_getLogs, so the range is read in pages
+        let mut tokens = Vec::new();
+        let mut from = self.deployment_block;
+        while from <= latest {
+            let to = std::cmp::min(from + LOGS_BLOCK_RANGE - 1, latest);
+            let filter = FilterBuilder::default()
+                .address(vec![self.contract_address.clone()])
+                .topics(Some(vec![event.signature()]), None, None, None)
+                .from_block(BlockNumber::Number(from.into()))
+                .to_block(BlockNumber::Number(to.into()))
+                .build();
+
+            let logs = match web3.eth().logs(filter).await {
+                Err(e) => {
+                    return Err(AssetBlockachainError(e.to_string()).into());
+                }
+                Ok(lgs) => lgs,
+            };
+
+            for log in logs {
+                let parsed_op = event.parse_log(RawLog {
+                    topics: log.topics,
+                    data: log.data.0,
+                });
+                let parsed = match parsed_op {
+                    Err(e) => {
+                        return Err(AssetBlockachainError(e.to_string()).into());
+                    }
+                    Ok(prs) => prs,
+                };
+                for param in parsed.params {
+                    if param.name == CONTRACT_EVENT_MINTED_TOKEN {
+                        if let Some(token) = param.value.into_string() {
+                            tokens.push(token);
+                        }
+                    }
+                }
+            }
+            from = to + 1;
+        }
+        Ok(tokens)
+    }
+
     async fn create_keypair(&self, user_id: &String) -> ResultE<(KeyPair, bool)> {
         use secp256k1::rand::{rngs, SeedableRng};
-        use web3::signing::keccak256;
+        use web3::signing::{Key, SecretKeyRef};
 
         let secp = secp256k1::Secp256k1::new();
 
@@ -306,35 +453,134 @@ impl NFTsRepository for GanacheBlockChain {
         let mut rng = rngs::StdRng::seed_from_u64(rand::random::<u64>());
 
         let contract_owner_key_pair = secp.generate_keypair(&mut rng);
-        let contract_owner_public = contract_owner_key_pair.1.serialize();
-        let hash = keccak256(&contract_owner_public[1..32]);
-        let user_address = format!("0x{}", hex::encode(&hash[12..32]));
+        let user_address = format!(
+            "{:?}",
+            SecretKeyRef::new(&contract_owner_key_pair.0).address()
+        );
         //let user_private = contract_owner_key_pair.0;
-        let user_private_key = format!("{}", contract_owner_key_pair.0.display_secret());
+        let user_private_key =
+            Secret::from(format!("{}", contract_owner_key_pair.0.display_secret()));
         let user_public_key = format!("{}", contract_owner_key_pair.1);
 
-        let user_private_key_cyphered =
-            cypher_with_secret_key(&user_private_key, &self.kms_key_id, &self.config).await?;
-        let user_public_key_cyphered =
-            cypher_with_secret_key(&user_public_key, &self.kms_key_id, &self.config).await?;
+        let user_private_key_cyphered = self
+            .user_keys
+            .seal(user_id, &self.kms_key_id, &user_private_key)
+            .await?;
 
         let mut user_key = KeyPair::new();
         user_key.set_user_id(user_id);
+        user_key.set_chain(&ChainFamily::Evm);
         user_key.set_address(&user_address);
         user_key.set_private_key(&user_private_key_cyphered);
-        user_key.set_public_key(&user_public_key_cyphered);
+        user_key.set_public_key(&user_public_key);
+        user_key.set_key_id(&self.kms_key_id);
 
         Ok((user_key, true))
     }
 }
 
-fn _wei_to_eth(wei_val: U256) -> f64 {
-    let res = wei_val.as_u128() as f64;
-    res / 1_000_000_000_000_000_000.0
+/// Abi and creation code of a contract, as `solc --abi --bin` leaves them.
+#[derive(Clone, Debug)]
+pub struct EvmArtifact {
+    name: String,
+    abi: Vec<u8>,
+    bytecode: Vec<u8>,
+}
+
+impl EvmArtifact {
+    /// `<name>.abi` and `<name>.bin` at `dir`, for implementations built
+    /// outside the crate.
+    pub fn from_dir(dir: &Path, name: &str) -> ResultE<EvmArtifact> {
+        let read = |ext: &str| {
+            let path = dir.join(format!("{}.{}", name, ext));
+            fs::read_to_string(&path).map_err(|e| {
+                let mssag = format!("{} isn't built, see res/evm/README.md: {}", name, e);
+                DeploymentError(mssag)
+            })
+        };
+        let abi = read("abi")?.into_bytes();
+        let bytecode = hex::decode(read("bin")?.trim().trim_start_matches("0x"))?;
+        Ok(EvmArtifact {
+            name: name.to_string(),
+            abi,
+            bytecode,
+        })
+    }
+
+    /// The plain LightNFT, built into the crate.
+    pub fn light_nft() -> EvmArtifact {
+        EvmArtifact::built_in(
+            "LightNFT",
+            include_bytes!("../../res/evm/LightNFT.abi"),
+            include_str!("../../res/evm/LightNFT.bin"),
+        )
+    }
+
+    /// LightNFT to run behind a LightNFTProxy, built into the crate.
+    pub fn light_nft_upgradeable() -> EvmArtifact {
+        EvmArtifact::built_in(
+            "LightNFTUpgradeable",
+            include_bytes!("../../res/evm/LightNFTUpgradeable.abi"),
+            include_str!("../../res/evm/LightNFTUpgradeable.bin"),
+        )
+    }
+
+    /// The proxy LightNFTUpgradeable runs behind, built into the crate.
+    pub fn light_nft_proxy() -> EvmArtifact {
+        EvmArtifact::built_in(
+            "LightNFTProxy",
+            include_bytes!("../../res/evm/LightNFTProxy.abi"),
+            include_str!("../../res/evm/LightNFTProxy.bin"),
+        )
+    }
+
+    /// The admin of LightNFTProxy instances, built into the crate.
+    pub fn light_nft_proxy_admin() -> EvmArtifact {
+        EvmArtifact::built_in(
+            "LightNFTProxyAdmin",
+            include_bytes!("../../res/evm/LightNFTProxyAdmin.abi"),
+            include_str!("../../res/evm/LightNFTProxyAdmin.bin"),
+        )
+    }
+
+    // artifacts at res/evm are checked in, so they always decode
+    fn built_in(name: &str, abi: &[u8], bytecode: &str) -> EvmArtifact {
+        EvmArtifact {
+            name: name.to_string(),
+            abi: abi.to_vec(),
+            bytecode: hex::decode(bytecode.trim_end()).unwrap(),
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+    pub fn abi(&self) -> &Vec<u8> {
+        &self.abi
+    }
+    pub fn bytecode(&self) -> &Vec<u8> {
+        &self.bytecode
+    }
+
+    /// Name and digest of the abi, what a contract version records.
+    pub fn interface(&self) -> String {
+        format!("{}@{}", self.name, hex::encode(&keccak256(&self.abi)[..8]))
+    }
+
+    pub fn function(&self, name: &str) -> ResultE<Function> {
+        let abi = web3::ethabi::Contract::load(self.abi.as_slice())?;
+        Ok(abi.function(name)?.clone())
+    }
 }
-fn wei_to_gwei(wei_val: U256) -> f64 {
-    let res = wei_val.as_u128() as f64;
-    res / 1_000_000_000.0
+
+pub(crate) fn u256_to_u128(val: U256) -> Result<u128, AmountConversionError> {
+    if val > U256::from(u128::MAX) {
+        return Err(AmountConversionError(format!(
+            "{} doesn't fit in 128 bits",
+            val
+        )));
+    }
+    Ok(val.as_u128())
 }
 
 pub async fn block_status(client: &Web3<Http>) -> Block<H256> {
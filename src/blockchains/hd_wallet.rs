@@ -0,0 +1,166 @@
+use std::fmt;
+use std::str::FromStr;
+
+use bip32::{DerivationPath, Language, Mnemonic, XPrv};
+use lib_config::config::Config;
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use sui_keys::key_derive::derive_key_pair_from_path;
+use sui_types::crypto::{EncodeDecodeBase64, SignatureScheme};
+use web3::signing::{Key, SecretKeyRef};
+
+use crate::errors::keypair::KeyPairDerivationError;
+use crate::models::keypair::{ChainFamily, KeyPair};
+use crate::models::secret::Secret;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+// BIP-44 coin types
+const EVM_COIN_TYPE: u32 = 60;
+const SUI_COIN_TYPE: u32 = 784;
+
+/// BIP-32/BIP-44 wallet over a single master mnemonic. The mnemonic is only
+/// kept encrypted with KMS; user keys are re-derived from their index on
+/// demand, so the keypairs table doesn't hold any key material for them.
+#[derive(Clone)]
+pub struct HdWallet {
+    mnemonic_cyphered: Secret,
+    key_id: String,
+    config: Config,
+}
+
+impl fmt::Debug for HdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HdWallet key id: {}", self.key_id)
+    }
+}
+
+impl HdWallet {
+    pub fn new(mnemonic_cyphered: &Secret, key_id: &String, config: &Config) -> HdWallet {
+        HdWallet {
+            mnemonic_cyphered: mnemonic_cyphered.to_owned(),
+            key_id: key_id.to_owned(),
+            config: config.to_owned(),
+        }
+    }
+
+    /// Encrypts a plain mnemonic with `key_id`, keep `mnemonic_cyphered()` to build it again.
+    pub async fn from_mnemonic(
+        mnemonic: &Secret,
+        key_id: &String,
+        config: &Config,
+    ) -> ResultE<HdWallet> {
+        Mnemonic::new(mnemonic.expose().as_str(), Language::English)
+            .map_err(|_| KeyPairDerivationError("malformed master mnemonic".to_string()))?;
+        let mnemonic_cyphered = mnemonic.cypher(key_id, config).await?;
+        Ok(HdWallet::new(&mnemonic_cyphered, key_id, config))
+    }
+
+    pub fn mnemonic_cyphered(&self) -> &Secret {
+        &self.mnemonic_cyphered
+    }
+    pub fn key_id(&self) -> &String {
+        &self.key_id
+    }
+
+    /// The same mnemonic cyphered with `new_key_id`, derived keys don't
+    /// change. Keep its `mnemonic_cyphered()` in place of this one's.
+    pub async fn rotate(&self, new_key_id: &String) -> ResultE<HdWallet> {
+        let mnemonic = self
+            .mnemonic_cyphered
+            .uncypher(&self.key_id, &self.config)
+            .await?;
+        let mnemonic_cyphered = mnemonic.cypher(new_key_id, &self.config).await?;
+        Ok(HdWallet::new(&mnemonic_cyphered, new_key_id, &self.config))
+    }
+
+    pub fn derivation_path(chain: &ChainFamily, index: u32) -> String {
+        match chain {
+            ChainFamily::Evm => format!("m/44'/{}'/0'/0/{}", EVM_COIN_TYPE, index),
+            //sui only accepts hardened levels for ed25519
+            ChainFamily::Sui => format!("m/44'/{}'/{}'/0'/0'", SUI_COIN_TYPE, index),
+        }
+    }
+
+    pub async fn derive(&self, chain: &ChainFamily, index: u32) -> ResultE<DerivedKey> {
+        let mnemonic = self
+            .mnemonic_cyphered
+            .uncypher(&self.key_id, &self.config)
+            .await?;
+        derive_from_mnemonic(&mnemonic, chain, index)
+    }
+
+    /// The record stored for a derived user key: address, public key and
+    /// index, never the private key.
+    pub async fn derive_keypair(
+        &self,
+        user_id: &String,
+        chain: &ChainFamily,
+        index: u32,
+    ) -> ResultE<KeyPair> {
+        let derived = self.derive(chain, index).await?;
+
+        let mut user_key = KeyPair::new();
+        user_key.set_user_id(user_id);
+        user_key.set_chain(chain);
+        user_key.set_address(derived.address());
+        user_key.set_public_key(derived.public_key());
+        user_key.set_derivation_index(&index);
+        Ok(user_key)
+    }
+}
+
+/// A key derived from the master mnemonic. The secret is hex for EVM keys
+/// and the base64 `SuiKeyPair` for Sui keys.
+#[derive(Debug)]
+pub struct DerivedKey {
+    address: String,
+    public_key: String,
+    secret: Secret,
+}
+
+impl DerivedKey {
+    pub fn address(&self) -> &String {
+        &self.address
+    }
+    pub fn public_key(&self) -> &String {
+        &self.public_key
+    }
+    pub fn secret(&self) -> &Secret {
+        &self.secret
+    }
+}
+
+pub fn derive_from_mnemonic(
+    mnemonic: &Secret,
+    chain: &ChainFamily,
+    index: u32,
+) -> ResultE<DerivedKey> {
+    //the error would echo the mnemonic words back
+    let mnemonic = Mnemonic::new(mnemonic.expose().as_str(), Language::English)
+        .map_err(|_| KeyPairDerivationError("malformed master mnemonic".to_string()))?;
+    let seed = mnemonic.to_seed("");
+    let path = DerivationPath::from_str(HdWallet::derivation_path(chain, index).as_str())?;
+
+    match chain {
+        ChainFamily::Evm => {
+            let xprv = XPrv::derive_from_path(seed.as_bytes(), &path)?;
+            let secret_key = SecretKey::from_slice(&xprv.private_key().to_bytes())?;
+            let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+            let address = SecretKeyRef::new(&secret_key).address();
+            Ok(DerivedKey {
+                address: format!("{:?}", address),
+                public_key: format!("{}", public_key),
+                secret: Secret::from(format!("{}", secret_key.display_secret())),
+            })
+        }
+        ChainFamily::Sui => {
+            let (address, keypair) =
+                derive_key_pair_from_path(seed.as_bytes(), Some(path), &SignatureScheme::ED25519)?;
+            Ok(DerivedKey {
+                address: address.to_string(),
+                public_key: keypair.public().encode_base64(),
+                secret: Secret::from(keypair.encode_base64()),
+            })
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use lib_config::config::Config;
+
+use crate::models::secret::Secret;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+pub const DEFAULT_KEY_CACHE_TTL_SECS: u64 = 600;
+pub const DEFAULT_KEY_CACHE_CAPACITY: usize = 32;
+
+struct CachedKey {
+    plain: Secret,
+    cached_at: Instant,
+}
+
+/// Decrypted keys kept in memory for a while, so minting doesn't call KMS
+/// for the owner secret every time. Clones share the same entries; evicted
+/// keys are wiped as any `Secret`. A zero ttl or capacity disables it.
+#[derive(Clone)]
+pub struct KeyCache {
+    entries: Arc<Mutex<HashMap<(String, String), CachedKey>>>,
+    ttl: Duration,
+    capacity: usize,
+}
+
+impl fmt::Debug for KeyCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "KeyCache ttl: {:?} capacity: {} cached: {}",
+            self.ttl,
+            self.capacity,
+            self.len()
+        )
+    }
+}
+
+impl Default for KeyCache {
+    fn default() -> KeyCache {
+        KeyCache::new(
+            Duration::from_secs(DEFAULT_KEY_CACHE_TTL_SECS),
+            DEFAULT_KEY_CACHE_CAPACITY,
+        )
+    }
+}
+
+impl KeyCache {
+    pub fn new(ttl: Duration, capacity: usize) -> KeyCache {
+        KeyCache {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            capacity,
+        }
+    }
+
+    pub fn ttl(&self) -> &Duration {
+        &self.ttl
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+
+    /// `cyphered` in clear, from memory when it was decrypted less than
+    /// `ttl` ago, from KMS otherwise.
+    pub async fn uncypher(
+        &self,
+        cyphered: &Secret,
+        key_id: &String,
+        config: &Config,
+    ) -> ResultE<Secret> {
+        if self.ttl.is_zero() || self.capacity == 0 {
+            return cyphered.uncypher(key_id, config).await;
+        }
+        let entry_key = (key_id.to_owned(), cyphered.expose().to_owned());
+        {
+            let mut entries = self.entries.lock().unwrap();
+            let ttl = self.ttl;
+            entries.retain(|_, cached| cached.cached_at.elapsed() < ttl);
+            if let Some(cached) = entries.get(&entry_key) {
+                return Ok(cached.plain.clone());
+            }
+        }
+
+        //never hold the lock while waiting for KMS
+        let plain = cyphered.uncypher(key_id, config).await?;
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&entry_key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.cached_at)
+                .map(|(key, _)| key.clone());
+            if let Some(key) = oldest {
+                entries.remove(&key);
+            }
+        }
+        entries.insert(
+            entry_key,
+            CachedKey {
+                plain: plain.clone(),
+                cached_at: Instant::now(),
+            },
+        );
+        Ok(plain)
+    }
+
+    /// Drops every key decrypted with `key_id`, i.e. once it has been rotated.
+    pub fn invalidate(&self, key_id: &String) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(cached_key_id, _), _| cached_key_id != key_id);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
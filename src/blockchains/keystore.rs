@@ -0,0 +1,217 @@
+use aes::cipher::{KeyIvInit, StreamCipher};
+use base64::{engine::general_purpose, Engine};
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use serde::{Deserialize, Serialize};
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{EncodeDecodeBase64, SuiKeyPair};
+use uuid::Uuid;
+use web3::signing::{keccak256, Key, SecretKeyRef};
+use zeroize::Zeroize;
+
+use crate::errors::keystore::KeystoreError;
+use crate::models::keypair::ChainFamily;
+use crate::models::secret::Secret;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+// geth's "standard" scrypt cost, what wallets write by default
+const SCRYPT_LOG_N: u8 = 18;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+// the most a document to decrypt may ask for, 1GiB of memory at most
+const MAX_SCRYPT_LOG_N: u32 = 20;
+const MAX_SCRYPT_R: u32 = 8;
+const MAX_SCRYPT_P: u32 = 4;
+const DKLEN: usize = 32;
+const CIPHER: &str = "aes-128-ctr";
+const KDF: &str = "scrypt";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct V3Keystore {
+    version: u8,
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    address: Option<String>,
+    #[serde(alias = "Crypto")]
+    crypto: V3Crypto,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct V3Crypto {
+    cipher: String,
+    cipherparams: V3CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: V3KdfParams,
+    mac: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct V3CipherParams {
+    iv: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct V3KdfParams {
+    dklen: usize,
+    n: u64,
+    r: u32,
+    p: u32,
+    salt: String,
+}
+
+/// Address and public key of a secret as stored in `KeyPair`s: hex for
+/// EVM, a base64 keystore entry (flag || key) for Sui.
+pub fn key_details(chain: &ChainFamily, secret: &Secret) -> ResultE<(String, String)> {
+    match chain {
+        ChainFamily::Evm => {
+            let mut bytes = hex::decode(secret.expose().trim_start_matches("0x"))
+                .map_err(|_| KeystoreError("malformed evm private key".to_string()))?;
+            let secret_key = SecretKey::from_slice(&bytes);
+            bytes.zeroize();
+            let secret_key = secret_key?;
+            let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+            let address = SecretKeyRef::new(&secret_key).address();
+            Ok((format!("{:?}", address), format!("{}", public_key)))
+        }
+        ChainFamily::Sui => {
+            let keypair = SuiKeyPair::decode_base64(secret.expose())
+                .map_err(|_| KeystoreError("malformed sui keystore entry".to_string()))?;
+            let public_key = keypair.public();
+            let address = SuiAddress::from(&public_key);
+            Ok((address.to_string(), public_key.encode_base64()))
+        }
+    }
+}
+
+/// Web3 Secret Storage (V3) document holding `secret` under `passphrase`.
+/// EVM keys come out as the keystore any Ethereum wallet imports; Sui has
+/// no encrypted format of its own, so the payload is its keystore entry.
+pub fn encrypt_keystore(
+    chain: &ChainFamily,
+    secret: &Secret,
+    passphrase: &Secret,
+) -> ResultE<String> {
+    let (address, _) = key_details(chain, secret)?;
+    let mut payload = payload_bytes(chain, secret)?;
+
+    let salt: [u8; 32] = rand::random();
+    let iv: [u8; 16] = rand::random();
+    let mut derived = derive(passphrase, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+    let cipher = Aes128Ctr::new_from_slices(&derived[0..16], &iv);
+    let mut cipher = cipher.map_err(|e| KeystoreError(e.to_string()))?;
+    cipher.apply_keystream(&mut payload);
+    let mac = mac(&derived, &payload);
+    derived.zeroize();
+
+    let keystore = V3Keystore {
+        version: 3,
+        id: Uuid::new_v4().to_string(),
+        address: Some(match chain {
+            ChainFamily::Evm => address.trim_start_matches("0x").to_string(),
+            ChainFamily::Sui => address,
+        }),
+        crypto: V3Crypto {
+            cipher: CIPHER.to_string(),
+            cipherparams: V3CipherParams {
+                iv: hex::encode(iv),
+            },
+            ciphertext: hex::encode(&payload),
+            kdf: KDF.to_string(),
+            kdfparams: V3KdfParams {
+                dklen: DKLEN,
+                n: 1 << SCRYPT_LOG_N,
+                r: SCRYPT_R,
+                p: SCRYPT_P,
+                salt: hex::encode(salt),
+            },
+            mac: hex::encode(mac),
+        },
+    };
+    Ok(serde_json::to_string(&keystore)?)
+}
+
+/// The secret inside a V3 document, in the same form `encrypt_keystore`
+/// takes it. Fails on a wrong passphrase, and when the address the
+/// document claims isn't the key's.
+pub fn decrypt_keystore(
+    chain: &ChainFamily,
+    document: &String,
+    passphrase: &Secret,
+) -> ResultE<Secret> {
+    let keystore: V3Keystore = serde_json::from_str(document)
+        .map_err(|e| KeystoreError(format!("not a v3 keystore: {}", e)))?;
+    let crypto = &keystore.crypto;
+    if keystore.version != 3 || crypto.cipher != CIPHER || crypto.kdf != KDF {
+        return Err(KeystoreError("only v3, scrypt and aes-128-ctr keystores".to_string()).into());
+    }
+    let params = &crypto.kdfparams;
+    let log_n = params.n.trailing_zeros();
+    if params.dklen != DKLEN || params.n < 2 || params.n != 1 << log_n {
+        return Err(KeystoreError("unsupported scrypt parameters".to_string()).into());
+    }
+    if log_n > MAX_SCRYPT_LOG_N || params.r > MAX_SCRYPT_R || params.p > MAX_SCRYPT_P {
+        return Err(KeystoreError(format!(
+            "scrypt parameters above n=2^{}, r={}, p={}",
+            MAX_SCRYPT_LOG_N, MAX_SCRYPT_R, MAX_SCRYPT_P
+        ))
+        .into());
+    }
+    let malformed = || KeystoreError("malformed keystore".to_string());
+    let salt = hex::decode(&params.salt).map_err(|_| malformed())?;
+    let iv = hex::decode(&crypto.cipherparams.iv).map_err(|_| malformed())?;
+    let mut payload = hex::decode(&crypto.ciphertext).map_err(|_| malformed())?;
+    let expected_mac = hex::decode(&crypto.mac).map_err(|_| malformed())?;
+
+    let mut derived = derive(passphrase, &salt, log_n as u8, params.r, params.p)?;
+    if mac(&derived, &payload).to_vec() != expected_mac {
+        derived.zeroize();
+        return Err(KeystoreError("wrong passphrase or corrupted keystore".to_string()).into());
+    }
+    let cipher = Aes128Ctr::new_from_slices(&derived[0..16], &iv);
+    derived.zeroize();
+    let mut cipher = cipher.map_err(|e| KeystoreError(e.to_string()))?;
+    cipher.apply_keystream(&mut payload);
+
+    let secret = match chain {
+        ChainFamily::Evm => Secret::from(hex::encode(&payload)),
+        ChainFamily::Sui => Secret::from(general_purpose::STANDARD.encode(&payload)),
+    };
+    payload.zeroize();
+
+    let (address, _) = key_details(chain, &secret)?;
+    if let Some(claimed) = &keystore.address {
+        let claimed = claimed.trim_start_matches("0x").to_lowercase();
+        if claimed != address.trim_start_matches("0x") {
+            return Err(KeystoreError("keystore address doesn't match its key".to_string()).into());
+        }
+    }
+    Ok(secret)
+}
+
+fn payload_bytes(chain: &ChainFamily, secret: &Secret) -> ResultE<Vec<u8>> {
+    let decoded = match chain {
+        ChainFamily::Evm => hex::decode(secret.expose().trim_start_matches("0x")).ok(),
+        ChainFamily::Sui => general_purpose::STANDARD.decode(secret.expose()).ok(),
+    };
+    decoded.ok_or(KeystoreError("malformed private key".to_string()).into())
+}
+
+fn derive(passphrase: &Secret, salt: &[u8], log_n: u8, r: u32, p: u32) -> ResultE<[u8; DKLEN]> {
+    let params = scrypt::Params::new(log_n, r, p, DKLEN)
+        .map_err(|_| KeystoreError("unsupported scrypt parameters".to_string()))?;
+    let mut derived = [0u8; DKLEN];
+    scrypt::scrypt(passphrase.expose().as_bytes(), salt, &params, &mut derived)
+        .map_err(|e| KeystoreError(e.to_string()))?;
+    Ok(derived)
+}
+
+fn mac(derived: &[u8; DKLEN], cyphered: &[u8]) -> [u8; 32] {
+    let mut input = derived[16..32].to_vec();
+    input.extend_from_slice(cyphered);
+    let mac = keccak256(&input);
+    input.zeroize();
+    mac
+}
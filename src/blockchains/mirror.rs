@@ -0,0 +1,80 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::errors::blockchain::BlockchainBackendError;
+
+use super::chain::NFTsRepository;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+/// Contracts every registration is anchored on, possibly on different
+/// chains. A registration is complete once it is on all the required ones;
+/// the rest are minted on a best effort basis.
+#[derive(Clone)]
+pub struct MirrorSet {
+    backends: Vec<Box<dyn NFTsRepository + Sync + Send>>,
+    required: HashSet<u16>,
+}
+
+impl fmt::Debug for MirrorSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MirrorSet contracts: {:?} required: {:?}",
+            self.contract_ids(),
+            self.required
+        )
+    }
+}
+
+impl MirrorSet {
+    /// Fails if two backends serve the same contract, or a required
+    /// contract is none of theirs.
+    pub fn new(
+        backends: Vec<Box<dyn NFTsRepository + Sync + Send>>,
+        required: Vec<u16>,
+    ) -> ResultE<MirrorSet> {
+        let mut ids = HashSet::new();
+        for backend in &backends {
+            if !ids.insert(backend.contract_id()) {
+                let mssag = format!("contract {} is mirrored twice", backend.contract_id());
+                return Err(BlockchainBackendError(mssag).into());
+            }
+        }
+        if required.is_empty() {
+            return Err(
+                BlockchainBackendError("no required contracts to mirror".to_string()).into(),
+            );
+        }
+        if let Some(unknown) = required.iter().find(|id| !ids.contains(id)) {
+            let mssag = format!("contract {} is required, not served", unknown);
+            return Err(BlockchainBackendError(mssag).into());
+        }
+        Ok(MirrorSet {
+            backends,
+            required: required.into_iter().collect(),
+        })
+    }
+
+    /// In the order given, the registration is minted on them in turn.
+    pub fn contract_ids(&self) -> Vec<u16> {
+        self.backends
+            .iter()
+            .map(|backend| backend.contract_id())
+            .collect()
+    }
+
+    pub fn backends(&self) -> &Vec<Box<dyn NFTsRepository + Sync + Send>> {
+        &self.backends
+    }
+
+    pub fn backend(&self, contract_id: &u16) -> Option<&Box<dyn NFTsRepository + Sync + Send>> {
+        self.backends
+            .iter()
+            .find(|backend| backend.contract_id() == *contract_id)
+    }
+
+    pub fn is_required(&self, contract_id: &u16) -> bool {
+        self.required.contains(contract_id)
+    }
+}
@@ -1,4 +1,13 @@
 pub mod chain;
+pub mod factory;
 pub mod ganache;
+pub mod hd_wallet;
+pub mod key_cache;
+pub mod router;
+pub mod keystore;
+pub mod mirror;
+pub mod shamir;
+pub mod signer;
 pub mod sui;
+pub mod user_keys;
 //pub mod cypher;
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::errors::blockchain::BlockchainBackendError;
+use crate::errors::contract::ContractNotWritableError;
+use crate::models::block_tx::BlockchainTx;
+
+use super::chain::NFTsRepository;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+pub const DEFAULT_DOWN_COOLDOWN_SECS: u64 = 300;
+// consecutive failed mints before a contract is left aside
+const FAILURES_BEFORE_DOWN: u32 = 3;
+
+/// How `ContractRouter` picks the contract of a new mint. Contracts left
+/// aside after failing are skipped by all of them but `ByCategory`, whose
+/// mints fail until the contract of their category is back.
+#[derive(Clone, Debug)]
+pub enum RoutingPolicy {
+    /// every contract in turn
+    RoundRobin,
+    /// by asset category, `default` for the rest
+    ByCategory {
+        categories: HashMap<String, u16>,
+        default: u16,
+    },
+    /// lowest average fiat cost of the last mints, contracts with none
+    /// measured yet first; costs need a price source at `NFTsService`
+    LeastCost,
+    /// the first one of the list
+    Failover(Vec<u16>),
+}
+
+#[derive(Default)]
+struct Health {
+    failures: u32,
+    down_until: Option<Instant>,
+    average_cost: Option<u128>,
+}
+
+#[derive(Default)]
+struct RouterState {
+    next: usize,
+    health: HashMap<u16, Health>,
+}
+
+/// Several contracts minting side by side, possibly on different chains.
+/// Clones share what has been learnt about each contract.
+#[derive(Clone)]
+pub struct ContractRouter {
+    backends: Vec<Box<dyn NFTsRepository + Sync + Send>>,
+    policy: RoutingPolicy,
+    cooldown: Duration,
+    state: Arc<Mutex<RouterState>>,
+}
+
+impl fmt::Debug for ContractRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ContractRouter contracts: {:?} policy: {:?}",
+            self.contract_ids(),
+            self.policy
+        )
+    }
+}
+
+impl ContractRouter {
+    /// Fails if two backends serve the same contract, or the policy names
+    /// a contract none of them serves.
+    pub fn new(
+        backends: Vec<Box<dyn NFTsRepository + Sync + Send>>,
+        policy: RoutingPolicy,
+    ) -> ResultE<ContractRouter> {
+        let mut ids = HashSet::new();
+        for backend in &backends {
+            if !ids.insert(backend.contract_id()) {
+                let mssag = format!("contract {} is served twice", backend.contract_id());
+                return Err(BlockchainBackendError(mssag).into());
+            }
+        }
+        if ids.is_empty() {
+            return Err(BlockchainBackendError("no contracts to route to".to_string()).into());
+        }
+        let named: Vec<u16> = match &policy {
+            RoutingPolicy::ByCategory {
+                categories,
+                default,
+            } => categories.values().chain([default]).cloned().collect(),
+            RoutingPolicy::Failover(order) => order.clone(),
+            _ => Vec::new(),
+        };
+        if let Some(unknown) = named.iter().find(|id| !ids.contains(id)) {
+            let mssag = format!("the policy routes to contract {}, not served", unknown);
+            return Err(BlockchainBackendError(mssag).into());
+        }
+        Ok(ContractRouter {
+            backends,
+            policy,
+            cooldown: Duration::from_secs(DEFAULT_DOWN_COOLDOWN_SECS),
+            state: Arc::new(Mutex::new(RouterState::default())),
+        })
+    }
+
+    /// How long a failing contract is left aside before trying it again.
+    pub fn with_cooldown(mut self, cooldown: Duration) -> ContractRouter {
+        self.cooldown = cooldown;
+        self
+    }
+
+    pub fn contract_ids(&self) -> Vec<u16> {
+        self.backends
+            .iter()
+            .map(|backend| backend.contract_id())
+            .collect()
+    }
+
+    pub fn backend(&self, contract_id: &u16) -> Option<&Box<dyn NFTsRepository + Sync + Send>> {
+        self.backends
+            .iter()
+            .find(|backend| backend.contract_id() == *contract_id)
+    }
+
+    pub fn is_up(&self, contract_id: &u16) -> bool {
+        let state = self.state.lock().unwrap();
+        ContractRouter::up(&state, contract_id)
+    }
+
+    fn up(state: &RouterState, contract_id: &u16) -> bool {
+        match state.health.get(contract_id).and_then(|h| h.down_until) {
+            Some(until) => Instant::now() >= until,
+            None => true,
+        }
+    }
+
+    /// The backend the next mint goes to.
+    pub fn pick(
+        &self,
+        category: &Option<String>,
+    ) -> ResultE<&Box<dyn NFTsRepository + Sync + Send>> {
+        let mut state = self.state.lock().unwrap();
+        let ids = self.contract_ids();
+        let picked = match &self.policy {
+            RoutingPolicy::ByCategory {
+                categories,
+                default,
+            } => {
+                let id = category
+                    .as_ref()
+                    .and_then(|cat| categories.get(cat))
+                    .unwrap_or(default);
+                //a category stays on its contract, no falling back
+                if !ContractRouter::up(&state, id) {
+                    let mssag = format!("contract {} of category {:?} is down", id, category);
+                    return Err(BlockchainBackendError(mssag).into());
+                }
+                Some(*id)
+            }
+            RoutingPolicy::Failover(order) => order
+                .iter()
+                .find(|id| ContractRouter::up(&state, id))
+                .cloned(),
+            RoutingPolicy::RoundRobin => {
+                let start = state.next;
+                let found = (0..ids.len())
+                    .map(|offset| (start + offset) % ids.len())
+                    .find(|pos| ContractRouter::up(&state, &ids[*pos]));
+                found.map(|pos| {
+                    state.next = pos + 1;
+                    ids[pos]
+                })
+            }
+            RoutingPolicy::LeastCost => ids
+                .iter()
+                .filter(|id| ContractRouter::up(&state, id))
+                .min_by_key(|id| {
+                    let cost = state.health.get(*id).and_then(|h| h.average_cost);
+                    (cost.is_some(), cost)
+                })
+                .cloned(),
+        };
+        match picked {
+            Some(id) => Ok(self.backend(&id).unwrap()),
+            None => Err(BlockchainBackendError("every contract is down".to_string()).into()),
+        }
+    }
+
+    /// Records a mint that went through, and its fiat cost if valued.
+    pub fn report_success(&self, tx: &BlockchainTx) {
+        let contract_id = match tx.contract_id() {
+            None => return,
+            Some(id) => id,
+        };
+        let cost = tx
+            .fiat_cost()
+            .as_ref()
+            .and_then(|val| val.value_units().ok());
+        let mut state = self.state.lock().unwrap();
+        let health = state.health.entry(*contract_id).or_default();
+        health.failures = 0;
+        health.down_until = None;
+        if let Some(cost) = cost {
+            //the last mints weigh the most
+            health.average_cost = Some(match health.average_cost {
+                None => cost,
+                Some(avg) => (avg * 3 + cost) / 4,
+            });
+        }
+    }
+
+    /// Records a failed mint. Contracts that don't take mints any more are
+    /// left aside at once, the rest after a few failures in a row.
+    pub fn report_failure(&self, contract_id: &u16, error: &(dyn std::error::Error + Sync + Send)) {
+        let mut state = self.state.lock().unwrap();
+        let health = state.health.entry(*contract_id).or_default();
+        health.failures += 1;
+        if error.is::<ContractNotWritableError>() || health.failures >= FAILURES_BEFORE_DOWN {
+            health.down_until = Some(Instant::now() + self.cooldown);
+            //once back, a single failure is enough to leave it aside again
+            health.failures = FAILURES_BEFORE_DOWN - 1;
+            tracing::warn!("contract {} left aside: {}", contract_id, error);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use fastcrypto::hash::{HashFunction, Sha256};
+use zeroize::Zeroize;
+
+use crate::errors::backup::BackupShareError;
+use crate::models::secret::Secret;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+// shr1:<label>:<set id>:<threshold>:<index>:<payload>:<checksum>, hex but the label
+const SHARE_PREFIX: &str = "shr1";
+const SET_ID_LEN: usize = 4;
+const CHECKSUM_LEN: usize = 4;
+
+struct Share {
+    label: String,
+    set_id: String,
+    threshold: u8,
+    index: u8,
+    payload: Vec<u8>,
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.payload.zeroize();
+    }
+}
+
+/// Splits `secret` into `count` Shamir shares over GF(256), any `threshold`
+/// of them rebuild it and fewer tell nothing about it. Shares are plain
+/// text, to be printed or written down; `label` says what they belong to.
+pub fn split_secret(
+    secret: &Secret,
+    label: &String,
+    threshold: u8,
+    count: u8,
+) -> ResultE<Vec<Secret>> {
+    if threshold < 2 || threshold > count {
+        let mssag = format!("{} of {} shares can't be split", threshold, count);
+        return Err(BackupShareError(mssag).into());
+    }
+    if label.is_empty() || label.contains(':') {
+        return Err(BackupShareError("labels can't be empty nor hold ':'".to_string()).into());
+    }
+    if secret.is_empty() {
+        return Err(BackupShareError("nothing to split".to_string()).into());
+    }
+    let set_id = hex::encode(rand::random::<[u8; SET_ID_LEN]>());
+    let plain = secret.expose().as_bytes();
+
+    let mut payloads = vec![Vec::with_capacity(plain.len()); count as usize];
+    let mut coefficients = vec![0u8; threshold as usize];
+    for byte in plain {
+        coefficients[0] = *byte;
+        for coefficient in coefficients.iter_mut().skip(1) {
+            *coefficient = rand::random();
+        }
+        for (i, payload) in payloads.iter_mut().enumerate() {
+            payload.push(evaluate(&coefficients, i as u8 + 1));
+        }
+    }
+    coefficients.zeroize();
+
+    let shares = payloads
+        .into_iter()
+        .enumerate()
+        .map(|(i, payload)| {
+            let share = Share {
+                label: label.to_owned(),
+                set_id: set_id.to_owned(),
+                threshold,
+                index: i as u8 + 1,
+                payload,
+            };
+            encode(&share)
+        })
+        .collect();
+    Ok(shares)
+}
+
+/// Rebuilds the secret from shares made by `split_secret` for `label`.
+/// Typos, shares from another split and too few shares are all refused.
+pub fn combine_shares(label: &String, shares: &[Secret]) -> ResultE<Secret> {
+    let mut parsed: Vec<Share> = Vec::new();
+    for share in shares {
+        let share = decode(share)?;
+        if share.label != *label {
+            let mssag = format!("share {} belongs to {}", share.index, share.label);
+            return Err(BackupShareError(mssag).into());
+        }
+        if let Some(first) = parsed.first() {
+            if share.set_id != first.set_id
+                || share.threshold != first.threshold
+                || share.payload.len() != first.payload.len()
+            {
+                let mssag = format!("share {} comes from another split", share.index);
+                return Err(BackupShareError(mssag).into());
+            }
+        }
+        if parsed.iter().any(|other| other.index == share.index) {
+            continue;
+        }
+        parsed.push(share);
+    }
+    let threshold = match parsed.first() {
+        None => return Err(BackupShareError("no shares".to_string()).into()),
+        Some(first) => first.threshold as usize,
+    };
+    if parsed.len() < threshold {
+        let mssag = format!("{} shares needed, {} given", threshold, parsed.len());
+        return Err(BackupShareError(mssag).into());
+    }
+    parsed.truncate(threshold);
+
+    let xs: Vec<u8> = parsed.iter().map(|share| share.index).collect();
+    let mut plain = Vec::with_capacity(parsed[0].payload.len());
+    for position in 0..parsed[0].payload.len() {
+        let mut ys: Vec<u8> = parsed.iter().map(|share| share.payload[position]).collect();
+        plain.push(interpolate_at_zero(&xs, &ys));
+        ys.zeroize();
+    }
+    match String::from_utf8(plain) {
+        Ok(text) => Ok(Secret::from(text)),
+        Err(e) => {
+            e.into_bytes().zeroize();
+            Err(BackupShareError("shares don't rebuild a secret".to_string()).into())
+        }
+    }
+}
+
+fn encode(share: &Share) -> Secret {
+    let body = format!(
+        "{}:{}:{}:{}:{}:{}",
+        SHARE_PREFIX,
+        share.label,
+        share.set_id,
+        share.threshold,
+        share.index,
+        hex::encode(&share.payload)
+    );
+    let checksum = checksum(&body);
+    Secret::from(format!("{}:{}", body, checksum))
+}
+
+fn decode(share: &Secret) -> ResultE<Share> {
+    let malformed = || BackupShareError("malformed share".to_string());
+    let text = share.expose().trim();
+    let (body, checksum_found) = text.rsplit_once(':').ok_or(malformed())?;
+    if checksum(&body.to_string()) != checksum_found.to_lowercase() {
+        return Err(
+            BackupShareError("share checksum mismatch, check for typos".to_string()).into(),
+        );
+    }
+    let parts: Vec<&str> = body.split(':').collect();
+    if parts.len() != 6 || parts[0] != SHARE_PREFIX {
+        return Err(malformed().into());
+    }
+    let share = Share {
+        label: parts[1].to_string(),
+        set_id: parts[2].to_lowercase(),
+        threshold: parts[3].parse().map_err(|_| malformed())?,
+        index: parts[4].parse().map_err(|_| malformed())?,
+        payload: hex::decode(parts[5]).map_err(|_| malformed())?,
+    };
+    if share.index == 0 || share.threshold < 2 || share.payload.is_empty() {
+        return Err(malformed().into());
+    }
+    Ok(share)
+}
+
+fn checksum(body: &String) -> String {
+    let digest = Sha256::digest(body.to_lowercase().as_bytes()).digest;
+    hex::encode(&digest[0..CHECKSUM_LEN])
+}
+
+fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0, |acc, coefficient| gf_mul(acc, x) ^ coefficient)
+}
+
+// lagrange at x = 0, subtraction is xor in GF(256)
+fn interpolate_at_zero(xs: &[u8], ys: &[u8]) -> u8 {
+    let mut value = 0u8;
+    for (i, xi) in xs.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, xj) in xs.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_mul(*xj, gf_inv(xj ^ xi)));
+            }
+        }
+        value ^= gf_mul(ys[i], basis);
+    }
+    value
+}
+
+// AES field, x^8 + x^4 + x^3 + x + 1; no branches on the operands
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        a = (a << 1) ^ (0x1b & 0u8.wrapping_sub(a >> 7));
+        b >>= 1;
+    }
+    product
+}
+
+fn gf_inv(a: u8) -> u8 {
+    // a^254 == a^-1
+    let mut result = 1u8;
+    let mut base = a;
+    for bit in 0..8 {
+        if (254u8 >> bit) & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+    }
+    result
+}
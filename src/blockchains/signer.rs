@@ -0,0 +1,547 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::{KeySpec, MessageType, SigningAlgorithmSpec};
+use base64::engine::general_purpose;
+use base64::Engine;
+use fastcrypto::hash::{Blake2b256, HashFunction, Sha256};
+use lib_config::config::Config;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId, Signature as EcdsaSignature};
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use sui_keys::keystore::{AccountKeystore, InMemKeystore, Keystore};
+use sui_types::base_types::SuiAddress;
+use sui_types::crypto::{EncodeDecodeBase64, SuiKeyPair};
+use web3::signing::{keccak256, Key, Signature, SigningError};
+use web3::transports::Http;
+use web3::types::{Address, SignedTransaction, TransactionParameters, H256};
+use web3::Web3;
+use zeroize::Zeroize;
+
+use crate::errors::nft::HydrateMasterSecretKeyError;
+use crate::errors::signer::SignerError;
+use crate::models::contract::Contract;
+use crate::models::keypair::ChainFamily;
+use crate::models::secret::Secret;
+
+use super::key_cache::KeyCache;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+// Sui prefixes secp256k1 signatures and public keys with this flag
+const SUI_SECP256K1_FLAG: u8 = 0x01;
+
+/// Signs digests on behalf of an account, so the chain backends never
+/// handle the private key themselves.
+///
+/// Evm: the digest is the keccak hash of the transaction and the answer is
+/// `r || s || recovery id`, 65 bytes with a low `s`.
+/// Sui: the digest is the blake2b hash of the intent message and the answer
+/// is a serialized Sui signature, `flag || signature || public key`.
+#[async_trait]
+pub trait Signer: Send + Sync + CloneBoxSigner {
+    fn chain_family(&self) -> ChainFamily;
+    fn address(&self) -> &String;
+    async fn sign_digest(&self, digest: &[u8; 32]) -> ResultE<Vec<u8>>;
+}
+
+impl fmt::Debug for dyn Signer + Sync + Send {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signer {} {}", self.chain_family(), self.address())
+    }
+}
+
+pub trait CloneBoxSigner {
+    fn clone_box(&self) -> Box<dyn Signer + Sync + Send>;
+}
+
+impl<T> CloneBoxSigner for T
+where
+    T: 'static + Signer + Clone + Send + Sync,
+{
+    fn clone_box(&self) -> Box<dyn Signer + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Signer + Send + Sync> {
+    fn clone(&self) -> Box<dyn Signer + Send + Sync> {
+        self.clone_box()
+    }
+}
+
+/// The signer of a contract owner: KMS when the contract records an
+/// asymmetric key for it, its encrypted secret otherwise, decrypted
+/// through `key_cache`.
+pub async fn contract_owner_signer(
+    contract: &Contract,
+    family: &ChainFamily,
+    config: &Config,
+    key_cache: &KeyCache,
+) -> ResultE<Box<dyn Signer + Sync + Send>> {
+    if let Some(key_id) = contract.owner_signer_key_id() {
+        let signer = KmsSigner::new(key_id, family, config).await?;
+        return Ok(Box::new(signer));
+    }
+    let secret = contract
+        .owner_secret()
+        .clone()
+        .ok_or(HydrateMasterSecretKeyError {})?;
+    let address = contract.owner_address().clone().ok_or(SignerError(format!(
+        "contract {} has no owner address",
+        contract.id()
+    )))?;
+    //secrets stored before the key id was recorded are under the env key
+    let key_id = contract
+        .owner_secret_key_id()
+        .clone()
+        .unwrap_or(config.env_vars().kms_key_id().unwrap());
+    let signer = EncryptedKeySigner::new(family, &address, &secret, &key_id, config)
+        .with_key_cache(key_cache);
+    Ok(Box::new(signer))
+}
+
+/// Address of an owner secret in the format `EncryptedKeySigner` takes:
+/// hex for Evm, a base64 bincode `Keystore` holding a single key for Sui.
+pub fn owner_secret_address(family: &ChainFamily, secret: &Secret) -> ResultE<String> {
+    match family {
+        ChainFamily::Evm => Ok(LocalKeySigner::new(family, secret)?.address().to_owned()),
+        ChainFamily::Sui => {
+            let mut raw = general_purpose::STANDARD_NO_PAD
+                .decode(secret.expose())
+                .map_err(|_| HydrateMasterSecretKeyError {})?;
+            let res = match bincode::deserialize::<Keystore>(&raw[..]) {
+                Err(_) => Err(HydrateMasterSecretKeyError {}.into()),
+                Ok(keystore) => match keystore.addresses().as_slice() {
+                    [address] => Ok(address.to_string()),
+                    _ => Err(SignerError("the keystore must hold a single key".to_string()).into()),
+                },
+            };
+            raw.zeroize();
+            res
+        }
+    }
+}
+
+fn evm_address(public_key: &PublicKey) -> String {
+    let uncompressed = public_key.serialize_uncompressed();
+    let address = Address::from_slice(&keccak256(&uncompressed[1..])[12..]);
+    format!("{:?}", address)
+}
+
+/// Address that produced an EVM signature (r || s || recovery id) of `digest`.
+pub fn recover_evm_address(digest: &[u8; 32], signature: &[u8]) -> ResultE<String> {
+    if signature.len() != 65 {
+        return Err(SignerError("not an evm signature".to_string()).into());
+    }
+    let recovery_id = RecoveryId::from_i32(signature[64] as i32)?;
+    let recoverable = RecoverableSignature::from_compact(&signature[0..64], recovery_id)?;
+    let public_key = Secp256k1::new().recover_ecdsa(&Message::from_slice(digest)?, &recoverable)?;
+    Ok(evm_address(&public_key))
+}
+
+fn sui_secp256k1_address(public_key: &PublicKey) -> String {
+    let mut bytes = vec![SUI_SECP256K1_FLAG];
+    bytes.extend_from_slice(&public_key.serialize());
+    format!("0x{}", hex::encode(Blake2b256::digest(&bytes).digest))
+}
+
+fn sign_recoverable(secret_key: &SecretKey, digest: &[u8; 32]) -> ResultE<Vec<u8>> {
+    let message = Message::from_slice(digest)?;
+    let (recovery_id, compact) = Secp256k1::new()
+        .sign_ecdsa_recoverable(&message, secret_key)
+        .serialize_compact();
+    let mut signature = compact.to_vec();
+    signature.push(recovery_id.to_i32() as u8);
+    Ok(signature)
+}
+
+fn sign_with_keystore(
+    keystore: &Keystore,
+    address: &String,
+    digest: &[u8; 32],
+) -> ResultE<Vec<u8>> {
+    let address = SuiAddress::from_str(address.as_str())?;
+    let signature = keystore
+        .sign_hashed(&address, digest)
+        .map_err(|e| SignerError(e.to_string()))?;
+    Ok(signature.as_ref().to_vec())
+}
+
+/// Key held in memory, for tests and keys derived on the fly. `secret` is
+/// hex for Evm and a base64 `SuiKeyPair` for Sui, as `DerivedKey::secret()`.
+#[derive(Clone, Debug)]
+pub struct LocalKeySigner {
+    family: ChainFamily,
+    address: String,
+    secret: Secret,
+}
+
+impl LocalKeySigner {
+    pub fn new(family: &ChainFamily, secret: &Secret) -> ResultE<LocalKeySigner> {
+        let address = match family {
+            ChainFamily::Evm => {
+                let secret_key = SecretKey::from_str(secret.expose().as_str())?;
+                evm_address(&PublicKey::from_secret_key(&Secp256k1::new(), &secret_key))
+            }
+            ChainFamily::Sui => {
+                let keypair = SuiKeyPair::decode_base64(secret.expose().as_str())
+                    .map_err(|e| SignerError(e.to_string()))?;
+                SuiAddress::from(&keypair.public()).to_string()
+            }
+        };
+        Ok(LocalKeySigner {
+            family: family.to_owned(),
+            address,
+            secret: secret.to_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for LocalKeySigner {
+    fn chain_family(&self) -> ChainFamily {
+        self.family.clone()
+    }
+    fn address(&self) -> &String {
+        &self.address
+    }
+    async fn sign_digest(&self, digest: &[u8; 32]) -> ResultE<Vec<u8>> {
+        match self.family {
+            ChainFamily::Evm => {
+                sign_recoverable(&SecretKey::from_str(self.secret.expose().as_str())?, digest)
+            }
+            ChainFamily::Sui => {
+                let keypair = SuiKeyPair::decode_base64(self.secret.expose().as_str())
+                    .map_err(|e| SignerError(e.to_string()))?;
+                let mut keystore = Keystore::InMem(InMemKeystore::default());
+                keystore
+                    .add_key(keypair)
+                    .map_err(|e| SignerError(e.to_string()))?;
+                sign_with_keystore(&keystore, &self.address, digest)
+            }
+        }
+    }
+}
+
+/// Key stored encrypted with a KMS symmetric key, decrypted for every
+/// signature, or kept in a `KeyCache` for a while when given one. Evm
+/// secrets are hex, Sui secrets are a base64 bincode `Keystore`.
+#[derive(Clone, Debug)]
+pub struct EncryptedKeySigner {
+    family: ChainFamily,
+    address: String,
+    secret_cyphered: Secret,
+    key_id: String,
+    config: Config,
+    key_cache: Option<KeyCache>,
+}
+
+impl EncryptedKeySigner {
+    pub fn new(
+        family: &ChainFamily,
+        address: &String,
+        secret_cyphered: &Secret,
+        key_id: &String,
+        config: &Config,
+    ) -> EncryptedKeySigner {
+        EncryptedKeySigner {
+            family: family.to_owned(),
+            address: address.to_owned(),
+            secret_cyphered: secret_cyphered.to_owned(),
+            key_id: key_id.to_owned(),
+            config: config.to_owned(),
+            key_cache: None,
+        }
+    }
+
+    pub fn with_key_cache(mut self, key_cache: &KeyCache) -> EncryptedKeySigner {
+        self.key_cache = Some(key_cache.clone());
+        self
+    }
+
+    fn sign_with_secret(&self, secret: &Secret, digest: &[u8; 32]) -> ResultE<Vec<u8>> {
+        match self.family {
+            ChainFamily::Evm => match SecretKey::from_str(secret.expose().as_str()) {
+                Err(_) => Err(HydrateMasterSecretKeyError {}.into()),
+                Ok(secret_key) => sign_recoverable(&secret_key, digest),
+            },
+            ChainFamily::Sui => {
+                let mut raw = general_purpose::STANDARD_NO_PAD
+                    .decode(secret.expose())
+                    .map_err(|_| HydrateMasterSecretKeyError {})?;
+                let res = match bincode::deserialize::<Keystore>(&raw[..]) {
+                    Err(_) => Err(HydrateMasterSecretKeyError {}.into()),
+                    Ok(keystore) => sign_with_keystore(&keystore, &self.address, digest),
+                };
+                raw.zeroize();
+                res
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for EncryptedKeySigner {
+    fn chain_family(&self) -> ChainFamily {
+        self.family.clone()
+    }
+    fn address(&self) -> &String {
+        &self.address
+    }
+    async fn sign_digest(&self, digest: &[u8; 32]) -> ResultE<Vec<u8>> {
+        let secret_op = match &self.key_cache {
+            None => {
+                self.secret_cyphered
+                    .uncypher(&self.key_id, &self.config)
+                    .await
+            }
+            Some(cache) => {
+                cache
+                    .uncypher(&self.secret_cyphered, &self.key_id, &self.config)
+                    .await
+            }
+        };
+        let secret = secret_op.map_err(|_| HydrateMasterSecretKeyError {})?;
+        self.sign_with_secret(&secret, digest)
+    }
+}
+
+/// Key living in KMS as an asymmetric `ECC_SECG_P256K1` key: only digests
+/// are sent over and the private key never leaves KMS. On Sui it signs as a
+/// secp256k1 account.
+#[derive(Clone, Debug)]
+pub struct KmsSigner {
+    family: ChainFamily,
+    address: String,
+    key_id: String,
+    public_key: PublicKey,
+    client: aws_sdk_kms::Client,
+}
+
+impl KmsSigner {
+    pub async fn new(key_id: &String, family: &ChainFamily, config: &Config) -> ResultE<KmsSigner> {
+        let client = aws_sdk_kms::Client::new(config.aws_config());
+        let output = client.get_public_key().key_id(key_id).send().await?;
+        if output.key_spec() != Some(&KeySpec::EccSecgP256K1) {
+            return Err(SignerError(format!("kms key {} is not ECC_SECG_P256K1", key_id)).into());
+        }
+        let der = output
+            .public_key()
+            .ok_or(SignerError(format!("kms key {} has no public key", key_id)))?;
+        let public_key = public_key_from_spki(der.as_ref())?;
+
+        let address = match family {
+            ChainFamily::Evm => evm_address(&public_key),
+            ChainFamily::Sui => sui_secp256k1_address(&public_key),
+        };
+        Ok(KmsSigner {
+            family: family.to_owned(),
+            address,
+            key_id: key_id.to_owned(),
+            public_key,
+            client,
+        })
+    }
+
+    pub fn key_id(&self) -> &String {
+        &self.key_id
+    }
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    // compact r || s, KMS may answer a high s that neither chain accepts
+    async fn kms_sign(&self, digest: &[u8; 32]) -> ResultE<[u8; 64]> {
+        let output = self
+            .client
+            .sign()
+            .key_id(&self.key_id)
+            .message(Blob::new(digest.to_vec()))
+            .message_type(MessageType::Digest)
+            .signing_algorithm(SigningAlgorithmSpec::EcdsaSha256)
+            .send()
+            .await?;
+        let der = output.signature().ok_or(SignerError(format!(
+            "kms key {} sent no signature",
+            self.key_id
+        )))?;
+        let mut signature = EcdsaSignature::from_der(der.as_ref())?;
+        signature.normalize_s();
+        Ok(signature.serialize_compact())
+    }
+
+    // KMS doesn't give the recovery id, find the one giving back our key
+    fn recovery_id(&self, digest: &[u8; 32], compact: &[u8; 64]) -> ResultE<u8> {
+        let secp = Secp256k1::new();
+        let message = Message::from_slice(digest)?;
+        for id in 0..2 {
+            let recoverable =
+                RecoverableSignature::from_compact(compact, RecoveryId::from_i32(id)?)?;
+            if secp.recover_ecdsa(&message, &recoverable).ok() == Some(self.public_key) {
+                return Ok(id as u8);
+            }
+        }
+        Err(SignerError(format!("no recovery id matches kms key {}", self.key_id)).into())
+    }
+}
+
+// KMS answers a DER SubjectPublicKeyInfo ending with the uncompressed point
+fn public_key_from_spki(der: &[u8]) -> ResultE<PublicKey> {
+    if der.len() < 65 || der[der.len() - 65] != 0x04 {
+        return Err(SignerError("unexpected kms public key encoding".to_string()).into());
+    }
+    Ok(PublicKey::from_slice(&der[der.len() - 65..])?)
+}
+
+#[async_trait]
+impl Signer for KmsSigner {
+    fn chain_family(&self) -> ChainFamily {
+        self.family.clone()
+    }
+    fn address(&self) -> &String {
+        &self.address
+    }
+    #[tracing::instrument()]
+    async fn sign_digest(&self, digest: &[u8; 32]) -> ResultE<Vec<u8>> {
+        match self.family {
+            ChainFamily::Evm => {
+                let compact = self.kms_sign(digest).await?;
+                let mut signature = compact.to_vec();
+                signature.push(self.recovery_id(digest, &compact)?);
+                Ok(signature)
+            }
+            ChainFamily::Sui => {
+                //sui secp256k1 keys sign the sha256 of the intent digest
+                let prehashed = Sha256::digest(digest).digest;
+                let compact = self.kms_sign(&prehashed).await?;
+                let mut signature = vec![SUI_SECP256K1_FLAG];
+                signature.extend_from_slice(&compact);
+                signature.extend_from_slice(&self.public_key.serialize());
+                Ok(signature)
+            }
+        }
+    }
+}
+
+// web3 hashes the transaction and hands the hash to the key, which must
+// answer straight away. A first pass records that hash and a second one
+// returns the signature the `Signer` made of it meanwhile.
+#[derive(Clone)]
+struct DigestCapture {
+    address: Address,
+    digest: Arc<Mutex<Option<[u8; 32]>>>,
+}
+
+impl Key for DigestCapture {
+    fn sign(&self, message: &[u8], _chain_id: Option<u64>) -> Result<Signature, SigningError> {
+        self.sign_message(message)
+    }
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, SigningError> {
+        let digest: [u8; 32] = message
+            .try_into()
+            .map_err(|_| SigningError::InvalidMessage)?;
+        *self.digest.lock().unwrap() = Some(digest);
+        //thrown away with the rest of this pass
+        Ok(Signature {
+            v: 0,
+            r: H256::zero(),
+            s: H256::zero(),
+        })
+    }
+    fn address(&self) -> Address {
+        self.address
+    }
+}
+
+struct PresignedKey {
+    address: Address,
+    digest: [u8; 32],
+    signature: Vec<u8>,
+}
+
+impl PresignedKey {
+    fn parts(&self, message: &[u8]) -> Result<(H256, H256, u64), SigningError> {
+        if message != self.digest {
+            return Err(SigningError::InvalidMessage);
+        }
+        Ok((
+            H256::from_slice(&self.signature[0..32]),
+            H256::from_slice(&self.signature[32..64]),
+            self.signature[64] as u64,
+        ))
+    }
+}
+
+impl Key for PresignedKey {
+    fn sign(&self, message: &[u8], chain_id: Option<u64>) -> Result<Signature, SigningError> {
+        let (r, s, recovery_id) = self.parts(message)?;
+        let v = match chain_id {
+            Some(id) => recovery_id + 35 + id * 2,
+            None => recovery_id + 27,
+        };
+        Ok(Signature { v, r, s })
+    }
+    fn sign_message(&self, message: &[u8]) -> Result<Signature, SigningError> {
+        let (r, s, recovery_id) = self.parts(message)?;
+        Ok(Signature {
+            v: recovery_id,
+            r,
+            s,
+        })
+    }
+    fn address(&self) -> Address {
+        self.address
+    }
+}
+
+/// Signs an EVM transaction with any `Signer`. `nonce`, `gas_price` and
+/// `chain_id` must be set, so both passes sign the very same transaction.
+pub async fn sign_evm_transaction(
+    web3: &Web3<Http>,
+    signer: &(dyn Signer + Sync + Send),
+    tx: TransactionParameters,
+) -> ResultE<SignedTransaction> {
+    if signer.chain_family() != ChainFamily::Evm {
+        return Err(SignerError(format!(
+            "{} signer can't sign evm txs",
+            signer.chain_family()
+        ))
+        .into());
+    }
+    if tx.nonce.is_none() || tx.gas_price.is_none() || tx.chain_id.is_none() {
+        return Err(SignerError("nonce, gas price and chain id must be set".to_string()).into());
+    }
+    let address = Address::from_str(signer.address().as_str())?;
+
+    let capture = DigestCapture {
+        address,
+        digest: Arc::new(Mutex::new(None)),
+    };
+    web3.accounts()
+        .sign_transaction(tx.clone(), capture.clone())
+        .await?;
+    let digest = capture
+        .digest
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or(SignerError("transaction digest not captured".to_string()))?;
+
+    let signature = signer.sign_digest(&digest).await?;
+    if signature.len() != 65 {
+        return Err(SignerError(format!(
+            "unexpected evm signature length {}",
+            signature.len()
+        ))
+        .into());
+    }
+    let presigned = PresignedKey {
+        address,
+        digest,
+        signature,
+    };
+    Ok(web3.accounts().sign_transaction(tx, presigned).await?)
+}
@@ -1,57 +1,93 @@
 use async_trait::async_trait;
-use base64::engine::general_purpose;
-use base64::Engine;
 use chrono::Utc;
-use lib_config::infra::uncypher_with_secret_key;
-use lib_config::{config::Config, environment::DEV_ENV};
+use lib_config::config::Config;
 use log::error;
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
 use url::Url;
 use uuid::Uuid;
 
+use crate::models::amount::Amount;
 use crate::models::block_tx::MintingStatus;
-use crate::models::keypair::KeyPair;
+use crate::models::keypair::{ChainFamily, KeyPair};
+use crate::models::secret::Secret;
 use crate::{
-    errors::block_tx::BlockchainTxError,
+    errors::{
+        block_tx::BlockchainTxError, contract::ContractNotWritableError,
+        deployment::DeploymentError, nft::NftUserAddressMalformedError,
+    },
     models::block_tx::BlockchainTx,
     repositories::{
         blockchain::BlockchainRepo, blockchain::BlockchainRepository, contract::ContractRepo,
-        contract::ContractRepository,
+        contract::ContractRepository, user_data_keys::UserDataKeyRepo,
     },
 };
 
 const CONTRACT_METHOD_MINTING: &'static str = "add_hash";
-
-use super::chain::{ContractContentInfo, NFTsRepository};
+const CONTRACT_METHOD_MINTING_FOR: &'static str = "add_hash_for";
+const CONTRACT_MODULE: &'static str = "hasher";
+const CONTRACT_EVENT_HASH_ADDED: &'static str = "HashAdded";
+const EVENTS_PAGE_SIZE: usize = 50;
+pub const PUBLISH_GAS_BUDGET: u64 = 50000000;
+const UPGRADE_CAP_MODULE: &'static str = "package";
+const UPGRADE_CAP_STRUCT: &'static str = "UpgradeCap";
+pub const TRULY_BUILD_DIR: &'static str =
+    concat!(env!("CARGO_MANIFEST_DIR"), "/res/sui/truly/build/truly");
+
+use super::chain::{node_url, ContentState, ContractContentInfo, NFTsRepository};
+use super::key_cache::KeyCache;
+use super::signer::{contract_owner_signer, Signer};
+use super::user_keys::UserDataKeys;
 
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
-use shared_crypto::intent::Intent;
-use sui_json_rpc_types::{SuiObjectDataOptions, SuiParsedData, SuiTransactionBlockResponseOptions};
+use fastcrypto::hash::{Blake2b256, HashFunction};
+use fastcrypto::traits::ToFromBytes;
+use shared_crypto::intent::{Intent, IntentMessage};
+use sui_json_rpc_types::{
+    EventFilter, ObjectChange, SuiObjectDataOptions, SuiParsedData, SuiTransactionBlockResponse,
+    SuiTransactionBlockResponseOptions,
+};
 use sui_keys::keystore::{AccountKeystore, Keystore};
 use sui_sdk::{
     json::SuiJsonValue,
     rpc_types::SuiTransactionBlockEffectsAPI,
     types::{
         base_types::{ObjectID, SuiAddress},
-        transaction::Transaction,
+        transaction::{Transaction, TransactionData},
     },
-    SuiClientBuilder,
+    SuiClient, SuiClientBuilder,
 };
+use sui_types::crypto::{get_key_pair, Ed25519KeyPair, EncodeDecodeBase64, Signature, SuiKeyPair};
+use sui_types::move_package::{MovePackage, UpgradePolicy};
+use sui_types::parse_sui_struct_tag;
 use sui_types::quorum_driver_types::ExecuteTransactionRequestType;
-
-use zeroize::Zeroize;
+use sui_types::{MOVE_STDLIB_OBJECT_ID, SUI_FRAMEWORK_ADDRESS, SUI_FRAMEWORK_OBJECT_ID};
 
 #[derive(Clone, Debug)]
 pub struct SuiBlockChain {
     url: Url,
     contract_address: String,
     contract_owner_address: String,
-    contract_owner_secret: String,
+    signer: Box<dyn Signer + Sync + Send>,
+    key_cache: KeyCache,
+    user_keys: UserDataKeys,
+    contracts_repo: ContractRepo,
     contract_owner_cash: String,
     config: Config,
     contract_id: u16,
+    keeper_mode: HashKeeperMode,
+}
+
+/// What happens to the `HashKeeper` created for every registration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum HashKeeperMode {
+    /// frozen, nobody owns it (default)
+    Frozen,
+    /// owned by the user's Sui address
+    TransferToUser,
 }
 
 impl SuiBlockChain {
@@ -61,35 +97,62 @@ impl SuiBlockChain {
         blockchains_repo: &BlockchainRepo,
     ) -> ResultE<SuiBlockChain> {
         let aux = conf.env_vars().contract_id().unwrap();
+        SuiBlockChain::for_contract(&aux, conf, contracts_repo, blockchains_repo).await
+    }
+
+    /// Backend for `contract_id` rather than the one in the env config.
+    pub async fn for_contract(
+        contract_id: &u16,
+        conf: &Config,
+        contracts_repo: &ContractRepo,
+        blockchains_repo: &BlockchainRepo,
+    ) -> ResultE<SuiBlockChain> {
+        let aux = contract_id.to_owned();
         let contract = contracts_repo.get_by_id(&aux).await?;
+        if !contract.status().accepts_reads() {
+            let mssag = format!("contract {} is {}", aux, contract.status());
+            return Err(ContractNotWritableError(mssag).into());
+        }
         let blockchain = blockchains_repo.get_by_id(contract.blockchain()).await?;
 
-        let blockchain_url;
-        if conf.env_vars().environment().unwrap() == DEV_ENV {
-            blockchain_url = blockchain.url().to_owned()
-        } else {
-            blockchain_url = Url::from_str(
-                format!(
-                    "{}/{}",
-                    blockchain.url().to_owned(),
-                    blockchain.api_key().to_owned()
-                )
-                .as_str(),
-            )
-            .unwrap();
-        }
+        let blockchain_url = node_url(&blockchain, conf);
+
+        let key_cache = KeyCache::default();
+        let signer = contract_owner_signer(&contract, &ChainFamily::Sui, conf, &key_cache).await?;
 
         Ok(SuiBlockChain {
             url: blockchain_url.to_owned(),
             contract_address: contract.address().clone().unwrap().to_owned(),
-            contract_owner_address: contract.owner_address().clone().unwrap().to_owned(),
-            contract_owner_secret: contract.owner_secret().clone().unwrap().to_owned(),
+            contract_owner_address: signer.address().to_owned(),
+            signer,
+            key_cache,
+            user_keys: UserDataKeys::new(UserDataKeyRepo::new(conf), conf),
+            contracts_repo: contracts_repo.clone(),
             contract_owner_cash: contract.owner_cash().clone().unwrap().to_owned(),
             config: conf.to_owned(),
             contract_id: aux.to_owned(),
+            keeper_mode: HashKeeperMode::Frozen,
         })
     }
 
+    pub fn with_keeper_mode(mut self, mode: HashKeeperMode) -> SuiBlockChain {
+        self.keeper_mode = mode;
+        self
+    }
+
+    /// Decrypted owner secrets, shared by the clones of this backend.
+    pub fn key_cache(&self) -> &KeyCache {
+        &self.key_cache
+    }
+
+    /// Signs with `signer` instead of the one recorded at the contract. The
+    /// gas coin must belong to its address.
+    pub fn with_signer(mut self, signer: Box<dyn Signer + Sync + Send>) -> SuiBlockChain {
+        self.contract_owner_address = signer.address().to_owned();
+        self.signer = signer;
+        self
+    }
+
     pub fn keystore_add_new_random_address(keystore: &mut Keystore) -> ResultE<String> {
         let (address, _phrase, _scheme) = keystore
             .generate_and_add_new_key(sui_types::crypto::SignatureScheme::ED25519, None, None)
@@ -112,15 +175,22 @@ impl NFTsRepository for SuiBlockChain {
     fn contract_id(&self) -> u16 {
         self.contract_id
     }
+    fn chain_family(&self) -> ChainFamily {
+        ChainFamily::Sui
+    }
     async fn add(
         &self,
         asset_id: &Uuid,
-        _: &KeyPair, //unused at SUI
+        user_key: &KeyPair,
         hash_file: &String,
         hash_algorithm: &String,
         _: &Option<u64>, //unused at SUI
         _: &u64,         //unused at SUI
     ) -> ResultE<BlockchainTx> {
+        //the status may have changed since this backend was built
+        let stored = self.contracts_repo.get_by_id(&self.contract_id).await?;
+        stored.check_mintable()?;
+
         let sui = SuiClientBuilder::default()
             .build(self.url.as_str())
             .await
@@ -129,11 +199,30 @@ impl NFTsRepository for SuiBlockChain {
         let my_address = SuiAddress::from_str(&self.contract_owner_address.as_str())?;
         let gas_object_id = ObjectID::from_str(&self.contract_owner_cash.as_str())?;
 
-        let package_object_id = ObjectID::from_str(self.contract_address.as_str())?;
-
-        let module = "hasher";
-
-        let function = CONTRACT_METHOD_MINTING;
+        //the latest version runs, types and events keep the first package id
+        let package_address = stored
+            .code_address()
+            .unwrap_or(self.contract_address.clone());
+        let package_object_id = ObjectID::from_str(package_address.as_str())?;
+
+        let module = CONTRACT_MODULE;
+
+        let mut function = CONTRACT_METHOD_MINTING;
+        let mut call_args = vec![
+            SuiJsonValue::from_str(&hash_file.as_str())?,
+            SuiJsonValue::from_str(&hash_algorithm.as_str())?,
+            SuiJsonValue::from_str(&asset_id.to_string().as_str())?,
+        ];
+        let mut recipient = None;
+        if self.keeper_mode == HashKeeperMode::TransferToUser {
+            let user_address = match SuiAddress::from_str(user_key.address().as_str()) {
+                Err(e) => return Err(NftUserAddressMalformedError(e.to_string()).into()),
+                Ok(addr) => addr,
+            };
+            function = CONTRACT_METHOD_MINTING_FOR;
+            call_args.push(SuiJsonValue::from_str(user_address.to_string().as_str())?);
+            recipient = Some(user_address.to_string());
+        }
         let gas_budget = 10000000;
 
         let transfer_tx_op = sui
@@ -144,11 +233,7 @@ impl NFTsRepository for SuiBlockChain {
                 module,
                 function,
                 vec![],
-                vec![
-                    SuiJsonValue::from_str(&hash_file.as_str())?,
-                    SuiJsonValue::from_str(&hash_algorithm.as_str())?,
-                    SuiJsonValue::from_str(&asset_id.to_string().as_str())?,
-                ],
+                call_args,
                 Some(gas_object_id), //None,
                 gas_budget,
             )
@@ -159,54 +244,7 @@ impl NFTsRepository for SuiBlockChain {
         }
         let transfer_tx = transfer_tx_op.ok().unwrap();
 
-        // Sign transaction
-        let kms_key_id = self.config.env_vars().kms_key_id().unwrap();
-        let transaction_response_op;
-        {
-            let mut encoded_secret_cyphered = self.contract_owner_secret.clone();
-            let mut encoded_secret_base64 = uncypher_with_secret_key(
-                encoded_secret_cyphered.clone(),
-                &kms_key_id,
-                &self.config,
-            )
-            .await?;
-            let mut contract_owner_secret =
-                general_purpose::STANDARD_NO_PAD.decode(&encoded_secret_base64)?;
-
-            let keystore: Keystore = bincode::deserialize(&contract_owner_secret[..]).unwrap();
-
-            let signature =
-                keystore.sign_secure(&my_address, &transfer_tx, Intent::sui_transaction())?;
-
-            transaction_response_op = sui
-                .quorum_driver_api()
-                .execute_transaction_block(
-                    Transaction::from_data(transfer_tx, Intent::sui_transaction(), vec![signature])
-                        .verify()?,
-                    SuiTransactionBlockResponseOptions::full_content(),
-                    Some(ExecuteTransactionRequestType::WaitForLocalExecution),
-                )
-                .await;
-            //clear memory with sensible data
-            drop(keystore);
-            contract_owner_secret.zeroize();
-            encoded_secret_base64.zeroize();
-            encoded_secret_cyphered.zeroize();
-        }
-        if let Err(err) = transaction_response_op {
-            error!("{}", err);
-            return Err(BlockchainTxError { 0: err.to_string() }.into());
-        }
-        let transaction_response = transaction_response_op.ok().unwrap();
-
-        if let Some(confirmation) = transaction_response.confirmed_local_execution {
-            if !confirmation {
-                return Err(BlockchainTxError {
-                    0: "failed transaction - confirmed local exec is false".to_string(),
-                }
-                .into());
-            }
-        }
+        let transaction_response = execute_signed(&sui, self.signer.as_ref(), transfer_tx).await?;
 
         println!("{:#?}", transaction_response);
 
@@ -246,26 +284,25 @@ impl NFTsRepository for SuiBlockChain {
 
         let tx_paylaod = BlockchainTx::new(
             asset_id.to_owned(),
+            None,
             MintingStatus::CompletedSuccessfully,
             Utc::now(),
             Utc::now(),
             Some(new_tx_address.to_string()), //Some(tx.digest),
             Some(epoch),                      //tx.block_number,
-            Some(gas_cost.to_string()),       //tx.gas_used,
-            None,                             //Some("".to_string()), // tx.effective_gas_price,
-            None,                             //Some(
-            //gas_cost, //wei_to_gwei(tx.gas_used.unwrap())
-            //    * wei_to_gwei(tx.effective_gas_price.unwrap_or_default()),
-            //),
-            Some("mist".to_string()),
-            Some(paid_from.to_string()), //Some(tx.from),
-            None,                        //Some("".to_string()), //tx.to,
+            None,                             //sui charges computation + storage, not gas units
+            None,                             //tx.effective_gas_price,
+            Some(Amount::mist(gas_cost.unsigned_abs())), //balance change is negative when paying
+            Some(paid_from.to_string()),      //Some(tx.from),
+            recipient,                        //keeper owner, none when frozen
             Some(self.contract_id),
+            Some(hash_file.to_owned()),
+            Some(hash_algorithm.to_owned()),
             None,
         );
         Ok(tx_paylaod)
     }
-    //TODO
+
     async fn get(&self, token: &String) -> ResultE<ContractContentInfo> {
         let sui = SuiClientBuilder::default()
             .build(self.url.as_str())
@@ -287,52 +324,453 @@ impl NFTsRepository for SuiBlockChain {
         }
         let objects = transaction_response_op.ok().unwrap();
 
-        println!("{:?}", objects.data);
-        #[derive(Deserialize, Debug)]
-        struct Auxi {
-            pub _hash: String,
-            pub _algorithm: String,
-            pub _truly_id: String,
-        }
-        impl<'a> From<&'a SuiParsedData> for Auxi {
-            fn from(data: &'a SuiParsedData) -> Self {
-                // implementation of the conversion logic goes here
-                let _ppp = format!("{:?}", data);
-                let _p: Auxi = serde_json::from_str(&_ppp).unwrap();
-
-                Auxi {
-                    _hash: "".to_string(),
-                    _algorithm: "".to_string(),
-                    _truly_id: "".to_string(),
+        let fields = match &objects.object()?.content {
+            Some(SuiParsedData::MoveObject(obj)) => obj.fields.clone().to_json_value(),
+            _ => {
+                return Err(BlockchainTxError {
+                    0: format!("object {} isn't a HashKeeper", token),
                 }
+                .into());
             }
-        }
+        };
 
-        // let _hash = objects.clone().data.unwrap().content.unwrap() ;// .first().content.unwrap().fields.unwrap();
-        let _res: Auxi = objects
-            .object()?
-            .content
-            .as_ref()
-            .unwrap()
-            .try_into()
-            .unwrap();
+        //HashKeeper fields, see res/sui/truly/sources/hasher.move
+        let field = |name: &str| -> String {
+            fields
+                .get(name)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
 
         let res = ContractContentInfo {
-            hashFile: "".to_string(),
-            hashAlgo: "".to_string(),
-            uri: Some("".to_string()),
+            hashFile: field("hash"),
+            hashAlgo: field("algorithm"),
+            uri: None,
             price: None,
-            state: None,
-            token: None,
+            state: Some(ContentState::Active), //keepers can't be disabled, frozen or owned
+            token: Some(field("truly_id")),
         };
 
         Ok(res)
     }
 
-    //we reuse the same keypair for all users and we don't want to store it (bool = false)
-    async fn create_keypair(&self, _user_id: &String) -> ResultE<(KeyPair, bool)> {
-        let user_key = KeyPair::new();
+    fn token(&self, tx: &BlockchainTx) -> Option<String> {
+        //the HashKeeper object id created at minting time
+        tx.tx().clone()
+    }
 
-        Ok((user_key, false))
+    async fn minted_asset_ids(&self) -> ResultE<Vec<String>> {
+        let sui = SuiClientBuilder::default()
+            .build(self.url.as_str())
+            .await
+            .unwrap();
+
+        let event_type = parse_sui_struct_tag(
+            format!(
+                "{}::{}::{}",
+                self.contract_address, CONTRACT_MODULE, CONTRACT_EVENT_HASH_ADDED
+            )
+            .as_str(),
+        )?;
+
+        let mut asset_ids = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page_op = sui
+                .event_api()
+                .query_events(
+                    EventFilter::MoveEventType(event_type.clone()),
+                    cursor,
+                    Some(EVENTS_PAGE_SIZE),
+                    false,
+                )
+                .await;
+            let page = match page_op {
+                Err(err) => {
+                    error!("{}", err);
+                    return Err(BlockchainTxError { 0: err.to_string() }.into());
+                }
+                Ok(pg) => pg,
+            };
+            for event in page.data {
+                if let Some(truly_id) = event.parsed_json.get("truly_id").and_then(|v| v.as_str()) {
+                    asset_ids.push(truly_id.to_string());
+                }
+            }
+            if !page.has_next_page {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+        Ok(asset_ids)
+    }
+
+    //an ed25519 key per user, stored encrypted (bool = true)
+    async fn create_keypair(&self, user_id: &String) -> ResultE<(KeyPair, bool)> {
+        let kms_key_id = self.config.env_vars().kms_key_id().unwrap();
+
+        let (address, keypair): (SuiAddress, Ed25519KeyPair) = get_key_pair();
+        let keypair = SuiKeyPair::Ed25519(keypair);
+
+        let public_key = keypair.public().encode_base64();
+        let private_key = Secret::from(keypair.encode_base64());
+        drop(keypair);
+        let user_private_key_cyphered = self
+            .user_keys
+            .seal(user_id, &kms_key_id, &private_key)
+            .await?;
+
+        let mut user_key = KeyPair::new();
+        user_key.set_user_id(user_id);
+        user_key.set_chain(&ChainFamily::Sui);
+        user_key.set_address(&address.to_string());
+        user_key.set_private_key(&user_private_key_cyphered);
+        user_key.set_public_key(&public_key);
+        user_key.set_key_id(&kms_key_id);
+
+        Ok((user_key, true))
+    }
+}
+
+// signs `tx_data` wherever the key of `signer` lives and waits for the node
+// to execute it
+async fn execute_signed(
+    sui: &SuiClient,
+    signer: &(dyn Signer + Sync + Send),
+    tx_data: TransactionData,
+) -> ResultE<SuiTransactionBlockResponse> {
+    let intent_msg = IntentMessage::new(Intent::sui_transaction(), tx_data.clone());
+    let digest = Blake2b256::digest(&bcs::to_bytes(&intent_msg)?).digest;
+    let signature_bytes = signer.sign_digest(&digest).await?;
+    let signature = Signature::from_bytes(&signature_bytes)?;
+
+    let transaction_response_op = sui
+        .quorum_driver_api()
+        .execute_transaction_block(
+            Transaction::from_data(tx_data, Intent::sui_transaction(), vec![signature]).verify()?,
+            SuiTransactionBlockResponseOptions::full_content(),
+            Some(ExecuteTransactionRequestType::WaitForLocalExecution),
+        )
+        .await;
+    if let Err(err) = transaction_response_op {
+        error!("{}", err);
+        return Err(BlockchainTxError { 0: err.to_string() }.into());
+    }
+    let transaction_response = transaction_response_op.ok().unwrap();
+
+    if let Some(confirmation) = transaction_response.confirmed_local_execution {
+        if !confirmation {
+            return Err(BlockchainTxError {
+                0: "failed transaction - confirmed local exec is false".to_string(),
+            }
+            .into());
+        }
+    }
+    Ok(transaction_response)
+}
+
+/// Compiled Move modules as `sui move build` leaves them, and the packages
+/// they link against.
+#[derive(Clone, Debug)]
+pub struct SuiPackage {
+    modules: Vec<Vec<u8>>,
+    dependencies: Vec<ObjectID>,
+}
+
+impl SuiPackage {
+    /// The modules at `build_dir`/bytecode_modules, linked against the Move
+    /// stdlib and the Sui framework only. Modules calling each other must
+    /// sort in dependency order, as they are published in name order.
+    pub fn from_build_dir(build_dir: &Path) -> ResultE<SuiPackage> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(build_dir.join("bytecode_modules"))? {
+            let path = entry?.path();
+            if path.extension().map(|ext| ext == "mv").unwrap_or(false) {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            let mssag = format!("no modules built at {}", build_dir.display());
+            return Err(DeploymentError(mssag).into());
+        }
+        paths.sort();
+        let mut modules = Vec::new();
+        for path in paths {
+            modules.push(fs::read(path)?);
+        }
+        Ok(SuiPackage {
+            modules,
+            dependencies: vec![MOVE_STDLIB_OBJECT_ID, SUI_FRAMEWORK_OBJECT_ID],
+        })
+    }
+
+    /// The hasher package the backend mints with, from res/sui/truly.
+    pub fn truly() -> ResultE<SuiPackage> {
+        SuiPackage::from_build_dir(Path::new(TRULY_BUILD_DIR))
+    }
+
+    pub fn modules(&self) -> &Vec<Vec<u8>> {
+        &self.modules
     }
+
+    pub fn dependencies(&self) -> &Vec<ObjectID> {
+        &self.dependencies
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PublishedPackage {
+    pub package_id: String,
+    /// 1 when published, one up on every upgrade
+    pub version: u64,
+    pub modules: Vec<String>,
+    pub upgrade_cap: String,
+    pub digest: String,
+    pub epoch: u64,
+    /// computation + storage - rebate, None if the rebate was larger
+    pub cost: Option<Amount>,
+}
+
+/// The SUI coin of `owner` holding the most, at least `min_balance` mist.
+/// Only the first page of coins is looked at.
+pub async fn pick_gas_coin(url: &Url, owner: &String, min_balance: u64) -> ResultE<String> {
+    let sui = SuiClientBuilder::default()
+        .build(url.as_str())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let owner = SuiAddress::from_str(owner.as_str())?;
+    let coins = sui
+        .coin_read_api()
+        .get_coins(owner, None, None, None)
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    match coins.data.iter().max_by_key(|coin| coin.balance) {
+        Some(coin) if coin.balance >= min_balance => Ok(coin.coin_object_id.to_string()),
+        _ => {
+            let mssag = format!("{} has no coin holding {} mist", owner, min_balance);
+            Err(DeploymentError(mssag).into())
+        }
+    }
+}
+
+/// Publishes `package` from the address of `signer`, paying from
+/// `gas_coin`. The `UpgradeCap` of the package is kept by the same address.
+pub async fn publish_package(
+    url: &Url,
+    signer: &(dyn Signer + Sync + Send),
+    package: &SuiPackage,
+    gas_coin: &String,
+) -> ResultE<PublishedPackage> {
+    let sui = SuiClientBuilder::default()
+        .build(url.as_str())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let sender = SuiAddress::from_str(signer.address().as_str())?;
+    let gas_object_id = ObjectID::from_str(gas_coin.as_str())?;
+
+    let publish_tx = sui
+        .transaction_builder()
+        .publish(
+            sender,
+            package.modules.clone(),
+            package.dependencies.clone(),
+            Some(gas_object_id),
+            PUBLISH_GAS_BUDGET,
+        )
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let response = execute_signed(&sui, signer, publish_tx).await?;
+    published_package(&response, None)
+}
+
+/// Publishes `package` as the next version of the one at `package_id`,
+/// the latest, through the `UpgradeCap` held by the address of `signer`.
+/// Only upgrades compatible with the previous version are accepted.
+pub async fn upgrade_package(
+    url: &Url,
+    signer: &(dyn Signer + Sync + Send),
+    package: &SuiPackage,
+    package_id: &String,
+    upgrade_cap: &String,
+    gas_coin: &String,
+) -> ResultE<PublishedPackage> {
+    let sui = SuiClientBuilder::default()
+        .build(url.as_str())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let sender = SuiAddress::from_str(signer.address().as_str())?;
+    let package_object_id = ObjectID::from_str(package_id.as_str())?;
+    let upgrade_cap_id = ObjectID::from_str(upgrade_cap.as_str())?;
+    let gas_object_id = ObjectID::from_str(gas_coin.as_str())?;
+    let digest =
+        MovePackage::compute_digest_for_modules_and_deps(&package.modules, &package.dependencies);
+
+    let upgrade_tx = sui
+        .transaction_builder()
+        .upgrade(
+            sender,
+            package_object_id,
+            package.modules.clone(),
+            package.dependencies.clone(),
+            upgrade_cap_id,
+            UpgradePolicy::Compatible as u8,
+            digest.to_vec(),
+            Some(gas_object_id),
+            PUBLISH_GAS_BUDGET,
+        )
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let response = execute_signed(&sui, signer, upgrade_tx).await?;
+    published_package(&response, Some(upgrade_cap.to_owned()))
+}
+
+// the package a publish or upgrade left, the UpgradeCap is created by
+// publishing only
+fn published_package(
+    response: &SuiTransactionBlockResponse,
+    upgrade_cap: Option<String>,
+) -> ResultE<PublishedPackage> {
+    let digest = response.digest.to_string();
+    let effects = match response.effects.as_ref() {
+        None => {
+            let mssag = format!("publish {} returned no effects", digest);
+            return Err(DeploymentError(mssag).into());
+        }
+        Some(effects) => effects,
+    };
+    if !effects.status().is_ok() {
+        let mssag = format!("publish {} failed: {:?}", digest, effects.status());
+        return Err(DeploymentError(mssag).into());
+    }
+
+    let mut published = None;
+    let mut upgrade_cap = upgrade_cap;
+    for change in response.object_changes.clone().unwrap_or_default() {
+        match change {
+            ObjectChange::Published {
+                package_id,
+                version,
+                modules,
+                ..
+            } => published = Some((package_id, version.value(), modules)),
+            ObjectChange::Created {
+                object_type,
+                object_id,
+                ..
+            } if object_type.address == SUI_FRAMEWORK_ADDRESS
+                && object_type.module.as_str() == UPGRADE_CAP_MODULE
+                && object_type.name.as_str() == UPGRADE_CAP_STRUCT =>
+            {
+                upgrade_cap = Some(object_id.to_string())
+            }
+            _ => {}
+        }
+    }
+    let ((package_id, version, modules), upgrade_cap) = match (published, upgrade_cap) {
+        (Some(published), Some(upgrade_cap)) => (published, upgrade_cap),
+        _ => {
+            let mssag = format!("publish {} created no package and upgrade cap", digest);
+            return Err(DeploymentError(mssag).into());
+        }
+    };
+
+    //what the sender paid for gas; balance changes mix in any coin moved
+    let gas = effects.gas_cost_summary();
+    let cost = (gas.computation_cost as u128 + gas.storage_cost as u128)
+        .checked_sub(gas.storage_rebate as u128)
+        .map(Amount::mist);
+
+    Ok(PublishedPackage {
+        package_id: package_id.to_string(),
+        version,
+        modules,
+        upgrade_cap,
+        digest,
+        epoch: effects.executed_epoch(),
+        cost,
+    })
+}
+
+/// Fails unless the package at `package_id` has the entry functions the
+/// backend mints with.
+pub async fn check_hasher_package(url: &Url, package_id: &String) -> ResultE<()> {
+    let sui = SuiClientBuilder::default()
+        .build(url.as_str())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let package_object_id = ObjectID::from_str(package_id.as_str())?;
+    let modules = sui
+        .read_api()
+        .get_normalized_move_modules_by_package(package_object_id)
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let hasher = match modules.get(CONTRACT_MODULE) {
+        None => {
+            let mssag = format!("{} has no {} module", package_id, CONTRACT_MODULE);
+            return Err(DeploymentError(mssag).into());
+        }
+        Some(hasher) => hasher,
+    };
+    for function in [CONTRACT_METHOD_MINTING, CONTRACT_METHOD_MINTING_FOR] {
+        if !hasher.exposed_functions.contains_key(function) {
+            let mssag = format!(
+                "{} lacks {}::{}, rebuild it with `sui move build`",
+                package_id, CONTRACT_MODULE, function
+            );
+            return Err(DeploymentError(mssag).into());
+        }
+    }
+    Ok(())
+}
+
+/// The package the `UpgradeCap` at `upgrade_cap` was last upgraded to and
+/// its version, what the chain holds whether it was recorded or not.
+pub async fn upgrade_cap_package(url: &Url, upgrade_cap: &String) -> ResultE<(String, u64)> {
+    let sui = SuiClientBuilder::default()
+        .build(url.as_str())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let upgrade_cap_id = ObjectID::from_str(upgrade_cap.as_str())?;
+    let response = sui
+        .read_api()
+        .get_object_with_options(upgrade_cap_id, SuiObjectDataOptions::new().with_content())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let fields = match &response.object()?.content {
+        Some(SuiParsedData::MoveObject(obj)) => obj.fields.clone().to_json_value(),
+        _ => {
+            let mssag = format!("object {} isn't an UpgradeCap", upgrade_cap);
+            return Err(DeploymentError(mssag).into());
+        }
+    };
+
+    //UpgradeCap fields, see sui::package; u64 come as strings
+    let package = fields.get("package").and_then(|v| v.as_str());
+    let version = fields.get("version").and_then(|v| match v.as_str() {
+        Some(version) => version.parse::<u64>().ok(),
+        None => v.as_u64(),
+    });
+    match (package, version) {
+        (Some(package), Some(version)) => Ok((package.to_string(), version)),
+        _ => {
+            let mssag = format!("UpgradeCap {} has no package or version", upgrade_cap);
+            Err(DeploymentError(mssag).into())
+        }
+    }
+}
+
+/// Names of the modules of the package at `package_id`.
+pub async fn package_modules(url: &Url, package_id: &String) -> ResultE<Vec<String>> {
+    let sui = SuiClientBuilder::default()
+        .build(url.as_str())
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    let package_object_id = ObjectID::from_str(package_id.as_str())?;
+    let modules = sui
+        .read_api()
+        .get_normalized_move_modules_by_package(package_object_id)
+        .await
+        .map_err(|e| DeploymentError(e.to_string()))?;
+    Ok(modules.keys().cloned().collect())
 }
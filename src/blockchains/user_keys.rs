@@ -0,0 +1,153 @@
+use std::fmt;
+
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::DataKeySpec;
+use base64::{engine::general_purpose, Engine};
+use chrono::Utc;
+use lib_config::config::Config;
+use zeroize::Zeroize;
+
+use crate::errors::keypair::{KeyPairAlreadyExistsError, KeyPairShreddedError};
+use crate::errors::secret::SecretCypherError;
+use crate::models::secret::Secret;
+use crate::models::user_data_key::UserDataKey;
+use crate::repositories::user_data_keys::{UserDataKeyRepo, UserDataKeyRepository};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+/// Per-user data keys. Each user's key material is sealed with a key of
+/// its own, kept wrapped by KMS in its own table; `shred` deletes it and
+/// with it any way to open what was sealed, copies in backups included.
+#[derive(Clone)]
+pub struct UserDataKeys {
+    repo: UserDataKeyRepo,
+    config: Config,
+}
+
+impl fmt::Debug for UserDataKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "UserDataKeys")
+    }
+}
+
+impl UserDataKeys {
+    pub fn new(repo: UserDataKeyRepo, config: &Config) -> UserDataKeys {
+        UserDataKeys {
+            repo,
+            config: config.to_owned(),
+        }
+    }
+
+    /// Seals `plain` with the user's data key, creating it under `key_id`
+    /// the first time.
+    pub async fn seal(&self, user_id: &String, key_id: &String, plain: &Secret) -> ResultE<Secret> {
+        let data_key = match self.repo.get_by_id(user_id).await? {
+            Some(existing) => self.unwrap(&existing).await?,
+            None => self.create(user_id, key_id).await?,
+        };
+        plain.seal(&data_key)
+    }
+
+    pub async fn open(&self, user_id: &String, sealed: &Secret) -> ResultE<Secret> {
+        let existing = self
+            .repo
+            .get_by_id(user_id)
+            .await?
+            .ok_or(KeyPairShreddedError(user_id.to_owned()))?;
+        let data_key = self.unwrap(&existing).await?;
+        sealed.open(&data_key)
+    }
+
+    /// Wraps the user's data key under `new_key_id`, inside KMS. Nothing
+    /// sealed with it changes. False if the user has no data key.
+    pub async fn rewrap(&self, user_id: &String, new_key_id: &String) -> ResultE<bool> {
+        let mut existing = match self.repo.get_by_id(user_id).await? {
+            None => return Ok(false),
+            Some(key) => key,
+        };
+        if existing.key_id() == new_key_id {
+            return Ok(true);
+        }
+        let client = aws_sdk_kms::Client::new(self.config.aws_config());
+        let output = client
+            .re_encrypt()
+            .ciphertext_blob(Blob::new(self.decode(&existing)?))
+            .source_key_id(existing.key_id())
+            .destination_key_id(new_key_id)
+            .send()
+            .await?;
+        let wrapped = output.ciphertext_blob().ok_or(SecretCypherError(
+            "kms sent no wrapped data key".to_string(),
+        ))?;
+        existing.set_wrapped_key(&general_purpose::STANDARD.encode(wrapped.as_ref()));
+        existing.set_key_id(new_key_id);
+        self.repo.update(&existing).await?;
+        Ok(true)
+    }
+
+    /// Deletes the user's data key for good. False if there was none.
+    pub async fn shred(&self, user_id: &String) -> ResultE<bool> {
+        self.repo.delete(user_id).await
+    }
+
+    async fn create(&self, user_id: &String, key_id: &String) -> ResultE<Secret> {
+        let client = aws_sdk_kms::Client::new(self.config.aws_config());
+        let output = client
+            .generate_data_key()
+            .key_id(key_id)
+            .key_spec(DataKeySpec::Aes256)
+            .send()
+            .await?;
+        let wrapped = output.ciphertext_blob().ok_or(SecretCypherError(
+            "kms sent no wrapped data key".to_string(),
+        ))?;
+        let plain = output
+            .plaintext()
+            .ok_or(SecretCypherError("kms sent no data key".to_string()))?;
+
+        let mut data_key = UserDataKey::new();
+        data_key.set_user_id(user_id);
+        data_key.set_wrapped_key(&general_purpose::STANDARD.encode(wrapped.as_ref()));
+        data_key.set_key_id(key_id);
+        data_key.set_creation_time(&Utc::now());
+        match self.repo.add(&data_key).await {
+            Ok(()) => Ok(Secret::from(
+                general_purpose::STANDARD.encode(plain.as_ref()),
+            )),
+            //someone else created it meanwhile, theirs is the one stored
+            Err(e) if e.is::<KeyPairAlreadyExistsError>() => {
+                let existing = self
+                    .repo
+                    .get_by_id(user_id)
+                    .await?
+                    .ok_or(KeyPairShreddedError(user_id.to_owned()))?;
+                self.unwrap(&existing).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn unwrap(&self, data_key: &UserDataKey) -> ResultE<Secret> {
+        let client = aws_sdk_kms::Client::new(self.config.aws_config());
+        let output = client
+            .decrypt()
+            .key_id(data_key.key_id())
+            .ciphertext_blob(Blob::new(self.decode(data_key)?))
+            .send()
+            .await?;
+        let mut plain = output
+            .plaintext()
+            .ok_or(SecretCypherError("kms sent no data key".to_string()))?
+            .as_ref()
+            .to_vec();
+        let encoded = general_purpose::STANDARD.encode(&plain);
+        plain.zeroize();
+        Ok(Secret::from(encoded))
+    }
+
+    fn decode(&self, data_key: &UserDataKey) -> ResultE<Vec<u8>> {
+        general_purpose::STANDARD
+            .decode(data_key.wrapped_key())
+            .map_err(|_| SecretCypherError("malformed wrapped data key".to_string()).into())
+    }
+}
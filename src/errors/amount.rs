@@ -0,0 +1,12 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct AmountConversionError(pub String);
+
+impl std::error::Error for AmountConversionError {}
+
+impl Display for AmountConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "amount conversion error: {}", self.0)
+    }
+}
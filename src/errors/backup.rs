@@ -0,0 +1,12 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct BackupShareError(pub String);
+
+impl std::error::Error for BackupShareError {}
+
+impl Display for BackupShareError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "backup share error: {}", self.0)
+    }
+}
@@ -31,3 +31,14 @@ impl Display for BlockchainKeyPairGenError {
         write!(f, "blockchain keypair generation error: {}", self.0)
     }
 }
+
+#[derive(Debug)]
+pub struct BlockchainBackendError(pub String);
+
+impl std::error::Error for BlockchainBackendError {}
+
+impl Display for BlockchainBackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no blockchain backend for the contract: {}", self.0)
+    }
+}
@@ -21,3 +21,36 @@ impl Display for ContractNoExistsError {
         write!(f, "contract doesn't exists in database: {}", self.0)
     }
 }
+
+#[derive(Debug)]
+pub struct ContractNotWritableError(pub String);
+
+impl std::error::Error for ContractNotWritableError {}
+
+impl Display for ContractNotWritableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contract doesn't accept writes: {}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ContractStatusTransitionError(pub String);
+
+impl std::error::Error for ContractStatusTransitionError {}
+
+impl Display for ContractStatusTransitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contract status can't change: {}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ContractUpgradeError(pub String);
+
+impl std::error::Error for ContractUpgradeError {}
+
+impl Display for ContractUpgradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contract can't be upgraded: {}", self.0)
+    }
+}
@@ -0,0 +1,12 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct DeploymentError(pub String);
+
+impl std::error::Error for DeploymentError {}
+
+impl Display for DeploymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contract deployment error: {}", self.0)
+    }
+}
@@ -32,3 +32,36 @@ impl Display for KeyPairNoExistsError {
         write!(f, "keypair doesn't exists in database: {}", self.0)
     }
 }
+
+#[derive(Debug)]
+pub struct KeyPairDerivationError(pub String);
+
+impl std::error::Error for KeyPairDerivationError {}
+
+impl Display for KeyPairDerivationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "keypair derivation error: {}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct KeyPairShreddedError(pub String);
+
+impl std::error::Error for KeyPairShreddedError {}
+
+impl Display for KeyPairShreddedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "keypair data key has been shredded: {}", self.0)
+    }
+}
+
+#[derive(Debug)]
+pub struct KeyPairAddressMismatchError(pub String);
+
+impl std::error::Error for KeyPairAddressMismatchError {}
+
+impl Display for KeyPairAddressMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "keypair address isn't its key's: {}", self.0)
+    }
+}
@@ -1,6 +1,13 @@
+pub mod amount;
+pub mod backup;
 pub mod block_tx;
 pub mod blockchain;
 pub mod contract;
+pub mod deployment;
 pub mod keypair;
+pub mod keystore;
 pub mod nft;
+pub mod price;
+pub mod secret;
+pub mod signer;
 pub mod asset;
@@ -85,3 +85,19 @@ impl Display for TokenNotSuccessfullyMintedPreviously {
         )
     }
 }
+
+#[derive(Debug)]
+pub struct TokenNotAnchoredOnRequiredChainsError(pub Uuid, pub Vec<u16>);
+
+impl std::error::Error for TokenNotAnchoredOnRequiredChainsError {}
+
+impl Display for TokenNotAnchoredOnRequiredChainsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "this token: {} couldn't be minted on the required contracts {:?}, re-try to complete it.",
+            self.0.to_string(),
+            self.1
+        )
+    }
+}
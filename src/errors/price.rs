@@ -0,0 +1,12 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct PriceSourceError(pub String);
+
+impl std::error::Error for PriceSourceError {}
+
+impl Display for PriceSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "price source error: {}", self.0)
+    }
+}
@@ -0,0 +1,12 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct SecretCypherError(pub String);
+
+impl std::error::Error for SecretCypherError {}
+
+impl Display for SecretCypherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "secret cypher error: {}", self.0)
+    }
+}
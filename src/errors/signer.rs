@@ -0,0 +1,12 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub struct SignerError(pub String);
+
+impl std::error::Error for SignerError {}
+
+impl Display for SignerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "signer error: {}", self.0)
+    }
+}
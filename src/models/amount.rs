@@ -0,0 +1,318 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::errors::amount::AmountConversionError;
+
+/// Native currency of the chain an amount was paid in.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Currency {
+    Eth,
+    Sui,
+}
+
+impl Currency {
+    pub fn base_unit(&self) -> Unit {
+        match self {
+            Currency::Eth => Unit::Wei,
+            Currency::Sui => Unit::Mist,
+        }
+    }
+    /// The unit prices are quoted in, one whole coin.
+    pub fn main_unit(&self) -> Unit {
+        match self {
+            Currency::Eth => Unit::Ether,
+            Currency::Sui => Unit::Sui,
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Currency::Eth => write!(f, "ETH"),
+            Currency::Sui => write!(f, "SUI"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseCurrencyError;
+impl FromStr for Currency {
+    type Err = ParseCurrencyError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "ETH" => Ok(Currency::Eth),
+            "SUI" => Ok(Currency::Sui),
+            _ => Err(ParseCurrencyError),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub enum Unit {
+    Wei,
+    Gwei,
+    Ether,
+    Mist,
+    Sui,
+}
+
+impl Unit {
+    pub fn currency(&self) -> Currency {
+        match self {
+            Unit::Wei | Unit::Gwei | Unit::Ether => Currency::Eth,
+            Unit::Mist | Unit::Sui => Currency::Sui,
+        }
+    }
+    /// Decimal places between this unit and the base unit of its currency.
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Unit::Wei | Unit::Mist => 0,
+            Unit::Gwei | Unit::Sui => 9,
+            Unit::Ether => 18,
+        }
+    }
+    fn factor(&self) -> u128 {
+        10u128.pow(self.decimals())
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unit::Wei => write!(f, "wei"),
+            Unit::Gwei => write!(f, "gwei"),
+            Unit::Ether => write!(f, "eth"),
+            Unit::Mist => write!(f, "mist"),
+            Unit::Sui => write!(f, "sui"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseUnitError;
+impl FromStr for Unit {
+    type Err = ParseUnitError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "wei" => Ok(Unit::Wei),
+            "gwei" => Ok(Unit::Gwei),
+            "eth" => Ok(Unit::Ether),
+            "mist" => Ok(Unit::Mist),
+            "sui" => Ok(Unit::Sui),
+            _ => Err(ParseUnitError),
+        }
+    }
+}
+
+/// An exact on-chain amount. `value` is an integer count of `unit`, so
+/// converting down to the base unit (wei, mist) never loses precision.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Amount {
+    #[serde(with = "u128_as_string")]
+    value: u128,
+    unit: Unit,
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+impl Amount {
+    pub fn new(value: u128, unit: Unit) -> Amount {
+        Amount { value, unit }
+    }
+    pub fn wei(value: u128) -> Amount {
+        Amount::new(value, Unit::Wei)
+    }
+    pub fn mist(value: u128) -> Amount {
+        Amount::new(value, Unit::Mist)
+    }
+    pub fn zero(currency: Currency) -> Amount {
+        Amount::new(0, currency.base_unit())
+    }
+
+    pub fn value(&self) -> u128 {
+        self.value
+    }
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+    pub fn currency(&self) -> Currency {
+        self.unit.currency()
+    }
+
+    pub fn base_value(&self) -> Result<u128, AmountConversionError> {
+        self.value
+            .checked_mul(self.unit.factor())
+            .ok_or(AmountConversionError(format!("{} overflows", self)))
+    }
+
+    pub fn to_base(&self) -> Result<Amount, AmountConversionError> {
+        Ok(Amount::new(self.base_value()?, self.currency().base_unit()))
+    }
+
+    /// Exact conversion, it fails rather than rounding.
+    pub fn convert(&self, unit: Unit) -> Result<Amount, AmountConversionError> {
+        if unit.currency() != self.currency() {
+            return Err(AmountConversionError(format!(
+                "can't convert {} into {}",
+                self, unit
+            )));
+        }
+        let base = self.base_value()?;
+        if base % unit.factor() != 0 {
+            return Err(AmountConversionError(format!(
+                "{} isn't a whole amount of {}",
+                self, unit
+            )));
+        }
+        Ok(Amount::new(base / unit.factor(), unit))
+    }
+
+    pub fn checked_add(&self, other: &Amount) -> Result<Amount, AmountConversionError> {
+        if other.currency() != self.currency() {
+            return Err(AmountConversionError(format!(
+                "can't add {} to {}",
+                other, self
+            )));
+        }
+        let total = self
+            .base_value()?
+            .checked_add(other.base_value()?)
+            .ok_or(AmountConversionError(format!("{} + {} overflows", self, other)))?;
+        Ok(Amount::new(total, self.currency().base_unit()))
+    }
+
+    pub fn checked_mul(&self, times: u128) -> Result<Amount, AmountConversionError> {
+        let total = self
+            .value
+            .checked_mul(times)
+            .ok_or(AmountConversionError(format!("{} * {} overflows", self, times)))?;
+        Ok(Amount::new(total, self.unit))
+    }
+
+    /// Exact decimal representation in any unit of the same currency,
+    /// i.e. 21000 gwei -> "0.000021" eth
+    pub fn to_decimal_string(&self, unit: Unit) -> Result<String, AmountConversionError> {
+        if unit.currency() != self.currency() {
+            return Err(AmountConversionError(format!(
+                "can't express {} in {}",
+                self, unit
+            )));
+        }
+        let base = self.base_value()?;
+        let int = base / unit.factor();
+        let frac = base % unit.factor();
+        if frac == 0 {
+            return Ok(int.to_string());
+        }
+        let frac_str = format!("{:0width$}", frac, width = unit.decimals() as usize);
+        Ok(format!("{}.{}", int, frac_str.trim_end_matches('0')))
+    }
+
+    /// Parses a decimal string expressed in `unit` into its base unit.
+    /// Digits beyond the base unit precision must be zero.
+    pub fn from_decimal_str(value: &str, unit: Unit) -> Result<Amount, AmountConversionError> {
+        let malformed = || AmountConversionError(format!("malformed amount: {} {}", value, unit));
+        if !value.is_ascii() {
+            return Err(malformed());
+        }
+
+        let (int_part, frac_part) = match value.trim().split_once('.') {
+            None => (value.trim(), ""),
+            Some((int, frac)) => (int, frac),
+        };
+        let decimals = unit.decimals() as usize;
+        let (frac_kept, frac_extra) = frac_part.split_at(frac_part.len().min(decimals));
+        if frac_extra.chars().any(|c| c != '0') {
+            return Err(AmountConversionError(format!(
+                "{} {} has more precision than the base unit",
+                value, unit
+            )));
+        }
+
+        let int = if int_part.is_empty() {
+            0
+        } else {
+            u128::from_str(int_part).map_err(|_| malformed())?
+        };
+        let frac = if frac_kept.is_empty() {
+            0
+        } else {
+            u128::from_str(frac_kept).map_err(|_| malformed())?
+                * 10u128.pow((decimals - frac_kept.len()) as u32)
+        };
+
+        let base = int
+            .checked_mul(unit.factor())
+            .and_then(|v| v.checked_add(frac))
+            .ok_or(AmountConversionError(format!("{} {} overflows", value, unit)))?;
+        Ok(Amount::new(base, unit.currency().base_unit()))
+    }
+
+    /// As `from_decimal_str`, rounding what goes past the base unit to the
+    /// nearest one. For amounts once stored as `f64`, which carry noise
+    /// like `0.00041999999999999996` and may come in scientific notation.
+    pub fn from_float_str(value: &str, unit: Unit) -> Result<Amount, AmountConversionError> {
+        let malformed = || AmountConversionError(format!("malformed amount: {} {}", value, unit));
+        let overflows = || AmountConversionError(format!("{} {} overflows", value, unit));
+        let (mantissa, exponent) = match value.trim().split_once(|c| c == 'e' || c == 'E') {
+            None => (value.trim(), 0),
+            Some((mnt, exp)) => (mnt, i32::from_str(exp).map_err(|_| malformed())?),
+        };
+        let (int_part, frac_part) = match mantissa.split_once('.') {
+            None => (mantissa, ""),
+            Some((int, frac)) => (int, frac),
+        };
+        let digits = format!("{}{}", int_part, frac_part);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(malformed());
+        }
+
+        //digits left of the point once counted in the base unit
+        let point = int_part.len() as i64 + exponent as i64 + unit.decimals() as i64;
+        if point > digits.len() as i64 + 39 {
+            return Err(overflows());
+        }
+        let (kept, round_up) = if point <= 0 {
+            (0, point == 0 && digits.as_bytes()[0] >= b'5')
+        } else {
+            let point = point as usize;
+            let mut kept = digits.chars().take(point).collect::<String>();
+            while kept.len() < point {
+                kept.push('0');
+            }
+            let kept = u128::from_str(&kept).map_err(|_| overflows())?;
+            (
+                kept,
+                digits.len() > point && digits.as_bytes()[point] >= b'5',
+            )
+        };
+        let base = kept.checked_add(round_up as u128).ok_or_else(overflows)?;
+        Ok(Amount::new(base, unit.currency().base_unit()))
+    }
+}
+
+// json numbers lose precision above 2^53 in most consumers
+mod u128_as_string {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S>(value: &u128, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(value.to_string().as_str())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<u128, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        u128::from_str(value.as_str()).map_err(de::Error::custom)
+    }
+}
@@ -4,24 +4,32 @@ use serde_json::json;
 use std::{fmt, str::FromStr};
 use uuid::Uuid;
 use validator::Validate;
+
+use super::amount::{Amount, Currency};
+use super::fiat::FiatValuation;
+use super::keypair::ChainFamily;
 //use web3::types::{H160, H256, U256, U64};
 
 #[derive(Clone, Serialize, Validate, Deserialize, Debug, PartialEq)]
 pub struct BlockchainTx {
     asset_id: Uuid,
+    user_id: Option<String>,
     mint_status: MintingStatus,
     creation_time: DateTime<Utc>,
     last_update_time: DateTime<Utc>,
     tx_hash: Option<String>,
     block_number: Option<u64>,
-    gas_used: Option<String>,
-    effective_gas_price: Option<String>,
-    cost: Option<f64>,
-    currency: Option<String>,
+    gas_used: Option<u64>,
+    effective_gas_price: Option<Amount>,
+    cost: Option<Amount>,
+    fiat_cost: Option<FiatValuation>,
     from: Option<String>,
     to: Option<String>,
     contract_id: Option<u16>,
+    hash_file: Option<String>,
+    hash_algorithm: Option<String>,
     tx_error: Option<String>,
+    anchors: Vec<ChainAnchor>,
 }
 
 impl fmt::Display for BlockchainTx {
@@ -33,22 +41,25 @@ impl fmt::Display for BlockchainTx {
 impl BlockchainTx {
     pub fn new(
         asset_id: Uuid,
+        user_id: Option<String>,
         mint_status: MintingStatus,
         creation_time: DateTime<Utc>,
         last_update_time: DateTime<Utc>,
         tx_hash: Option<String>,
         block_number: Option<u64>,
-        gas_used: Option<String>,
-        effective_gas_price: Option<String>,
-        cost: Option<f64>,
-        currency: Option<String>,
+        gas_used: Option<u64>,
+        effective_gas_price: Option<Amount>,
+        cost: Option<Amount>,
         from: Option<String>,
         to: Option<String>,
         contract_id: Option<u16>,
+        hash_file: Option<String>,
+        hash_algorithm: Option<String>,
         tx_error: Option<String>,
     ) -> BlockchainTx {
         BlockchainTx {
             asset_id,
+            user_id,
             mint_status,
             creation_time,
             last_update_time,
@@ -57,10 +68,13 @@ impl BlockchainTx {
             gas_used,
             effective_gas_price,
             cost,
-            currency,
+            fiat_cost: None,
+            anchors: Vec::new(),
             from,
             to,
             contract_id,
+            hash_file,
+            hash_algorithm,
             tx_error,
         }
     }
@@ -71,6 +85,15 @@ impl BlockchainTx {
     pub fn set_asset_id(&mut self, val: &Uuid) {
         self.asset_id = val.clone()
     }
+    pub fn user_id(&self) -> &Option<String> {
+        &self.user_id
+    }
+    pub fn set_user_id(&mut self, val: &String) {
+        self.user_id = Some(val.clone())
+    }
+    pub fn clear_user_id(&mut self) {
+        self.user_id = None
+    }
     pub fn creation_time(&self) -> &DateTime<Utc> {
         &self.creation_time
     }
@@ -95,30 +118,33 @@ impl BlockchainTx {
     pub fn set_block_number(&mut self, val: &u64) {
         self.block_number = Some(val.clone())
     }
-    pub fn gas_used(&self) -> &Option<String> {
+    pub fn gas_used(&self) -> &Option<u64> {
         &self.gas_used
     }
-    pub fn set_gas_used(&mut self, val: &String) {
+    pub fn set_gas_used(&mut self, val: &u64) {
         self.gas_used = Some(val.clone())
     }
 
-    pub fn effective_gas_price(&self) -> &Option<String> {
+    pub fn effective_gas_price(&self) -> &Option<Amount> {
         &self.effective_gas_price
     }
-    pub fn set_effective_gas_price(&mut self, val: &String) {
+    pub fn set_effective_gas_price(&mut self, val: &Amount) {
         self.effective_gas_price = Some(val.clone())
     }
-    pub fn cost(&self) -> &Option<f64> {
+    pub fn cost(&self) -> &Option<Amount> {
         &self.cost
     }
-    pub fn set_cost(&mut self, val: &f64) {
+    pub fn set_cost(&mut self, val: &Amount) {
         self.cost = Some(val.clone())
     }
-    pub fn currency(&self) -> &Option<String> {
-        &self.currency
+    pub fn fiat_cost(&self) -> &Option<FiatValuation> {
+        &self.fiat_cost
+    }
+    pub fn set_fiat_cost(&mut self, val: &FiatValuation) {
+        self.fiat_cost = Some(val.clone())
     }
-    pub fn set_currency(&mut self, val: &String) {
-        self.currency = Some(val.clone())
+    pub fn currency(&self) -> Option<Currency> {
+        self.cost.map(|c| c.currency())
     }
     pub fn from(&self) -> &Option<String> {
         &self.from
@@ -138,6 +164,18 @@ impl BlockchainTx {
     pub fn set_contract_id(&mut self, val: &u16) {
         self.contract_id = Some(val.clone());
     }
+    pub fn hash_file(&self) -> &Option<String> {
+        &self.hash_file
+    }
+    pub fn set_hash_file(&mut self, val: &String) {
+        self.hash_file = Some(val.clone())
+    }
+    pub fn hash_algorithm(&self) -> &Option<String> {
+        &self.hash_algorithm
+    }
+    pub fn set_hash_algorithm(&mut self, val: &String) {
+        self.hash_algorithm = Some(val.clone())
+    }
     pub fn tx_error(&self) -> &Option<String> {
         &self.tx_error
     }
@@ -150,12 +188,186 @@ impl BlockchainTx {
     pub fn set_minted_status(&mut self, val: MintingStatus) {
         self.mint_status = val.clone()
     }
+    pub fn anchors(&self) -> &Vec<ChainAnchor> {
+        &self.anchors
+    }
+    pub fn set_anchors(&mut self, val: &Vec<ChainAnchor>) {
+        self.anchors = val.clone()
+    }
+    pub fn anchor(&self, contract_id: &u16) -> Option<&ChainAnchor> {
+        self.anchors
+            .iter()
+            .find(|anchor| anchor.contract_id() == contract_id)
+    }
+    /// Replaces the anchor on the same contract, if any.
+    pub fn set_anchor(&mut self, val: &ChainAnchor) {
+        match self
+            .anchors
+            .iter_mut()
+            .find(|anchor| anchor.contract_id() == val.contract_id())
+        {
+            Some(anchor) => *anchor = val.clone(),
+            None => self.anchors.push(val.clone()),
+        }
+    }
+
+    /// Status of a registration mirrored over several chains: complete once
+    /// every required anchor is, in error if any required one failed.
+    /// Registrations on a single chain have no anchors, their own status.
+    pub fn anchors_status(&self) -> MintingStatus {
+        let required: Vec<&ChainAnchor> = self.anchors.iter().filter(|a| a.required()).collect();
+        if required.is_empty() {
+            return self.mint_status();
+        }
+        if required
+            .iter()
+            .all(|a| a.mint_status() == MintingStatus::CompletedSuccessfully)
+        {
+            MintingStatus::CompletedSuccessfully
+        } else if required
+            .iter()
+            .any(|a| a.mint_status() == MintingStatus::Error)
+        {
+            MintingStatus::Error
+        } else {
+            MintingStatus::Started
+        }
+    }
+
+    /// This registration as the contract of `anchor` sees it, what its
+    /// backend needs to find the token.
+    pub fn anchored_tx(&self, anchor: &ChainAnchor) -> BlockchainTx {
+        let mut res = self.clone();
+        res.anchors = Vec::new();
+        //the row holds the details of the primary contract's tx only
+        if self.contract_id != Some(*anchor.contract_id()) {
+            res.gas_used = None;
+            res.effective_gas_price = None;
+            res.fiat_cost = None;
+            res.from = None;
+            res.to = None;
+        }
+        res.contract_id = Some(*anchor.contract_id());
+        res.mint_status = anchor.mint_status();
+        res.tx_hash = anchor.tx().clone();
+        res.block_number = anchor.block_number().clone();
+        res.cost = anchor.cost().clone();
+        res.tx_error = anchor.tx_error().clone();
+        res
+    }
+
+    /// This registration once per contract it went to: itself, or as each
+    /// anchor sees it when mirrored.
+    pub fn per_contract(&self) -> Vec<BlockchainTx> {
+        if self.anchors.is_empty() {
+            return vec![self.clone()];
+        }
+        self.anchors
+            .iter()
+            .map(|anchor| self.anchored_tx(anchor))
+            .collect()
+    }
+}
+
+/// Where one chain of a mirrored registration stands.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChainAnchor {
+    contract_id: u16,
+    chain: ChainFamily,
+    required: bool,
+    mint_status: MintingStatus,
+    last_update_time: DateTime<Utc>,
+    tx_hash: Option<String>,
+    block_number: Option<u64>,
+    cost: Option<Amount>,
+    tx_error: Option<String>,
+}
+
+impl ChainAnchor {
+    pub fn new(contract_id: &u16, chain: &ChainFamily, required: bool) -> ChainAnchor {
+        ChainAnchor {
+            contract_id: contract_id.to_owned(),
+            chain: chain.to_owned(),
+            required,
+            mint_status: MintingStatus::Started,
+            last_update_time: Utc::now(),
+            tx_hash: None,
+            block_number: None,
+            cost: None,
+            tx_error: None,
+        }
+    }
+
+    /// A successful mint on its contract.
+    pub fn completed(mut self, tx: &BlockchainTx) -> ChainAnchor {
+        self.mint_status = MintingStatus::CompletedSuccessfully;
+        self.last_update_time = Utc::now();
+        self.tx_hash = tx.tx().clone();
+        self.block_number = tx.block_number().clone();
+        self.cost = tx.cost().clone();
+        self.tx_error = None;
+        self
+    }
+
+    pub fn failed(mut self, error: &String) -> ChainAnchor {
+        self.mint_status = MintingStatus::Error;
+        self.last_update_time = Utc::now();
+        self.tx_error = Some(error.to_owned());
+        self
+    }
+
+    pub fn contract_id(&self) -> &u16 {
+        &self.contract_id
+    }
+    pub fn chain(&self) -> &ChainFamily {
+        &self.chain
+    }
+    pub fn required(&self) -> bool {
+        self.required
+    }
+    pub fn mint_status(&self) -> MintingStatus {
+        self.mint_status.clone()
+    }
+    pub fn set_minted_status(&mut self, val: MintingStatus) {
+        self.mint_status = val
+    }
+    pub fn last_update_time(&self) -> &DateTime<Utc> {
+        &self.last_update_time
+    }
+    pub fn set_last_update_time(&mut self, val: &DateTime<Utc>) {
+        self.last_update_time = val.clone()
+    }
+    pub fn tx(&self) -> &Option<String> {
+        &self.tx_hash
+    }
+    pub fn set_tx(&mut self, val: &String) {
+        self.tx_hash = Some(val.clone())
+    }
+    pub fn block_number(&self) -> &Option<u64> {
+        &self.block_number
+    }
+    pub fn set_block_number(&mut self, val: &u64) {
+        self.block_number = Some(val.clone())
+    }
+    pub fn cost(&self) -> &Option<Amount> {
+        &self.cost
+    }
+    pub fn set_cost(&mut self, val: &Amount) {
+        self.cost = Some(val.clone())
+    }
+    pub fn tx_error(&self) -> &Option<String> {
+        &self.tx_error
+    }
+    pub fn set_tx_error(&mut self, val: &String) {
+        self.tx_error = Some(val.clone())
+    }
 }
 
 impl Default for BlockchainTx {
     fn default() -> Self {
         Self {
             asset_id: Default::default(),
+            user_id: Default::default(),
             mint_status: MintingStatus::NeverMinted,
             creation_time: Utc::now(),
             last_update_time: Utc::now(),
@@ -164,11 +376,14 @@ impl Default for BlockchainTx {
             gas_used: Default::default(),
             effective_gas_price: Default::default(),
             cost: Default::default(),
-            currency: Default::default(),
+            fiat_cost: Default::default(),
             from: Default::default(),
             to: Default::default(),
             contract_id: Default::default(),
+            hash_file: Default::default(),
+            hash_algorithm: Default::default(),
             tx_error: Default::default(),
+            anchors: Default::default(),
         }
     }
 }
@@ -214,6 +429,7 @@ impl FromStr for MintingStatus {
 
 pub struct BlockchainTxBuilder {
     asset_id: Uuid,
+    user_id: Option<String>,
     mint_status: MintingStatus
 }
 
@@ -221,6 +437,7 @@ impl BlockchainTxBuilder {
     pub fn new() -> BlockchainTxBuilder {
         BlockchainTxBuilder{
             asset_id: Uuid::default(),
+            user_id: None,
             mint_status: MintingStatus::NeverMinted
         }
     }
@@ -228,6 +445,10 @@ impl BlockchainTxBuilder {
         self.asset_id = id.clone();
         self
     }
+    pub fn user_id(&mut self, id: &String) -> &mut BlockchainTxBuilder {
+        self.user_id = Some(id.clone());
+        self
+    }
     pub fn mint_status(&mut self, state: MintingStatus) -> &mut BlockchainTxBuilder {
         self.mint_status = state;
         self
@@ -237,6 +458,9 @@ impl BlockchainTxBuilder {
     pub fn build(&self) -> BlockchainTx {
         let mut res = BlockchainTx::default();
         res.set_asset_id(&self.asset_id);
+        if let Some(user_id) = &self.user_id {
+            res.set_user_id(user_id);
+        }
         res.set_minted_status(self.mint_status.clone());
 
         res
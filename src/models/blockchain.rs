@@ -4,6 +4,8 @@ use std::fmt;
 use url::Url;
 use validator::Validate;
 
+use super::keypair::ChainFamily;
+
 #[derive(Clone, Serialize, Validate, Deserialize, Debug)]
 pub struct Blockchain {
     id: String,
@@ -12,6 +14,7 @@ pub struct Blockchain {
     confirmations: u16,
     explorer: Url,
     explorer_api_key: String,
+    family: Option<ChainFamily>, //none for records older than the field, no backend for those
 }
 
 impl fmt::Display for Blockchain {
@@ -28,6 +31,7 @@ impl Blockchain {
         confirmations: u16,
         explorer: Url,
         explorer_api_key: String,
+        family: Option<ChainFamily>,
     ) -> Blockchain {
         Blockchain {
             id,
@@ -36,6 +40,7 @@ impl Blockchain {
             confirmations,
             explorer,
             explorer_api_key,
+            family,
         }
     }
 
@@ -78,4 +83,11 @@ impl Blockchain {
     pub fn set_explorer_api_key(&mut self, val: &String) {
         self.explorer_api_key = val.clone()
     }
+    /// Kind of chain, it decides which backend serves its contracts.
+    pub fn family(&self) -> &Option<ChainFamily> {
+        &self.family
+    }
+    pub fn set_family(&mut self, val: &ChainFamily) {
+        self.family = Some(val.clone())
+    }
 }
@@ -3,6 +3,12 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::{fmt, str::FromStr};
 use validator::Validate;
+
+use crate::errors::contract::{
+    ContractNotWritableError, ContractStatusTransitionError, ContractUpgradeError,
+};
+
+use super::secret::Secret;
 //use web3::types::H160;
 
 #[derive(Clone, Serialize, Validate, Deserialize, Debug)]
@@ -12,10 +18,18 @@ pub struct Contract {
     blockchain: String,
     address: Option<String>,
     owner_address: Option<String>,
-    owner_secret: Option<String>,
+    #[serde(skip_serializing, default)]
+    owner_secret: Option<Secret>,
+    owner_secret_key_id: Option<String>, //kms key encrypting owner_secret
+    owner_signer_key_id: Option<String>, //kms asymmetric key holding the owner key, no owner_secret then
     owner_cash: Option<String>,
+    upgrade_cap: Option<String>, //sui UpgradeCap of the package at address
+    proxy_admin: Option<String>, //evm ProxyAdmin upgrading the proxy at address, none if not a proxy
+    deployment_block: Option<u64>, //evm block deploying address, its logs start there
     details: Option<String>,
     status: ContractStatus,
+    status_history: Vec<ContractStatusChange>,
+    versions: Vec<ContractVersion>,
 }
 
 impl fmt::Display for Contract {
@@ -33,9 +47,16 @@ impl Contract {
             address: None,
             owner_address: None,
             owner_secret: None,
+            owner_secret_key_id: None,
+            owner_signer_key_id: None,
             owner_cash: None,
+            upgrade_cap: None,
+            proxy_admin: None,
+            deployment_block: None,
             details: None,
             status: ContractStatus::Disabled,
+            status_history: Vec::new(),
+            versions: Vec::new(),
         }
     }
 
@@ -45,7 +66,7 @@ impl Contract {
         blockchain: String,
         address: Option<String>,
         owner_address: Option<String>,
-        owner_secret: Option<String>,
+        owner_secret: Option<Secret>,
         owner_cash: Option<String>,
         details: Option<String>,
         status: ContractStatus,
@@ -57,9 +78,16 @@ impl Contract {
             address,
             owner_address,
             owner_secret,
+            owner_secret_key_id: None,
+            owner_signer_key_id: None,
             owner_cash,
+            upgrade_cap: None,
+            proxy_admin: None,
+            deployment_block: None,
             details,
             status,
+            status_history: Vec::new(),
+            versions: Vec::new(),
         }
     }
 
@@ -93,18 +121,48 @@ impl Contract {
     pub fn set_owner_address(&mut self, val: &String) {
         self.owner_address = Some(val.clone())
     }
-    pub fn owner_secret(&self) -> &Option<String> {
+    pub fn owner_secret(&self) -> &Option<Secret> {
         &self.owner_secret
     }
-    pub fn set_owner_secret(&mut self, val: &String) {
+    pub fn set_owner_secret(&mut self, val: &Secret) {
         self.owner_secret = Some(val.clone())
     }
+    pub fn owner_secret_key_id(&self) -> &Option<String> {
+        &self.owner_secret_key_id
+    }
+    pub fn set_owner_secret_key_id(&mut self, val: &String) {
+        self.owner_secret_key_id = Some(val.clone())
+    }
+    pub fn owner_signer_key_id(&self) -> &Option<String> {
+        &self.owner_signer_key_id
+    }
+    pub fn set_owner_signer_key_id(&mut self, val: &String) {
+        self.owner_signer_key_id = Some(val.clone())
+    }
     pub fn owner_cash(&self) -> &Option<String> {
         &self.owner_cash
     }
     pub fn set_owner_cash(&mut self, val: &String) {
         self.owner_cash = Some(val.clone())
     }
+    pub fn upgrade_cap(&self) -> &Option<String> {
+        &self.upgrade_cap
+    }
+    pub fn set_upgrade_cap(&mut self, val: &String) {
+        self.upgrade_cap = Some(val.clone())
+    }
+    pub fn proxy_admin(&self) -> &Option<String> {
+        &self.proxy_admin
+    }
+    pub fn set_proxy_admin(&mut self, val: &String) {
+        self.proxy_admin = Some(val.clone())
+    }
+    pub fn deployment_block(&self) -> &Option<u64> {
+        &self.deployment_block
+    }
+    pub fn set_deployment_block(&mut self, val: &u64) {
+        self.deployment_block = Some(val.clone())
+    }
 
     pub fn details(&self) -> &Option<String> {
         &self.details
@@ -118,12 +176,139 @@ impl Contract {
     pub fn set_status(&mut self, val: &ContractStatus) {
         self.status = val.clone()
     }
+    pub fn status_history(&self) -> &Vec<ContractStatusChange> {
+        &self.status_history
+    }
+    pub fn set_status_history(&mut self, val: &Vec<ContractStatusChange>) {
+        self.status_history = val.clone()
+    }
+    /// When the current status was set, none for contracts older than the history.
+    pub fn status_time(&self) -> Option<&DateTime<Utc>> {
+        self.status_history.last().map(|change| &change.at)
+    }
+
+    /// Moves the contract to `next`, recording when. Retired contracts stay
+    /// retired. False if it already was in `next`, nothing recorded then.
+    pub fn change_status(
+        &mut self,
+        next: &ContractStatus,
+    ) -> Result<bool, ContractStatusTransitionError> {
+        if self.status == *next {
+            return Ok(false);
+        }
+        if self.status.is_final() {
+            return Err(ContractStatusTransitionError(format!(
+                "contract {} is {}, it can't become {}",
+                self.id, self.status, next
+            )));
+        }
+        self.status = next.clone();
+        self.status_history.push(ContractStatusChange {
+            status: next.clone(),
+            at: Utc::now(),
+        });
+        Ok(true)
+    }
+
+    pub fn versions(&self) -> &Vec<ContractVersion> {
+        &self.versions
+    }
+    pub fn set_versions(&mut self, val: &Vec<ContractVersion>) {
+        self.versions = val.clone()
+    }
+    /// The code in effect now, none for contracts older than the history.
+    pub fn current_version(&self) -> Option<&ContractVersion> {
+        self.versions.last()
+    }
+    /// The code that was in effect at `at`, none before the first version.
+    pub fn version_at(&self, at: &DateTime<Utc>) -> Option<&ContractVersion> {
+        self.versions
+            .iter()
+            .rev()
+            .find(|version| version.from <= *at)
+    }
+    /// Where calls go to run the code in effect: the latest Sui package,
+    /// while its types and events keep the id at `address`. Evm calls
+    /// always go to `address`, proxy or not.
+    pub fn code_address(&self) -> Option<String> {
+        match self.current_version() {
+            Some(version) => Some(version.code_address.clone()),
+            None => self.address.clone(),
+        }
+    }
+
+    /// Records `next` as the code in effect from now on. Versions only go
+    /// up, and retired contracts aren't upgraded.
+    pub fn add_version(&mut self, next: &ContractVersion) -> Result<(), ContractUpgradeError> {
+        if self.status.is_final() {
+            return Err(ContractUpgradeError(format!(
+                "contract {} is {}",
+                self.id, self.status
+            )));
+        }
+        if let Some(last) = self.versions.last() {
+            if next.version <= last.version || next.from < last.from {
+                return Err(ContractUpgradeError(format!(
+                    "contract {} is at version {}, {} doesn't follow it",
+                    self.id, last.version, next.version
+                )));
+            }
+        }
+        self.versions.push(next.clone());
+        Ok(())
+    }
+
+    /// Every write on-chain goes through here first.
+    pub fn check_mintable(&self) -> Result<(), ContractNotWritableError> {
+        if !self.status.accepts_mints() {
+            return Err(ContractNotWritableError(format!(
+                "contract {} is {}",
+                self.id, self.status
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContractStatusChange {
+    pub status: ContractStatus,
+    pub at: DateTime<Utc>,
+}
+
+/// Code in effect from `from` until the next version.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContractVersion {
+    /// sui package version, evm implementations are numbered from 1
+    pub version: u64,
+    /// sui package id of this version, implementation behind an evm proxy
+    pub code_address: String,
+    /// evm artifact and digest of its abi, sui module names
+    pub interface: String,
+    pub tx: Option<String>,
+    pub from: DateTime<Utc>,
+}
+
+impl ContractVersion {
+    pub fn new(version: u64, code_address: &String, interface: &String, tx: &String) -> Self {
+        ContractVersion {
+            version,
+            code_address: code_address.to_owned(),
+            interface: interface.to_owned(),
+            tx: Some(tx.to_owned()),
+            from: Utc::now(),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub enum ContractStatus {
     Enabled,
+    /// deprecated: its content can be read, nothing new is minted
+    ReadOnly,
     Disabled,
+    /// out of service for good, content is still readable
+    Retired,
 }
 
 impl ContractStatus {
@@ -133,13 +318,24 @@ impl ContractStatus {
             _ => false,
         }
     }
+    pub fn accepts_mints(&self) -> bool {
+        *self == ContractStatus::Enabled
+    }
+    pub fn accepts_reads(&self) -> bool {
+        *self != ContractStatus::Disabled
+    }
+    pub fn is_final(&self) -> bool {
+        *self == ContractStatus::Retired
+    }
 }
 
 impl fmt::Display for ContractStatus {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ContractStatus::Enabled => write!(f, "Enabled"),
+            ContractStatus::ReadOnly => write!(f, "ReadOnly"),
             ContractStatus::Disabled => write!(f, "Disabled"),
+            ContractStatus::Retired => write!(f, "Retired"),
         }
     }
 }
@@ -150,7 +346,9 @@ impl FromStr for ContractStatus {
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         match input {
             "Enabled" => Ok(ContractStatus::Enabled),
+            "ReadOnly" => Ok(ContractStatus::ReadOnly),
             "Disabled" => Ok(ContractStatus::Disabled),
+            "Retired" => Ok(ContractStatus::Retired),
             _ => Err(ParseContractStatusError),
         }
     }
@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::errors::amount::AmountConversionError;
+
+use super::amount::{Amount, Currency};
+
+/// Decimal places kept for fiat values, enough for sub-cent fees.
+pub const FIAT_DECIMALS: u32 = 9;
+const MAX_RATE_DECIMALS: u32 = 18;
+
+/// Price of one whole coin (1 ETH, 1 SUI) in a fiat currency.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FiatRate {
+    currency: Currency,
+    fiat: String,
+    rate: String,
+    source: String,
+    quoted_at: DateTime<Utc>,
+}
+
+impl FiatRate {
+    pub fn new(
+        currency: Currency,
+        fiat: &String,
+        rate: &String,
+        source: &String,
+        quoted_at: DateTime<Utc>,
+    ) -> Result<FiatRate, AmountConversionError> {
+        let (_, scale) = parse_decimal(rate)?;
+        if scale > MAX_RATE_DECIMALS {
+            return Err(AmountConversionError(format!(
+                "rate {} has more than {} decimals",
+                rate, MAX_RATE_DECIMALS
+            )));
+        }
+        Ok(FiatRate {
+            currency,
+            fiat: fiat.to_uppercase(),
+            rate: rate.trim().to_owned(),
+            source: source.to_owned(),
+            quoted_at,
+        })
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+    pub fn fiat(&self) -> &String {
+        &self.fiat
+    }
+    pub fn rate(&self) -> &String {
+        &self.rate
+    }
+    pub fn source(&self) -> &String {
+        &self.source
+    }
+    pub fn quoted_at(&self) -> &DateTime<Utc> {
+        &self.quoted_at
+    }
+
+    /// Values `cost` at this rate, rounded half up to `FIAT_DECIMALS`.
+    pub fn value_of(&self, cost: &Amount) -> Result<FiatValuation, AmountConversionError> {
+        if cost.currency() != self.currency {
+            return Err(AmountConversionError(format!(
+                "can't value {} with a {} rate",
+                cost, self.currency
+            )));
+        }
+        let (rate, rate_scale) = parse_decimal(&self.rate)?;
+        let overflow = || AmountConversionError(format!("{} * {} overflows", cost, self.rate));
+
+        let product = cost.base_value()?.checked_mul(rate).ok_or_else(overflow)?;
+        let scale = rate_scale + self.currency.main_unit().decimals();
+        let value = if scale > FIAT_DECIMALS {
+            let divisor = 10u128.pow(scale - FIAT_DECIMALS);
+            product / divisor + if product % divisor >= divisor / 2 { 1 } else { 0 }
+        } else {
+            product
+                .checked_mul(10u128.pow(FIAT_DECIMALS - scale))
+                .ok_or_else(overflow)?
+        };
+
+        Ok(FiatValuation {
+            fiat: self.fiat.clone(),
+            rate: self.rate.clone(),
+            value: FiatValuation::format_value(value),
+            source: self.source.clone(),
+            quoted_at: self.quoted_at,
+        })
+    }
+}
+
+/// Fiat value of an on-chain cost, frozen with the rate used at the time.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct FiatValuation {
+    fiat: String,
+    rate: String,
+    value: String,
+    source: String,
+    quoted_at: DateTime<Utc>,
+}
+
+impl fmt::Display for FiatValuation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.fiat)
+    }
+}
+
+impl FiatValuation {
+    pub fn new(
+        fiat: &String,
+        rate: &String,
+        value: &String,
+        source: &String,
+        quoted_at: DateTime<Utc>,
+    ) -> FiatValuation {
+        FiatValuation {
+            fiat: fiat.to_owned(),
+            rate: rate.to_owned(),
+            value: value.to_owned(),
+            source: source.to_owned(),
+            quoted_at,
+        }
+    }
+
+    pub fn fiat(&self) -> &String {
+        &self.fiat
+    }
+    pub fn rate(&self) -> &String {
+        &self.rate
+    }
+    pub fn value(&self) -> &String {
+        &self.value
+    }
+    pub fn source(&self) -> &String {
+        &self.source
+    }
+    pub fn quoted_at(&self) -> &DateTime<Utc> {
+        &self.quoted_at
+    }
+
+    /// Value as an integer count of 10^-FIAT_DECIMALS fiat units, to add them up exactly.
+    pub fn value_units(&self) -> Result<u128, AmountConversionError> {
+        FiatValuation::parse_value(&self.value)
+    }
+
+    pub fn parse_value(value: &String) -> Result<u128, AmountConversionError> {
+        let (mantissa, scale) = parse_decimal(value)?;
+        if scale > FIAT_DECIMALS {
+            return Err(AmountConversionError(format!(
+                "{} has more than {} decimals",
+                value, FIAT_DECIMALS
+            )));
+        }
+        mantissa
+            .checked_mul(10u128.pow(FIAT_DECIMALS - scale))
+            .ok_or(AmountConversionError(format!("{} overflows", value)))
+    }
+
+    pub fn format_value(units: u128) -> String {
+        let factor = 10u128.pow(FIAT_DECIMALS);
+        let int = units / factor;
+        let frac = units % factor;
+        if frac == 0 {
+            return int.to_string();
+        }
+        let frac_str = format!("{:0width$}", frac, width = FIAT_DECIMALS as usize);
+        format!("{}.{}", int, frac_str.trim_end_matches('0'))
+    }
+}
+
+// "1843.27" -> (184327, 2)
+fn parse_decimal(value: &String) -> Result<(u128, u32), AmountConversionError> {
+    let malformed = || AmountConversionError(format!("malformed decimal: {}", value));
+    let value = value.trim();
+    let (int_part, frac_part) = match value.split_once('.') {
+        None => (value, ""),
+        Some((int, frac)) => (int, frac.trim_end_matches('0')),
+    };
+    let digits = format!("{}{}", int_part, frac_part);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(malformed());
+    }
+    let mantissa = u128::from_str(digits.as_str()).map_err(|_| malformed())?;
+    Ok((mantissa, frac_part.len() as u32))
+}
@@ -1,23 +1,33 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, str::FromStr};
 use validator::Validate;
 
+use super::secret::Secret;
+
 #[derive(Clone, Serialize, Validate, Deserialize, Debug)]
 pub struct KeyPair {
     #[validate(length(max = 100))]
     user_id: String,
+    chain: ChainFamily,
     creation_time: DateTime<Utc>,
     last_update_time: DateTime<Utc>,
 
     address: String,
     public_key: String,
-    private_key: String,
+    #[serde(skip_serializing, default)]
+    private_key: Secret,
+    key_id: Option<String>, //kms key protecting private_key, or the data key sealing it
+    derivation_index: Option<u32>, //hd derived keys keep no key material, see HdWallet
 }
 
 impl fmt::Display for KeyPair {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Key user id: {} address: {}", self.user_id, self.address)
+        write!(
+            f,
+            "Key user id: {} chain: {} address: {}",
+            self.user_id, self.chain, self.address
+        )
     }
 }
 
@@ -25,11 +35,14 @@ impl KeyPair {
     pub fn new() -> KeyPair {
         KeyPair {
             user_id: "".to_string(),
+            chain: ChainFamily::Evm,
             creation_time: Utc::now(),
             last_update_time: Utc::now(),
             address: "".to_string(),
             public_key: "".to_string(),
-            private_key: "".to_string(),
+            private_key: Secret::default(),
+            key_id: None,
+            derivation_index: None,
         }
     }
 
@@ -45,6 +58,12 @@ impl KeyPair {
     pub fn set_user_id(&mut self, val: &String) {
         self.user_id = val.clone()
     }
+    pub fn chain(&self) -> &ChainFamily {
+        &self.chain
+    }
+    pub fn set_chain(&mut self, val: &ChainFamily) {
+        self.chain = val.clone()
+    }
     pub fn last_update_time(&self) -> &DateTime<Utc> {
         &self.last_update_time
     }
@@ -63,10 +82,52 @@ impl KeyPair {
     pub fn set_public_key(&mut self, val: &String) {
         self.public_key = val.clone()
     }
-    pub fn private_key(&self) -> &String {
+    pub fn private_key(&self) -> &Secret {
         &self.private_key
     }
-    pub fn set_private_key(&mut self, val: &String) {
+    pub fn set_private_key(&mut self, val: &Secret) {
         self.private_key = val.clone()
     }
+    pub fn key_id(&self) -> &Option<String> {
+        &self.key_id
+    }
+    pub fn set_key_id(&mut self, val: &String) {
+        self.key_id = Some(val.clone())
+    }
+    pub fn derivation_index(&self) -> &Option<u32> {
+        &self.derivation_index
+    }
+    pub fn set_derivation_index(&mut self, val: &u32) {
+        self.derivation_index = Some(val.clone())
+    }
+}
+
+/// Key families a user may hold one keypair for: secp256k1 keys for
+/// EVM chains (Ganache, Ethereum...) and Ed25519 keys for Sui.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub enum ChainFamily {
+    Evm,
+    Sui,
+}
+
+impl fmt::Display for ChainFamily {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChainFamily::Evm => write!(f, "Evm"),
+            ChainFamily::Sui => write!(f, "Sui"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseChainFamilyError;
+impl FromStr for ChainFamily {
+    type Err = ParseChainFamilyError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "Evm" => Ok(ChainFamily::Evm),
+            "Sui" => Ok(ChainFamily::Sui),
+            _ => Err(ParseChainFamilyError),
+        }
+    }
 }
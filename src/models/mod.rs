@@ -1,4 +1,9 @@
+pub mod amount;
 pub mod block_tx;
 pub mod blockchain;
 pub mod contract;
+pub mod fiat;
 pub mod keypair;
+pub mod page;
+pub mod secret;
+pub mod user_data_key;
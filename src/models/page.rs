@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// One page of results plus the opaque cursor to request the next one.
+/// `next_cursor` is `None` when there are no more results.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Page<T> {
+    items: Vec<T>,
+    next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, next_cursor: Option<String>) -> Page<T> {
+        Page { items, next_cursor }
+    }
+
+    pub fn items(&self) -> &Vec<T> {
+        &self.items
+    }
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+    pub fn next_cursor(&self) -> &Option<String> {
+        &self.next_cursor
+    }
+    pub fn has_next(&self) -> bool {
+        self.next_cursor.is_some()
+    }
+}
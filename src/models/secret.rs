@@ -0,0 +1,290 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use aws_sdk_kms::primitives::Blob;
+use aws_sdk_kms::types::DataKeySpec;
+use base64::{engine::general_purpose, Engine};
+use lib_config::config::Config;
+use lib_config::infra::uncypher_with_secret_key;
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use crate::errors::secret::SecretCypherError;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+// envelope format: env1:<wrapped data key>:<nonce>:<ciphertext>, base64 each
+const ENVELOPE_PREFIX: &str = "env1";
+// sealed under a user's data key: usr1:<nonce>:<ciphertext>
+const USER_SEALED_PREFIX: &str = "usr1";
+const NONCE_LEN: usize = 12;
+// a data key is reused this long, for this many secrets at most
+const DATA_KEY_TTL_SECS: u64 = 300;
+const DATA_KEY_MAX_USES: u32 = 10_000;
+
+/// Key material, cyphered or in clear. It prints as `[REDACTED]`, refuses
+/// to be serialized and is wiped from memory when dropped; `expose()` is
+/// the only way to read it.
+#[derive(Clone, Default)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &String {
+        &self.0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether it was cyphered by `cypher`, older items went whole through KMS.
+    pub fn is_envelope(&self) -> bool {
+        self.0.starts_with(format!("{}:", ENVELOPE_PREFIX).as_str())
+    }
+
+    /// Whether it was sealed by `seal`, under a user's own data key.
+    pub fn is_user_sealed(&self) -> bool {
+        self.0
+            .starts_with(format!("{}:", USER_SEALED_PREFIX).as_str())
+    }
+
+    /// Envelope encryption: a data key generated by KMS under `key_id`
+    /// cyphers the secret locally (AES-256-GCM) and is stored wrapped next
+    /// to it, so KMS never sees the secret. Data keys are reused for a few
+    /// minutes, most calls don't reach KMS at all.
+    pub async fn cypher(&self, key_id: &String, config: &Config) -> ResultE<Secret> {
+        let (mut plain_key, wrapped) = data_key_for(key_id, config).await?;
+        let sealed = aes_seal(&plain_key, self.0.as_bytes());
+        plain_key.zeroize();
+        let (nonce, cyphered) = sealed?;
+
+        Ok(Secret(format!(
+            "{}:{}:{}:{}",
+            ENVELOPE_PREFIX,
+            general_purpose::STANDARD.encode(wrapped),
+            general_purpose::STANDARD.encode(nonce),
+            general_purpose::STANDARD.encode(cyphered)
+        )))
+    }
+
+    /// Opens envelopes and, for older items, asks KMS to decrypt them whole.
+    pub async fn uncypher(&self, key_id: &String, config: &Config) -> ResultE<Secret> {
+        if !self.is_envelope() {
+            let plain = uncypher_with_secret_key(self.0.to_owned(), key_id, config).await?;
+            return Ok(Secret(plain));
+        }
+        let parts = decode_parts(&self.0, 4)?;
+        let mut plain_key = unwrap_data_key(&parts[0], key_id, config).await?;
+        let plain = aes_open(&plain_key, &parts[1], &parts[2]);
+        plain_key.zeroize();
+        plain
+    }
+
+    /// Cyphers with `data_key`, a base64 AES-256 key kept apart from the
+    /// result; once that key is destroyed nothing sealed with it, backups
+    /// included, can be opened again.
+    pub fn seal(&self, data_key: &Secret) -> ResultE<Secret> {
+        let mut plain_key = decode_data_key(data_key)?;
+        let sealed = aes_seal(&plain_key, self.0.as_bytes());
+        plain_key.zeroize();
+        let (nonce, cyphered) = sealed?;
+
+        Ok(Secret(format!(
+            "{}:{}:{}",
+            USER_SEALED_PREFIX,
+            general_purpose::STANDARD.encode(nonce),
+            general_purpose::STANDARD.encode(cyphered)
+        )))
+    }
+
+    pub fn open(&self, data_key: &Secret) -> ResultE<Secret> {
+        if !self.is_user_sealed() {
+            return Err(SecretCypherError("not sealed with a data key".to_string()).into());
+        }
+        let parts = decode_parts(&self.0, 3)?;
+        let mut plain_key = decode_data_key(data_key)?;
+        let plain = aes_open(&plain_key, &parts[0], &parts[1]);
+        plain_key.zeroize();
+        plain
+    }
+}
+
+// base64 fields after the prefix
+fn decode_parts(sealed: &String, count: usize) -> ResultE<Vec<Vec<u8>>> {
+    let malformed = || SecretCypherError("malformed envelope".to_string());
+    let parts: Vec<&str> = sealed.split(':').collect();
+    if parts.len() != count {
+        return Err(malformed().into());
+    }
+    let mut decoded = Vec::new();
+    for part in &parts[1..] {
+        decoded.push(
+            general_purpose::STANDARD
+                .decode(part)
+                .map_err(|_| malformed())?,
+        );
+    }
+    Ok(decoded)
+}
+
+fn decode_data_key(data_key: &Secret) -> ResultE<Vec<u8>> {
+    general_purpose::STANDARD
+        .decode(data_key.expose())
+        .map_err(|_| SecretCypherError("malformed data key".to_string()).into())
+}
+
+fn aes_seal(plain_key: &[u8], plain: &[u8]) -> ResultE<([u8; NONCE_LEN], Vec<u8>)> {
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let cipher =
+        Aes256Gcm::new_from_slice(plain_key).map_err(|e| SecretCypherError(e.to_string()))?;
+    let cyphered = cipher
+        .encrypt(Nonce::from_slice(&nonce), plain)
+        .map_err(|e| SecretCypherError(e.to_string()))?;
+    Ok((nonce, cyphered))
+}
+
+fn aes_open(plain_key: &[u8], nonce: &[u8], cyphered: &[u8]) -> ResultE<Secret> {
+    if nonce.len() != NONCE_LEN {
+        return Err(SecretCypherError("malformed envelope".to_string()).into());
+    }
+    let cipher =
+        Aes256Gcm::new_from_slice(plain_key).map_err(|e| SecretCypherError(e.to_string()))?;
+    let plain = cipher
+        .decrypt(Nonce::from_slice(nonce), cyphered)
+        .map_err(|_| SecretCypherError("envelope doesn't authenticate".to_string()))?;
+
+    let plain = String::from_utf8(plain).map_err(|e| {
+        let mut bytes = e.into_bytes();
+        bytes.zeroize();
+        SecretCypherError("envelope doesn't hold text".to_string())
+    })?;
+    Ok(Secret(plain))
+}
+
+struct DataKey {
+    plain: Vec<u8>,
+    wrapped: Vec<u8>,
+    created_at: Instant,
+    uses: u32,
+}
+
+impl DataKey {
+    fn is_fresh(&self) -> bool {
+        self.created_at.elapsed() < Duration::from_secs(DATA_KEY_TTL_SECS)
+            && self.uses < DATA_KEY_MAX_USES
+    }
+}
+
+impl Drop for DataKey {
+    fn drop(&mut self) {
+        self.plain.zeroize();
+    }
+}
+
+// current data key per kms key id, shared by the whole process
+fn data_keys() -> &'static Mutex<HashMap<String, DataKey>> {
+    static DATA_KEYS: OnceLock<Mutex<HashMap<String, DataKey>>> = OnceLock::new();
+    DATA_KEYS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+// (plain, wrapped) data key to cypher with
+async fn data_key_for(key_id: &String, config: &Config) -> ResultE<(Vec<u8>, Vec<u8>)> {
+    if let Some(key) = data_keys().lock().unwrap().get_mut(key_id) {
+        if key.is_fresh() {
+            key.uses += 1;
+            return Ok((key.plain.clone(), key.wrapped.clone()));
+        }
+    }
+
+    let client = aws_sdk_kms::Client::new(config.aws_config());
+    let output = client
+        .generate_data_key()
+        .key_id(key_id)
+        .key_spec(DataKeySpec::Aes256)
+        .send()
+        .await?;
+    let wrapped = output
+        .ciphertext_blob()
+        .ok_or(SecretCypherError(
+            "kms sent no wrapped data key".to_string(),
+        ))?
+        .as_ref()
+        .to_vec();
+    let plain = output
+        .plaintext()
+        .ok_or(SecretCypherError("kms sent no data key".to_string()))?
+        .as_ref()
+        .to_vec();
+
+    data_keys().lock().unwrap().insert(
+        key_id.to_owned(),
+        DataKey {
+            plain: plain.clone(),
+            wrapped: wrapped.clone(),
+            created_at: Instant::now(),
+            uses: 1,
+        },
+    );
+    Ok((plain, wrapped))
+}
+
+async fn unwrap_data_key(wrapped: &Vec<u8>, key_id: &String, config: &Config) -> ResultE<Vec<u8>> {
+    if let Some(key) = data_keys().lock().unwrap().get(key_id) {
+        if key.wrapped == *wrapped && key.is_fresh() {
+            return Ok(key.plain.clone());
+        }
+    }
+
+    let client = aws_sdk_kms::Client::new(config.aws_config());
+    let output = client
+        .decrypt()
+        .key_id(key_id)
+        .ciphertext_blob(Blob::new(wrapped.to_owned()))
+        .send()
+        .await?;
+    let plain = output
+        .plaintext()
+        .ok_or(SecretCypherError("kms sent no data key".to_string()))?
+        .as_ref()
+        .to_vec();
+    Ok(plain)
+}
+
+impl From<String> for Secret {
+    fn from(val: String) -> Secret {
+        Secret(val)
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
+// models holding a secret skip it explicitly, anything else is a leak
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(ser::Error::custom("secrets can't be serialized"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Secret, D::Error> {
+        Ok(Secret(String::deserialize(deserializer)?))
+    }
+}
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A user's own AES-256 data key, stored wrapped by KMS. The user's key
+/// material is sealed with it, so deleting it is enough to shred them.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct UserDataKey {
+    user_id: String,
+    wrapped_key: String, //base64 kms ciphertext blob
+    key_id: String,      //kms key wrapping it
+    creation_time: DateTime<Utc>,
+}
+
+impl fmt::Display for UserDataKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Data key user id: {} kms key: {}",
+            self.user_id, self.key_id
+        )
+    }
+}
+
+impl UserDataKey {
+    pub fn new() -> UserDataKey {
+        UserDataKey {
+            user_id: "".to_string(),
+            wrapped_key: "".to_string(),
+            key_id: "".to_string(),
+            creation_time: Utc::now(),
+        }
+    }
+
+    pub fn user_id(&self) -> &String {
+        &self.user_id
+    }
+    pub fn set_user_id(&mut self, val: &String) {
+        self.user_id = val.clone()
+    }
+    pub fn wrapped_key(&self) -> &String {
+        &self.wrapped_key
+    }
+    pub fn set_wrapped_key(&mut self, val: &String) {
+        self.wrapped_key = val.clone()
+    }
+    pub fn key_id(&self) -> &String {
+        &self.key_id
+    }
+    pub fn set_key_id(&mut self, val: &String) {
+        self.key_id = val.clone()
+    }
+    pub fn creation_time(&self) -> &DateTime<Utc> {
+        &self.creation_time
+    }
+    pub fn set_creation_time(&mut self, val: &DateTime<Utc>) {
+        self.creation_time = val.clone()
+    }
+}
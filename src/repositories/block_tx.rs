@@ -5,7 +5,7 @@ use aws_sdk_dynamodb::{
 };
 use chrono::{
     prelude::{DateTime, Utc},
-    Local,
+    Local, NaiveDate,
 };
 use lib_config::config::Config;
 use std::{collections::HashMap, str::FromStr};
@@ -13,24 +13,50 @@ use uuid::Uuid;
 
 use crate::{
     errors::{block_tx::{BlockchainTxError, BlockchainTxNoExistsError}, asset::AssetNoExistsError},
-    models::block_tx::{BlockchainTx, MintingStatus},
+    models::{
+        amount::{Amount, Unit},
+        block_tx::{BlockchainTx, ChainAnchor, MintingStatus},
+        fiat::FiatValuation,
+        keypair::ChainFamily,
+        page::Page,
+    },
 };
 
+use super::pagination::{decode_cursor, encode_cursor, next_cursor};
 use super::schema_block_tx::{
-    TX_ASSET_ID_FIELD_PK, TX_FIELD, TX_INDEX_NAME, TX_TABLE_NAME,
+    TX_ASSET_ID_FIELD_PK, TX_CONTRACT_ID_FIELD, TX_CONTRACT_INDEX_NAME, TX_CREATION_DAY_FIELD,
+    TX_CREATION_DAY_INDEX_NAME, TX_CREATION_TIME_FIELD, TX_FIELD, TX_INDEX_NAME,
+    TX_MINTED_STATUS_FIELD, TX_STATUS_INDEX_NAME, TX_TABLE_NAME, TX_USER_ID_FIELD,
+    TX_USER_INDEX_NAME,
 };
 pub const TX_BLOCK_NUMER: &str = "block_numer";
 pub const TX_GAS_USED: &str = "gas_used";
 pub const TX_EFECTIVE_GAS_PRICE: &str = "effective_gas_price";
+pub const TX_EFECTIVE_GAS_PRICE_UNIT: &str = "effective_gas_price_unit";
 pub const TX_COST: &str = "cost";
+pub const TX_COST_UNIT: &str = "cost_unit";
 pub const TX_CURRENCY: &str = "currency";
+pub const TX_FIAT: &str = "fiat";
+pub const TX_FIAT_RATE: &str = "fiat_rate";
+pub const TX_FIAT_VALUE: &str = "fiat_value";
+pub const TX_FIAT_SOURCE: &str = "fiat_source";
+pub const TX_FIAT_QUOTED_AT: &str = "fiat_quoted_at";
 pub const TX_FROM: &str = "from";
 pub const TX_TO: &str = "to";
-pub const TX_CONTRACT_ID: &str = "contract_id";
+pub const TX_CONTRACT_ID: &str = TX_CONTRACT_ID_FIELD;
+pub const TX_HASH_FILE: &str = "hash_file";
+pub const TX_HASH_ALGORITHM: &str = "hash_algorithm";
 pub const TX_ERROR: &str = "error";
-pub const TX_MINTED_STATUS_FIELD_NAME: &str = "minting_status";
-pub const TX_CREATION_TIME: &str = "creation_time";
+pub const TX_MINTED_STATUS_FIELD_NAME: &str = TX_MINTED_STATUS_FIELD;
+pub const TX_CREATION_TIME: &str = TX_CREATION_TIME_FIELD;
 pub const TX_LAST_UPDATE_TIME: &str = "last_update_time";
+pub const TX_ANCHORS: &str = "anchors";
+pub const TX_ANCHOR_CHAIN: &str = "chain";
+pub const TX_ANCHOR_REQUIRED: &str = "required";
+
+const CREATION_DAY_FORMAT: &str = "%Y-%m-%d";
+//days queried per creation time page, a page may come back short then
+const CREATION_DAYS_PER_PAGE: u32 = 31;
 
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
@@ -48,7 +74,45 @@ pub trait BlockchainTxRepository {
         asset_id: &Uuid,
         sts: MintingStatus,
     ) -> ResultE<()>;
-
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_user(
+        &self,
+        user_id: &String,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_contract(
+        &self,
+        contract_id: &u16,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_status(
+        &self,
+        status: &MintingStatus,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_creation_time(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    // sets the creation day on rows stored before it was written, the creation
+    // time listings miss them until then. Call it until it returns no cursor
+    async fn backfill_creation_day(
+        &self,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
 }
 
 #[derive(Clone, Debug)]
@@ -65,7 +129,9 @@ impl BlockchainTxRepo {
 
     async fn add_or_update(&self, tx: &BlockchainTx) -> ResultE<()> {
         let asset_id_av = AttributeValue::S(tx.asset_id().to_string());
-        let creation_time_av = AttributeValue::S(iso8601(&Utc::now()));
+        let creation_time_av = AttributeValue::S(iso8601(tx.creation_time()));
+        let creation_day_av =
+            AttributeValue::S(tx.creation_time().format(CREATION_DAY_FORMAT).to_string());
         let last_update_time_av = AttributeValue::S(iso8601(&Utc::now()));
         let minting_status_av = AttributeValue::S( tx.mint_status().to_string() );
 
@@ -73,9 +139,15 @@ impl BlockchainTxRepo {
         items = items
             .item(TX_ASSET_ID_FIELD_PK, asset_id_av)
             .item(TX_CREATION_TIME, creation_time_av)
+            .item(TX_CREATION_DAY_FIELD, creation_day_av)
             .item(TX_LAST_UPDATE_TIME, last_update_time_av )
             .item(TX_MINTED_STATUS_FIELD_NAME , minting_status_av);
 
+        if let Some(user_id) = tx.user_id() {
+            let user_id_av = AttributeValue::S(user_id.clone());
+            items = items.item(TX_USER_ID_FIELD, user_id_av);
+        }
+
         if let Some(hash) = tx.tx() {
             let tx_id_av = AttributeValue::S(hash.clone());
             items = items.item(TX_FIELD, tx_id_av);
@@ -92,18 +164,34 @@ impl BlockchainTxRepo {
         }
 
         if let Some(data) = tx.effective_gas_price() {
-            let data_av = AttributeValue::N(data.clone().to_string());
-            items = items.item(TX_EFECTIVE_GAS_PRICE, data_av);
+            let data_av = AttributeValue::N(data.value().to_string());
+            let unit_av = AttributeValue::S(data.unit().to_string());
+            items = items
+                .item(TX_EFECTIVE_GAS_PRICE, data_av)
+                .item(TX_EFECTIVE_GAS_PRICE_UNIT, unit_av);
         }
 
         if let Some(data) = tx.cost() {
-            let data_av = AttributeValue::N(data.clone().to_string());
-            items = items.item(TX_COST, data_av);
+            let data_av = AttributeValue::N(data.value().to_string());
+            let unit_av = AttributeValue::S(data.unit().to_string());
+            items = items.item(TX_COST, data_av).item(TX_COST_UNIT, unit_av);
         }
         if let Some(data) = tx.currency() {
-            let data_av = AttributeValue::S(data.clone().to_string());
+            let data_av = AttributeValue::S(data.to_string());
             items = items.item(TX_CURRENCY, data_av);
         }
+        //decimals as strings, dynamo numbers would be exact too but these are never queried
+        if let Some(data) = tx.fiat_cost() {
+            items = items
+                .item(TX_FIAT, AttributeValue::S(data.fiat().clone()))
+                .item(TX_FIAT_RATE, AttributeValue::S(data.rate().clone()))
+                .item(TX_FIAT_VALUE, AttributeValue::S(data.value().clone()))
+                .item(TX_FIAT_SOURCE, AttributeValue::S(data.source().clone()))
+                .item(
+                    TX_FIAT_QUOTED_AT,
+                    AttributeValue::S(iso8601(data.quoted_at())),
+                );
+        }
         if let Some(data) = tx.from() {
             let data_av = AttributeValue::S(data.clone());
             items = items.item(TX_FROM, data_av);
@@ -112,6 +200,14 @@ impl BlockchainTxRepo {
             let data_av = AttributeValue::S(data.clone());
             items = items.item(TX_TO, data_av);
         }
+        if let Some(data) = tx.hash_file() {
+            let data_av = AttributeValue::S(data.clone());
+            items = items.item(TX_HASH_FILE, data_av);
+        }
+        if let Some(data) = tx.hash_algorithm() {
+            let data_av = AttributeValue::S(data.clone());
+            items = items.item(TX_HASH_ALGORITHM, data_av);
+        }
         if let Some(data) = tx.tx_error() {
             let data_av = AttributeValue::S(data.to_string());
             items = items.item(TX_ERROR, data_av);
@@ -121,6 +217,14 @@ impl BlockchainTxRepo {
             let data_av = AttributeValue::N(data.to_string());
             items = items.item(TX_CONTRACT_ID, data_av);
         }
+        if !tx.anchors().is_empty() {
+            let anchors = tx
+                .anchors()
+                .iter()
+                .map(mapping_from_anchor_to_doc)
+                .collect();
+            items = items.item(TX_ANCHORS, AttributeValue::L(anchors));
+        }
 
         let request = self.client.transact_write_items().transact_items(
             TransactWriteItem::builder()
@@ -142,6 +246,96 @@ impl BlockchainTxRepo {
         }
     }
 
+    // queries one of the indexes sorted by creation time, optionally bounded by a date range
+    async fn query_index(
+        &self,
+        index_name: &str,
+        hash_field: &str,
+        hash_av: AttributeValue,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        start_key: Option<HashMap<String, AttributeValue>>,
+        newest_first: bool,
+    ) -> ResultE<(Vec<BlockchainTx>, Option<HashMap<String, AttributeValue>>)> {
+        let mut filter = format!("{} = :value", hash_field);
+        match (from, to) {
+            (Some(_), Some(_)) => {
+                filter.push_str(format!(" AND {} BETWEEN :from AND :to", TX_CREATION_TIME).as_str())
+            }
+            (Some(_), None) => filter.push_str(format!(" AND {} >= :from", TX_CREATION_TIME).as_str()),
+            (None, Some(_)) => filter.push_str(format!(" AND {} <= :to", TX_CREATION_TIME).as_str()),
+            (None, None) => {}
+        }
+
+        let mut request = self
+            .client
+            .query()
+            .table_name(TX_TABLE_NAME)
+            .index_name(index_name)
+            .key_condition_expression(filter)
+            .expression_attribute_values(":value".to_string(), hash_av)
+            .scan_index_forward(!newest_first)
+            .limit(*page_size)
+            .set_exclusive_start_key(start_key);
+
+        if let Some(val) = from {
+            request = request.expression_attribute_values(":from".to_string(), AttributeValue::S(iso8601(val)));
+        }
+        if let Some(val) = to {
+            request = request.expression_attribute_values(":to".to_string(), AttributeValue::S(iso8601(val)));
+        }
+
+        let results = request.send().await;
+        match results {
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(BlockchainTxError(e.to_string()).into());
+            }
+            Ok(data) => {
+                let mut queried = Vec::new();
+                if let Some(docs) = data.items() {
+                    for doc in docs {
+                        queried.push(mapping_from_doc_to_blockchain(doc));
+                    }
+                }
+                let last_key = match data.last_evaluated_key() {
+                    Some(key) if !key.is_empty() => Some(key.clone()),
+                    _ => None,
+                };
+                Ok((queried, last_key))
+            }
+        }
+    }
+
+    async fn query_index_page(
+        &self,
+        index_name: &str,
+        hash_field: &str,
+        hash_av: AttributeValue,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        let start_key = match cursor {
+            None => None,
+            Some(crs) => Some(
+                decode_cursor(crs)
+                    .ok_or(BlockchainTxError("malformed pagination cursor".to_string()))?,
+            ),
+        };
+        let (queried, last_key) = self
+            .query_index(index_name, hash_field, hash_av, from, to, page_size, start_key, true)
+            .await?;
+        Ok(Page::new(queried, next_cursor(last_key.as_ref())))
+    }
+
 }
 
 #[async_trait]
@@ -330,7 +524,227 @@ impl BlockchainTxRepository for BlockchainTxRepo {
         Ok(())
     }
 
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<BlockchainTx>> {
+        let mut request = self
+            .client
+            .scan()
+            .table_name(TX_TABLE_NAME)
+            .limit(*page_size);
+
+        if let Some(crs) = cursor {
+            let start_key = decode_cursor(crs)
+                .ok_or(BlockchainTxError("malformed pagination cursor".to_string()))?;
+            request = request.set_exclusive_start_key(Some(start_key));
+        }
+
+        let results = request.send().await;
+        match results {
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(BlockchainTxError(e.to_string()).into());
+            }
+            Ok(data) => {
+                let mut queried = Vec::new();
+                if let Some(docs) = data.items() {
+                    for doc in docs {
+                        queried.push(mapping_from_doc_to_blockchain(doc));
+                    }
+                }
+                let next = next_cursor(data.last_evaluated_key());
+                Ok(Page::new(queried, next))
+            }
+        }
+    }
+    async fn get_by_user(
+        &self,
+        user_id: &String,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        let user_id_av = AttributeValue::S(user_id.clone());
+        self.query_index_page(TX_USER_INDEX_NAME, TX_USER_ID_FIELD, user_id_av, from, to, page_size, cursor)
+            .await
+    }
+
+    async fn get_by_contract(
+        &self,
+        contract_id: &u16,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        let contract_id_av = AttributeValue::N(contract_id.to_string());
+        self.query_index_page(TX_CONTRACT_INDEX_NAME, TX_CONTRACT_ID_FIELD, contract_id_av, from, to, page_size, cursor)
+            .await
+    }
+
+    async fn get_by_status(
+        &self,
+        status: &MintingStatus,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        let status_av = AttributeValue::S(status.to_string());
+        self.query_index_page(TX_STATUS_INDEX_NAME, TX_MINTED_STATUS_FIELD, status_av, from, to, page_size, cursor)
+            .await
+    }
+
+    // the creation day index buckets the records per day, so a range query
+    // walks the days in order, at most CREATION_DAYS_PER_PAGE per call.
+    // The cursor keeps the day we stopped at.
+    async fn get_by_creation_time(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        let mut day;
+        let mut start_key;
+        match cursor {
+            None => {
+                day = from.date_naive();
+                start_key = None;
+            }
+            Some(crs) => {
+                let key = decode_cursor(crs)
+                    .ok_or(BlockchainTxError("malformed pagination cursor".to_string()))?;
+                let day_value = key
+                    .get(TX_CREATION_DAY_FIELD)
+                    .and_then(|v| v.as_s().ok())
+                    .ok_or(BlockchainTxError("malformed pagination cursor".to_string()))?;
+                day = NaiveDate::parse_from_str(day_value, CREATION_DAY_FORMAT)?;
+                //a cursor with only the day means "start that day from the beginning"
+                start_key = if key.len() > 1 { Some(key) } else { None };
+            }
+        }
+        let last_day = to.date_naive();
+
+        let mut found = Vec::new();
+        let mut queried_days = 0;
+        loop {
+            let remaining = *page_size - found.len() as i32;
+            queried_days += 1;
+            let day_av = AttributeValue::S(day.format(CREATION_DAY_FORMAT).to_string());
+            let (mut queried, last_key) = self
+                .query_index(
+                    TX_CREATION_DAY_INDEX_NAME,
+                    TX_CREATION_DAY_FIELD,
+                    day_av,
+                    &Some(from.to_owned()),
+                    &Some(to.to_owned()),
+                    &remaining,
+                    start_key,
+                    false,
+                )
+                .await?;
+            found.append(&mut queried);
+
+            if let Some(key) = last_key {
+                return Ok(Page::new(found, Some(encode_cursor(&key))));
+            }
+            if day >= last_day {
+                return Ok(Page::new(found, None));
+            }
+            day = day.succ_opt().unwrap();
+            start_key = None;
+
+            if found.len() as i32 >= *page_size || queried_days >= CREATION_DAYS_PER_PAGE {
+                let mut next_day = HashMap::new();
+                next_day.insert(
+                    TX_CREATION_DAY_FIELD.to_string(),
+                    AttributeValue::S(day.format(CREATION_DAY_FORMAT).to_string()),
+                );
+                return Ok(Page::new(found, Some(encode_cursor(&next_day))));
+            }
+        }
+    }
+
+    async fn backfill_creation_day(
+        &self,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        let mut request = self
+            .client
+            .scan()
+            .table_name(TX_TABLE_NAME)
+            .filter_expression("attribute_not_exists(#day)")
+            .expression_attribute_names("#day", TX_CREATION_DAY_FIELD)
+            .limit(*page_size);
+
+        if let Some(crs) = cursor {
+            let start_key = decode_cursor(crs)
+                .ok_or(BlockchainTxError("malformed pagination cursor".to_string()))?;
+            request = request.set_exclusive_start_key(Some(start_key));
+        }
 
+        let data = match request.send().await {
+            Ok(data) => data,
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(BlockchainTxError(e.to_string()).into());
+            }
+        };
+        let mut filled = Vec::new();
+        for doc in data.items().unwrap_or_default() {
+            let tx = mapping_from_doc_to_blockchain(doc);
+            let day_av =
+                AttributeValue::S(tx.creation_time().format(CREATION_DAY_FORMAT).to_string());
+            //only the day, a concurrent write may have set it already
+            let update = self
+                .client
+                .update_item()
+                .table_name(TX_TABLE_NAME)
+                .key(
+                    TX_ASSET_ID_FIELD_PK,
+                    AttributeValue::S(tx.asset_id().to_string()),
+                )
+                .update_expression("SET #day = :day")
+                .condition_expression("attribute_exists(#pk) AND attribute_not_exists(#day)")
+                .expression_attribute_names("#pk", TX_ASSET_ID_FIELD_PK)
+                .expression_attribute_names("#day", TX_CREATION_DAY_FIELD)
+                .expression_attribute_values(":day", day_av)
+                .send()
+                .await;
+            match update {
+                Ok(_) => filled.push(tx),
+                Err(e) => {
+                    let raced = e
+                        .as_service_error()
+                        .map(|se| se.is_conditional_check_failed_exception())
+                        .unwrap_or(false);
+                    if raced {
+                        continue;
+                    }
+                    let mssag = format!(
+                        "Error at [{}] - {} ",
+                        Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                        e
+                    );
+                    tracing::error!(mssag);
+                    return Err(BlockchainTxError(e.to_string()).into());
+                }
+            }
+        }
+        let next = next_cursor(data.last_evaluated_key());
+        Ok(Page::new(filled, next))
+    }
 }
 
 fn iso8601(st: &DateTime<Utc>) -> String {
@@ -348,6 +762,15 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
     let asset_id1 = _asset_id.as_s().unwrap();
     let asset_id = Uuid::from_str(asset_id1).unwrap();
 
+    let user_id;
+    match doc.get(TX_USER_ID_FIELD) {
+        None => user_id = None,
+        Some(v) => {
+            let s_val = v.as_s().unwrap().clone();
+            user_id = Some(s_val);
+        }
+    }
+
     let _creation_time = doc.get(TX_CREATION_TIME ).unwrap();
     let creation_time = from_iso8601(_creation_time.as_s().unwrap());
 
@@ -378,8 +801,8 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
         None => gas_used = None,
         Some(v) => {
             let s_val = v.as_n().unwrap();
-            //let val = U256::from_str(s_val).unwrap();
-            gas_used = Some(s_val.clone());
+            //old sui records kept the (negative) balance change here
+            gas_used = u64::from_str(s_val).ok();
         }
     }
     let effective_gas_price;
@@ -387,8 +810,13 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
         None => effective_gas_price = None,
         Some(v) => {
             let s_val = v.as_n().unwrap();
-            //let val = U256::from_str(s_val).unwrap();
-            effective_gas_price = Some(s_val.clone());
+            //prices were always stored in wei before the unit was recorded
+            let unit = match doc.get(TX_EFECTIVE_GAS_PRICE_UNIT) {
+                None => Unit::Wei,
+                Some(u) => Unit::from_str(u.as_s().unwrap()).unwrap(),
+            };
+            let val = u128::from_str(s_val).unwrap();
+            effective_gas_price = Some(Amount::new(val, unit));
         }
     }
     let cost;
@@ -396,17 +824,59 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
         None => cost = None,
         Some(v) => {
             let s_val = v.as_n().unwrap();
-            let val = f64::from_str(s_val).unwrap();
-            cost = Some(val);
+            match doc.get(TX_COST_UNIT) {
+                Some(u) => {
+                    let unit = Unit::from_str(u.as_s().unwrap()).unwrap();
+                    let val = u128::from_str(s_val).unwrap();
+                    cost = Some(Amount::new(val, unit));
+                }
+                None => {
+                    //legacy records: a f64 of ether, though the evm backend
+                    //labelled it "gweis" at the currency field, to the nearest wei
+                    let currency = doc.get(TX_CURRENCY).and_then(|c| c.as_s().ok());
+                    let legacy_unit = match currency.map(|c| c.as_str()) {
+                        None | Some("gweis") => Ok(Unit::Ether),
+                        Some(c) => Unit::from_str(c),
+                    };
+                    let legacy_cost = match legacy_unit {
+                        Err(_) => Err(format!("unknown cost unit {:?}", currency)),
+                        Ok(unit) => {
+                            Amount::from_float_str(s_val, unit).map_err(|e| e.to_string())
+                        }
+                    };
+                    cost = match legacy_cost {
+                        Err(e) => {
+                            let mssag =
+                                format!("legacy cost of tx for asset {} dropped: {}", asset_id, e);
+                            tracing::error!(mssag);
+                            None
+                        }
+                        Ok(amount) => Some(amount),
+                    };
+                }
+            }
         }
     }
-    let currency;
-    match doc.get(TX_CURRENCY) {
-        None => currency = None,
-        Some(v) => {
-            let val = v.as_s().unwrap().to_owned();
-            currency = Some(val);
+    let fiat_cost;
+    match (doc.get(TX_FIAT), doc.get(TX_FIAT_RATE), doc.get(TX_FIAT_VALUE)) {
+        (Some(fiat), Some(rate), Some(value)) => {
+            let source = match doc.get(TX_FIAT_SOURCE) {
+                None => String::new(),
+                Some(v) => v.as_s().unwrap().clone(),
+            };
+            let quoted_at = match doc.get(TX_FIAT_QUOTED_AT) {
+                None => creation_time,
+                Some(v) => from_iso8601(v.as_s().unwrap()),
+            };
+            fiat_cost = Some(FiatValuation::new(
+                fiat.as_s().unwrap(),
+                rate.as_s().unwrap(),
+                value.as_s().unwrap(),
+                &source,
+                quoted_at,
+            ));
         }
+        _ => fiat_cost = None,
     }
     let from;
     match doc.get(TX_FROM) {
@@ -437,6 +907,24 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
         }
     }
 
+    let hash_file;
+    match doc.get(TX_HASH_FILE) {
+        None => hash_file = None,
+        Some(v) => {
+            let s_val = v.as_s().unwrap().clone();
+            hash_file = Some(s_val);
+        }
+    }
+
+    let hash_algorithm;
+    match doc.get(TX_HASH_ALGORITHM) {
+        None => hash_algorithm = None,
+        Some(v) => {
+            let s_val = v.as_s().unwrap().clone();
+            hash_algorithm = Some(s_val);
+        }
+    }
+
     let tx_error;
     match doc.get(TX_ERROR) {
         None => tx_error = None,
@@ -455,8 +943,9 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
         }
     }
 
-    let res = BlockchainTx::new(
+    let mut res = BlockchainTx::new(
         asset_id,
+        user_id,
         mint_status,
         creation_time,
         last_update_time,
@@ -465,11 +954,89 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
         gas_used,
         effective_gas_price,
         cost,
-        currency,
         from,
         to,
         contract_id,
+        hash_file,
+        hash_algorithm,
         tx_error,
     );
+    if let Some(fiat) = fiat_cost {
+        res.set_fiat_cost(&fiat);
+    }
+    if let Some(anchors) = doc.get(TX_ANCHORS).and_then(|v| v.as_l().ok()) {
+        let anchors: Vec<ChainAnchor> = anchors
+            .iter()
+            .filter_map(|v| v.as_m().ok())
+            .map(mapping_from_doc_to_anchor)
+            .collect();
+        res.set_anchors(&anchors);
+    }
     res
 }
+
+fn mapping_from_anchor_to_doc(anchor: &ChainAnchor) -> AttributeValue {
+    let mut doc = HashMap::from([
+        (
+            TX_CONTRACT_ID.to_string(),
+            AttributeValue::N(anchor.contract_id().to_string()),
+        ),
+        (
+            TX_ANCHOR_CHAIN.to_string(),
+            AttributeValue::S(anchor.chain().to_string()),
+        ),
+        (
+            TX_ANCHOR_REQUIRED.to_string(),
+            AttributeValue::Bool(anchor.required()),
+        ),
+        (
+            TX_MINTED_STATUS_FIELD_NAME.to_string(),
+            AttributeValue::S(anchor.mint_status().to_string()),
+        ),
+        (
+            TX_LAST_UPDATE_TIME.to_string(),
+            AttributeValue::S(iso8601(anchor.last_update_time())),
+        ),
+    ]);
+    if let Some(hash) = anchor.tx() {
+        doc.insert(TX_FIELD.to_string(), AttributeValue::S(hash.clone()));
+    }
+    if let Some(data) = anchor.block_number() {
+        doc.insert(TX_BLOCK_NUMER.to_string(), AttributeValue::N(data.to_string()));
+    }
+    if let Some(data) = anchor.cost() {
+        doc.insert(TX_COST.to_string(), AttributeValue::N(data.value().to_string()));
+        doc.insert(TX_COST_UNIT.to_string(), AttributeValue::S(data.unit().to_string()));
+    }
+    if let Some(data) = anchor.tx_error() {
+        doc.insert(TX_ERROR.to_string(), AttributeValue::S(data.clone()));
+    }
+    AttributeValue::M(doc)
+}
+
+fn mapping_from_doc_to_anchor(doc: &HashMap<String, AttributeValue>) -> ChainAnchor {
+    let contract_id = u16::from_str(doc.get(TX_CONTRACT_ID).unwrap().as_n().unwrap()).unwrap();
+    let chain = ChainFamily::from_str(doc.get(TX_ANCHOR_CHAIN).unwrap().as_s().unwrap()).unwrap();
+    let required = *doc.get(TX_ANCHOR_REQUIRED).unwrap().as_bool().unwrap();
+
+    let mut anchor = ChainAnchor::new(&contract_id, &chain, required);
+    let status = doc.get(TX_MINTED_STATUS_FIELD_NAME).unwrap().as_s().unwrap();
+    anchor.set_minted_status(MintingStatus::from_str(status).unwrap());
+    let last_update_time = doc.get(TX_LAST_UPDATE_TIME).unwrap().as_s().unwrap();
+    anchor.set_last_update_time(&from_iso8601(last_update_time));
+    if let Some(v) = doc.get(TX_FIELD) {
+        anchor.set_tx(v.as_s().unwrap());
+    }
+    if let Some(v) = doc.get(TX_BLOCK_NUMER) {
+        anchor.set_block_number(&u64::from_str(v.as_n().unwrap()).unwrap());
+    }
+    if let (Some(v), Some(u)) = (doc.get(TX_COST), doc.get(TX_COST_UNIT)) {
+        let unit = Unit::from_str(u.as_s().unwrap()).unwrap();
+        let val = u128::from_str(v.as_n().unwrap()).unwrap();
+        anchor.set_cost(&Amount::new(val, unit));
+    }
+    if let Some(v) = doc.get(TX_ERROR) {
+        anchor.set_tx_error(v.as_s().unwrap());
+    }
+    anchor
+}
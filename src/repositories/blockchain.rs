@@ -8,9 +8,10 @@ use url::Url;
 
 use crate::{
     errors::blockchain::{BlockchainDynamoDBError, BlockchainNoExistsError},
-    models::blockchain::Blockchain,
+    models::{blockchain::Blockchain, keypair::ChainFamily, page::Page},
 };
 
+use super::pagination::{decode_cursor, next_cursor};
 use super::schema_blockchain::{BLOCKCHAIN_ID_FIELD_PK, BLOCKCHAIN_TABLE_NAME};
 
 pub const BLOCKCHAIN_URL_FIELD_NAME: &str = "url";
@@ -18,6 +19,7 @@ pub const BLOCKCHAIN_API_KEY_FIELD_NAME: &str = "api_key";
 pub const BLOCKCHAIN_CONFIRMATIONS_FIELD_NAME: &str = "confirmations";
 pub const BLOCKCHAIN_EXPLORER_URL_FIELD_NAME: &str = "explorer";
 pub const BLOCKCHAIN_EXPLORER_API_KEY_FIELD_NAME: &str = "explorer_api_key";
+pub const BLOCKCHAIN_FAMILY_FIELD_NAME: &str = "family";
 
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
@@ -26,6 +28,7 @@ pub trait BlockchainRepository {
     async fn add(&self, cont: &Blockchain) -> ResultE<()>;
     async fn update(&self, cont: &Blockchain) -> ResultE<()>;
     async fn get_by_id(&self, id: &String) -> ResultE<Blockchain>;
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<Blockchain>>;
 }
 
 #[derive(Clone, Debug)]
@@ -57,6 +60,10 @@ impl BlockchainRepo {
             .item(BLOCKCHAIN_CONFIRMATIONS_FIELD_NAME, confirmations_av)
             .item(BLOCKCHAIN_EXPLORER_URL_FIELD_NAME, explorer_av)
             .item(BLOCKCHAIN_EXPLORER_API_KEY_FIELD_NAME, explorer_api_key_av);
+        if let Some(family) = blockchain.family() {
+            let av = AttributeValue::S(family.to_string());
+            items = items.item(BLOCKCHAIN_FAMILY_FIELD_NAME, av)
+        }
         Ok(items)
     }
 }
@@ -137,6 +144,43 @@ impl BlockchainRepository for BlockchainRepo {
             }
         }
     }
+
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<Blockchain>> {
+        let mut request = self
+            .client
+            .scan()
+            .table_name(BLOCKCHAIN_TABLE_NAME)
+            .limit(*page_size);
+
+        if let Some(crs) = cursor {
+            let start_key = decode_cursor(crs)
+                .ok_or(BlockchainDynamoDBError("malformed pagination cursor".to_string()))?;
+            request = request.set_exclusive_start_key(Some(start_key));
+        }
+
+        let results = request.send().await;
+        match results {
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(BlockchainDynamoDBError(e.to_string()).into());
+            }
+            Ok(data) => {
+                let mut queried = Vec::new();
+                if let Some(docs) = data.items() {
+                    for doc in docs {
+                        queried.push(mapping_from_doc_to_blockchain(doc));
+                    }
+                }
+                let next = next_cursor(data.last_evaluated_key());
+                Ok(Page::new(queried, next))
+            }
+        }
+    }
 }
 
 pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) -> Blockchain {
@@ -161,6 +205,19 @@ pub fn mapping_from_doc_to_blockchain(doc: &HashMap<String, AttributeValue>) ->
     let _explorer_api_key = doc.get(BLOCKCHAIN_EXPLORER_API_KEY_FIELD_NAME).unwrap();
     let explorer_api_key = _explorer_api_key.as_s().unwrap().to_owned();
 
-    let res = Blockchain::new(id, url, api_key, confirmations, explorer, explorer_api_key);
+    let family = doc.get(BLOCKCHAIN_FAMILY_FIELD_NAME).map(|value| {
+        let family1 = value.as_s().unwrap();
+        ChainFamily::from_str(family1).unwrap()
+    });
+
+    let res = Blockchain::new(
+        id,
+        url,
+        api_key,
+        confirmations,
+        explorer,
+        explorer_api_key,
+        family,
+    );
     return res;
 }
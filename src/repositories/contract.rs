@@ -12,9 +12,14 @@ use lib_config::config::Config;
 
 use crate::{
     errors::contract::{ContractDynamoDBError, ContractNoExistsError},
-    models::contract::{Contract, ContractStatus},
+    models::{
+        contract::{Contract, ContractStatus, ContractStatusChange, ContractVersion},
+        page::Page,
+        secret::Secret,
+    },
 };
 
+use super::pagination::{decode_cursor, next_cursor};
 use super::schema_contract::{
     CONTRACT_BLOCKCHAIN_FIELD, CONTRACT_BLOCKCHAIN_INDEX, CONTRACT_ID_FIELD_PK,
     CONTRACT_STATUS_FIELD_NAME, CONTRACT_TABLE_NAME,
@@ -24,17 +29,53 @@ pub const CREATIONTIME_FIELD_NAME: &str = "creationTime";
 pub const CONTRACT_ADDRESS_FIELD_NAME: &str = "address";
 pub const CONTRACT_OWNER_ADDRESS_FIELD_NAME: &str = "owner_address";
 pub const CONTRACT_OWNER_SECRET_FIELD_NAME: &str = "owner_secret";
+pub const CONTRACT_OWNER_SECRET_KEY_ID_FIELD_NAME: &str = "owner_secret_key_id";
+pub const CONTRACT_OWNER_SIGNER_KEY_ID_FIELD_NAME: &str = "owner_signer_key_id";
 pub const CONTRACT_OWNER_CASH_FIELD_NAME: &str = "owner_cash";
+pub const CONTRACT_UPGRADE_CAP_FIELD_NAME: &str = "upgrade_cap";
+pub const CONTRACT_PROXY_ADMIN_FIELD_NAME: &str = "proxy_admin";
+pub const CONTRACT_DEPLOYMENT_BLOCK_FIELD_NAME: &str = "deployment_block";
 pub const CONTRACT_DETAILS_FIELD_NAME: &str = "details";
+pub const CONTRACT_STATUS_HISTORY_FIELD_NAME: &str = "status_history";
+const STATUS_CHANGE_STATUS_FIELD: &str = "status";
+const STATUS_CHANGE_AT_FIELD: &str = "at";
+pub const CONTRACT_VERSIONS_FIELD_NAME: &str = "versions";
+const VERSION_NUMBER_FIELD: &str = "version";
+const VERSION_CODE_ADDRESS_FIELD: &str = "code_address";
+const VERSION_INTERFACE_FIELD: &str = "interface";
+const VERSION_TX_FIELD: &str = "tx";
+const VERSION_FROM_FIELD: &str = "from";
+// written only when set, update clears them when unset
+const OPTIONAL_FIELDS: [&str; 11] = [
+    CONTRACT_ADDRESS_FIELD_NAME,
+    CONTRACT_OWNER_ADDRESS_FIELD_NAME,
+    CONTRACT_OWNER_SECRET_FIELD_NAME,
+    CONTRACT_OWNER_SECRET_KEY_ID_FIELD_NAME,
+    CONTRACT_OWNER_SIGNER_KEY_ID_FIELD_NAME,
+    CONTRACT_OWNER_CASH_FIELD_NAME,
+    CONTRACT_UPGRADE_CAP_FIELD_NAME,
+    CONTRACT_PROXY_ADMIN_FIELD_NAME,
+    CONTRACT_DEPLOYMENT_BLOCK_FIELD_NAME,
+    CONTRACT_DETAILS_FIELD_NAME,
+    CONTRACT_VERSIONS_FIELD_NAME,
+];
 
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
 #[async_trait]
 pub trait ContractRepository {
     async fn add(&self, cont: &Contract) -> ResultE<()>;
+    // writes everything but the status and its history, see change_status
     async fn update(&self, cont: &Contract) -> ResultE<()>;
     async fn get_by_id(&self, id: &u16) -> ResultE<Contract>;
-    async fn get_by_blockchain(&self, blockchain: &String) -> ResultE<Contract>;
+    async fn get_by_blockchain(
+        &self,
+        blockchain: &String,
+        status: &ContractStatus,
+    ) -> ResultE<Vec<Contract>>;
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<Contract>>;
+    // moves the contract to `status` and records when, fails if it changed meanwhile
+    async fn change_status(&self, id: &u16, status: &ContractStatus) -> ResultE<Contract>;
 }
 
 #[derive(Clone, Debug)]
@@ -71,17 +112,67 @@ impl ContractRepo {
             items = items.item(CONTRACT_OWNER_ADDRESS_FIELD_NAME, av)
         }
         if let Some(val) = contract.owner_secret() {
-            let av = AttributeValue::S(val.clone());
+            let av = AttributeValue::S(val.expose().clone());
             items = items.item(CONTRACT_OWNER_SECRET_FIELD_NAME, av)
         }
+        if let Some(val) = contract.owner_secret_key_id() {
+            let av = AttributeValue::S(val.clone());
+            items = items.item(CONTRACT_OWNER_SECRET_KEY_ID_FIELD_NAME, av)
+        }
+        if let Some(val) = contract.owner_signer_key_id() {
+            let av = AttributeValue::S(val.clone());
+            items = items.item(CONTRACT_OWNER_SIGNER_KEY_ID_FIELD_NAME, av)
+        }
         if let Some(val) = contract.owner_cash() {
             let av = AttributeValue::S(val.clone());
             items = items.item(CONTRACT_OWNER_CASH_FIELD_NAME, av)
         }
+        if let Some(val) = contract.upgrade_cap() {
+            let av = AttributeValue::S(val.clone());
+            items = items.item(CONTRACT_UPGRADE_CAP_FIELD_NAME, av)
+        }
+        if let Some(val) = contract.proxy_admin() {
+            let av = AttributeValue::S(val.clone());
+            items = items.item(CONTRACT_PROXY_ADMIN_FIELD_NAME, av)
+        }
+        if let Some(val) = contract.deployment_block() {
+            let av = AttributeValue::N(val.to_string());
+            items = items.item(CONTRACT_DEPLOYMENT_BLOCK_FIELD_NAME, av)
+        }
         if let Some(val) = contract.details() {
             let av = AttributeValue::S(val.clone());
             items = items.item(CONTRACT_DETAILS_FIELD_NAME, av)
         }
+        if !contract.status_history().is_empty() {
+            let changes = contract
+                .status_history()
+                .iter()
+                .map(|change| {
+                    AttributeValue::M(HashMap::from([
+                        (
+                            STATUS_CHANGE_STATUS_FIELD.to_string(),
+                            AttributeValue::S(change.status.to_string()),
+                        ),
+                        (
+                            STATUS_CHANGE_AT_FIELD.to_string(),
+                            AttributeValue::S(iso8601(&change.at)),
+                        ),
+                    ]))
+                })
+                .collect();
+            items = items.item(
+                CONTRACT_STATUS_HISTORY_FIELD_NAME,
+                AttributeValue::L(changes),
+            )
+        }
+        if !contract.versions().is_empty() {
+            let versions = contract
+                .versions()
+                .iter()
+                .map(mapping_from_version_to_doc)
+                .collect();
+            items = items.item(CONTRACT_VERSIONS_FIELD_NAME, AttributeValue::L(versions))
+        }
 
         Ok(items)
     }
@@ -112,17 +203,58 @@ impl ContractRepository for ContractRepo {
         }
     }
     async fn update(&self, contract: &Contract) -> ResultE<()> {
-        let items = self.new_or_update(contract).unwrap();
+        let put = self
+            .new_or_update(contract)?
+            .table_name(CONTRACT_TABLE_NAME)
+            .build();
+        let fields = put.item().cloned().unwrap_or_default();
 
-        let request = self.client.transact_write_items().transact_items(
-            TransactWriteItem::builder()
-                .put(items.table_name(CONTRACT_TABLE_NAME).build())
-                .build(),
-        );
+        //status and its history are change_status' alone, a put here would undo a concurrent one
+        let mut request = self
+            .client
+            .update_item()
+            .table_name(CONTRACT_TABLE_NAME)
+            .key(
+                CONTRACT_ID_FIELD_PK,
+                AttributeValue::N(contract.id().to_string()),
+            )
+            .condition_expression("attribute_exists(#pk)")
+            .expression_attribute_names("#pk", CONTRACT_ID_FIELD_PK);
+        let mut sets = Vec::new();
+        let mut removes = Vec::new();
+        for (idx, (name, value)) in fields.into_iter().enumerate() {
+            if name == CONTRACT_ID_FIELD_PK
+                || name == CONTRACT_STATUS_FIELD_NAME
+                || name == CONTRACT_STATUS_HISTORY_FIELD_NAME
+            {
+                continue;
+            }
+            sets.push(format!("#f{} = :f{}", idx, idx));
+            request = request
+                .expression_attribute_names(format!("#f{}", idx), name)
+                .expression_attribute_values(format!(":f{}", idx), value);
+        }
+        for (idx, name) in OPTIONAL_FIELDS.iter().enumerate() {
+            if put.item().map(|item| item.contains_key(*name)) != Some(true) {
+                removes.push(format!("#r{}", idx));
+                request = request.expression_attribute_names(format!("#r{}", idx), *name);
+            }
+        }
+        let mut expression = format!("SET {}", sets.join(", "));
+        if !removes.is_empty() {
+            expression = format!("{} REMOVE {}", expression, removes.join(", "));
+        }
 
-        match request.send().await {
+        match request.update_expression(expression).send().await {
             Ok(_) => Ok(()),
             Err(e) => {
+                let gone = e
+                    .as_service_error()
+                    .map(|se| se.is_conditional_check_failed_exception())
+                    .unwrap_or(false);
+                if gone {
+                    return Err(ContractNoExistsError(contract.id().to_string()).into());
+                }
                 let mssag = format!(
                     "Error at [{}] - {} ",
                     Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
@@ -163,23 +295,75 @@ impl ContractRepository for ContractRepo {
         }
     }
 
-    async fn get_by_blockchain(&self, blockchain: &String) -> ResultE<Contract> {
+    async fn get_by_blockchain(
+        &self,
+        blockchain: &String,
+        status: &ContractStatus,
+    ) -> ResultE<Vec<Contract>> {
         let block_ch_av = AttributeValue::S(blockchain.to_owned());
-        let state_ch_av = AttributeValue::S(ContractStatus::Enabled.to_string());
+        let state_ch_av = AttributeValue::S(status.to_string());
         let filter = format!(
-            "{} = :value, {} = :state",
-            CONTRACT_BLOCKCHAIN_FIELD, CONTRACT_STATUS_FIELD_NAME
+            "{} = :value AND #sts = :state",
+            CONTRACT_BLOCKCHAIN_FIELD
         );
 
-        let request = self
+        let mut contracts = Vec::new();
+        let mut start_key = None;
+        loop {
+            let request = self
+                .client
+                .query()
+                .table_name(CONTRACT_TABLE_NAME)
+                .index_name(CONTRACT_BLOCKCHAIN_INDEX)
+                .key_condition_expression(filter.clone())
+                // status is a reserved word at dynamodb
+                .expression_attribute_names("#sts".to_string(), CONTRACT_STATUS_FIELD_NAME)
+                .expression_attribute_values(":value".to_string(), block_ch_av.clone())
+                .expression_attribute_values(":state".to_string(), state_ch_av.clone())
+                .set_exclusive_start_key(start_key)
+                .select(Select::AllAttributes);
+
+            let results = request.send().await;
+            match results {
+                Err(e) => {
+                    let mssag = format!(
+                        "Error at [{}] - {} ",
+                        Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                        e
+                    );
+                    tracing::error!(mssag);
+                    return Err(ContractDynamoDBError(e.to_string()).into());
+                }
+                Ok(items) => {
+                    if let Some(docus) = items.items() {
+                        for doc in docus {
+                            let mut contract_f = Contract::new();
+                            mapping_from_doc_to_contract(doc, &mut contract_f);
+                            contracts.push(contract_f);
+                        }
+                    }
+                    match items.last_evaluated_key() {
+                        Some(key) if !key.is_empty() => start_key = Some(key.clone()),
+                        _ => break,
+                    }
+                }
+            }
+        }
+        Ok(contracts)
+    }
+
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<Contract>> {
+        let mut request = self
             .client
-            .query()
+            .scan()
             .table_name(CONTRACT_TABLE_NAME)
-            .index_name(CONTRACT_BLOCKCHAIN_INDEX)
-            .key_condition_expression(filter)
-            .expression_attribute_values(":value".to_string(), block_ch_av)
-            .expression_attribute_values(":state".to_string(), state_ch_av)
-            .select(Select::AllAttributes);
+            .limit(*page_size);
+
+        if let Some(crs) = cursor {
+            let start_key = decode_cursor(crs)
+                .ok_or(ContractDynamoDBError("malformed pagination cursor".to_string()))?;
+            request = request.set_exclusive_start_key(Some(start_key));
+        }
 
         let results = request.send().await;
         match results {
@@ -192,21 +376,50 @@ impl ContractRepository for ContractRepo {
                 tracing::error!(mssag);
                 return Err(ContractDynamoDBError(e.to_string()).into());
             }
-            Ok(items) => {
-                let docus = items.items().unwrap();
-
-                if docus.len() != 1 {
-                    return Err(ContractDynamoDBError(format!(
-                        "too many contracts enabled, don't which contract use. blockchain: {}",
-                        blockchain
-                    ))
-                    .into());
+            Ok(data) => {
+                let mut queried = Vec::new();
+                if let Some(docs) = data.items() {
+                    for doc in docs {
+                        let mut contract = Contract::new();
+                        mapping_from_doc_to_contract(doc, &mut contract);
+                        queried.push(contract);
+                    }
                 }
-                let doc = docus.first().unwrap();
-                let mut contract_f = Contract::new();
-                mapping_from_doc_to_contract(doc, &mut contract_f);
+                let next = next_cursor(data.last_evaluated_key());
+                Ok(Page::new(queried, next))
+            }
+        }
+    }
 
-                Ok(contract_f)
+    async fn change_status(&self, id: &u16, status: &ContractStatus) -> ResultE<Contract> {
+        let mut contract = self.get_by_id(id).await?;
+        let previous = contract.status().to_string();
+        if !contract.change_status(status)? {
+            return Ok(contract);
+        }
+        let items = self
+            .new_or_update(&contract)?
+            .table_name(CONTRACT_TABLE_NAME)
+            // status is a reserved word at dynamodb
+            .condition_expression("#sts = :previous")
+            .expression_attribute_names("#sts", CONTRACT_STATUS_FIELD_NAME)
+            .expression_attribute_values(":previous", AttributeValue::S(previous));
+
+        let request = self
+            .client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(items.build()).build());
+
+        match request.send().await {
+            Ok(_) => Ok(contract),
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(ContractDynamoDBError(e.to_string()).into());
             }
         }
     }
@@ -248,7 +461,17 @@ pub fn mapping_from_doc_to_contract(
 
     if let Some(value) = doc.get(CONTRACT_OWNER_SECRET_FIELD_NAME) {
         let value1 = value.as_s().unwrap();
-        contract.set_owner_secret(&value1);
+        contract.set_owner_secret(&Secret::from(value1.to_owned()));
+    }
+
+    if let Some(value) = doc.get(CONTRACT_OWNER_SECRET_KEY_ID_FIELD_NAME) {
+        let value1 = value.as_s().unwrap();
+        contract.set_owner_secret_key_id(&value1);
+    }
+
+    if let Some(value) = doc.get(CONTRACT_OWNER_SIGNER_KEY_ID_FIELD_NAME) {
+        let value1 = value.as_s().unwrap();
+        contract.set_owner_signer_key_id(&value1);
     }
 
     if let Some(value) = doc.get(CONTRACT_OWNER_CASH_FIELD_NAME) {
@@ -256,6 +479,21 @@ pub fn mapping_from_doc_to_contract(
         contract.set_owner_cash(&value1);
     }
 
+    if let Some(value) = doc.get(CONTRACT_UPGRADE_CAP_FIELD_NAME) {
+        let value1 = value.as_s().unwrap();
+        contract.set_upgrade_cap(&value1);
+    }
+
+    if let Some(value) = doc.get(CONTRACT_PROXY_ADMIN_FIELD_NAME) {
+        let value1 = value.as_s().unwrap();
+        contract.set_proxy_admin(&value1);
+    }
+
+    if let Some(value) = doc.get(CONTRACT_DEPLOYMENT_BLOCK_FIELD_NAME) {
+        let value1 = u64::from_str(value.as_n().unwrap()).unwrap();
+        contract.set_deployment_block(&value1);
+    }
+
     if let Some(value) = doc.get(CONTRACT_DETAILS_FIELD_NAME) {
         let value1 = value.as_s().unwrap();
         contract.set_details(&value1);
@@ -266,7 +504,72 @@ pub fn mapping_from_doc_to_contract(
     let sts = ContractStatus::from_str(status1).unwrap();
     contract.set_status(&sts);
 
+    if let Some(value) = doc.get(CONTRACT_STATUS_HISTORY_FIELD_NAME) {
+        let mut history = Vec::new();
+        for change in value.as_l().unwrap() {
+            let change = change.as_m().unwrap();
+            let status = change.get(STATUS_CHANGE_STATUS_FIELD).unwrap();
+            let at = change.get(STATUS_CHANGE_AT_FIELD).unwrap();
+            history.push(ContractStatusChange {
+                status: ContractStatus::from_str(status.as_s().unwrap()).unwrap(),
+                at: from_iso8601(at.as_s().unwrap()),
+            });
+        }
+        contract.set_status_history(&history);
+    }
+
+    if let Some(value) = doc.get(CONTRACT_VERSIONS_FIELD_NAME) {
+        let versions = value
+            .as_l()
+            .unwrap()
+            .iter()
+            .map(|version| mapping_from_doc_to_version(version.as_m().unwrap()))
+            .collect();
+        contract.set_versions(&versions);
+    }
+
     if let Some(creation_time) = doc.get(CREATIONTIME_FIELD_NAME) {
         contract.set_creation_time(&from_iso8601(creation_time.as_s().unwrap()));
     }
 }
+
+fn mapping_from_version_to_doc(version: &ContractVersion) -> AttributeValue {
+    let mut doc = HashMap::from([
+        (
+            VERSION_NUMBER_FIELD.to_string(),
+            AttributeValue::N(version.version.to_string()),
+        ),
+        (
+            VERSION_CODE_ADDRESS_FIELD.to_string(),
+            AttributeValue::S(version.code_address.clone()),
+        ),
+        (
+            VERSION_INTERFACE_FIELD.to_string(),
+            AttributeValue::S(version.interface.clone()),
+        ),
+        (
+            VERSION_FROM_FIELD.to_string(),
+            AttributeValue::S(iso8601(&version.from)),
+        ),
+    ]);
+    if let Some(tx) = &version.tx {
+        doc.insert(VERSION_TX_FIELD.to_string(), AttributeValue::S(tx.clone()));
+    }
+    AttributeValue::M(doc)
+}
+
+fn mapping_from_doc_to_version(doc: &HashMap<String, AttributeValue>) -> ContractVersion {
+    let version = doc.get(VERSION_NUMBER_FIELD).unwrap();
+    let code_address = doc.get(VERSION_CODE_ADDRESS_FIELD).unwrap();
+    let interface = doc.get(VERSION_INTERFACE_FIELD).unwrap();
+    let from = doc.get(VERSION_FROM_FIELD).unwrap();
+    ContractVersion {
+        version: u64::from_str(version.as_n().unwrap()).unwrap(),
+        code_address: code_address.as_s().unwrap().to_owned(),
+        interface: interface.as_s().unwrap().to_owned(),
+        tx: doc
+            .get(VERSION_TX_FIELD)
+            .map(|tx| tx.as_s().unwrap().to_owned()),
+        from: from_iso8601(from.as_s().unwrap()),
+    }
+}
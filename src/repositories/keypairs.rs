@@ -1,9 +1,13 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
-use crate::errors::keypair::KeyPairDynamoDBError;
-use crate::models::keypair::KeyPair;
+use crate::errors::keypair::{
+    KeyPairAlreadyExistsError, KeyPairDerivationError, KeyPairDynamoDBError, KeyPairNoExistsError,
+};
+use crate::models::keypair::{ChainFamily, KeyPair};
+use crate::models::page::Page;
+use crate::models::secret::Secret;
 use async_trait::async_trait;
-use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
 use chrono::{
     prelude::{DateTime, Utc},
     Local,
@@ -11,9 +15,12 @@ use chrono::{
 use lib_config::config::Config;
 //use rand::{prelude::*, SeedableRng};
 
+use super::pagination::{decode_cursor, next_cursor};
 use super::schema_keypairs::{
-    KEYPAIRS_ADDRESS_FIELD, KEYPAIRS_PRIVATE_FIELD, KEYPAIRS_PUBLIC_FIELD, KEYPAIRS_TABLE_NAME,
-    KEYPAIRS_USER_ID_FIELD_PK,
+    KEYPAIRS_ADDRESS_FIELD, KEYPAIRS_ADDRESS_INDEX_NAME, KEYPAIRS_CHAIN_FIELD_SK,
+    KEYPAIRS_DERIVATION_INDEX_FIELD, KEYPAIRS_HD_COUNTER_USER_ID, KEYPAIRS_HD_NEXT_INDEX_FIELD,
+    KEYPAIRS_KEY_ID_FIELD, KEYPAIRS_LEGACY_TABLE_NAME, KEYPAIRS_PRIVATE_FIELD,
+    KEYPAIRS_PUBLIC_FIELD, KEYPAIRS_TABLE_NAME, KEYPAIRS_USER_ID_FIELD_PK,
 };
 pub const CREATIONTIME_FIELD_NAME: &str = "creationTime";
 pub const LASTUPDATETIME_FIELD_NAME: &str = "lastUpdateTime";
@@ -22,8 +29,28 @@ type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send
 
 #[async_trait]
 pub trait KeyPairRepository {
+    // fails with KeyPairAlreadyExistsError if the user holds a keypair for
+    // that chain family already, it is never overwritten
     async fn add(&self, keypair: &KeyPair) -> ResultE<()>;
-    async fn get_by_id(&self, user_id: &String) -> ResultE<Option<KeyPair>>;
+    async fn get_by_id(&self, user_id: &String, chain: &ChainFamily) -> ResultE<Option<KeyPair>>;
+    // owner of an on-chain address, i.e. from events or ownership queries.
+    // Fails with KeyPairAlreadyExistsError if several keypairs hold it
+    async fn get_by_address(&self, address: &String) -> ResultE<Option<KeyPair>>;
+    // both fail with KeyPairNoExistsError when the keypair isn't there
+    async fn update(&self, keypair: &KeyPair) -> ResultE<()>;
+    async fn delete(&self, user_id: &String, chain: &ChainFamily) -> ResultE<()>;
+    // legacy rows are listed once migrated
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<KeyPair>>;
+    // atomically hands out the next hd derivation index of a chain family
+    async fn next_derivation_index(&self, chain: &ChainFamily) -> ResultE<u32>;
+    // moves a page of the legacy table, keyed by user alone, to the current
+    // one as evm keypairs. Run until the cursor is None, then the legacy
+    // table can be dropped
+    async fn migrate_legacy(
+        &self,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<KeyPair>>;
     //async fn save(&self, user_id: &String, keypair: &KeyPair) -> ResultE<()>;
 }
 
@@ -42,32 +69,164 @@ impl KeyPairRepo {
             //kms_key_id: conf.env_vars().kms_key_id().to_owned(),
         }
     }
-}
 
-#[async_trait]
-impl KeyPairRepository for KeyPairRepo {
-    async fn add(&self, keypair: &KeyPair) -> ResultE<()> {
+    async fn put(&self, keypair: &KeyPair, existing: bool) -> ResultE<()> {
         let user_id_av = AttributeValue::S(keypair.user_id().to_string());
+        let chain_av = AttributeValue::S(keypair.chain().to_string());
         let address_av = AttributeValue::S(keypair.address().to_string());
         let public_key_av = AttributeValue::S(keypair.public_key().to_string());
-        let private_key_av = AttributeValue::S(keypair.private_key().to_string());
+        let private_key_av = AttributeValue::S(keypair.private_key().expose().to_string());
         let creation_time_av = AttributeValue::S(iso8601(keypair.creation_time()));
-        let update_time_av = AttributeValue::S(iso8601(keypair.creation_time()));
+        let update_time_av = AttributeValue::S(iso8601(keypair.last_update_time()));
 
-        let request = self
+        let mut request = self
             .client_dynamo
             .put_item()
             .table_name(KEYPAIRS_TABLE_NAME)
             .item(KEYPAIRS_USER_ID_FIELD_PK, user_id_av)
+            .item(KEYPAIRS_CHAIN_FIELD_SK, chain_av)
             .item(KEYPAIRS_ADDRESS_FIELD, address_av)
             .item(KEYPAIRS_PRIVATE_FIELD, private_key_av)
             .item(KEYPAIRS_PUBLIC_FIELD, public_key_av)
             .item(CREATIONTIME_FIELD_NAME, creation_time_av)
             .item(LASTUPDATETIME_FIELD_NAME, update_time_av);
 
+        if let Some(key_id) = keypair.key_id() {
+            request = request.item(KEYPAIRS_KEY_ID_FIELD, AttributeValue::S(key_id.clone()));
+        }
+        if let Some(index) = keypair.derivation_index() {
+            request = request.item(
+                KEYPAIRS_DERIVATION_INDEX_FIELD,
+                AttributeValue::N(index.to_string()),
+            );
+        }
+
+        //an update never creates a keypair, an add never overwrites one
+        let condition = if existing {
+            "attribute_exists"
+        } else {
+            "attribute_not_exists"
+        };
+        request =
+            request.condition_expression(format!("{}({})", condition, KEYPAIRS_USER_ID_FIELD_PK));
+
         match request.send().await {
             Ok(_) => Ok(()),
             Err(e) => {
+                let conflict = e
+                    .as_service_error()
+                    .map(|se| se.is_conditional_check_failed_exception())
+                    .unwrap_or(false);
+                if conflict && !existing {
+                    let mssag = format!(
+                        "{} holds a {} keypair already",
+                        keypair.user_id(),
+                        keypair.chain()
+                    );
+                    tracing::warn!(mssag);
+                    return Err(KeyPairAlreadyExistsError(mssag).into());
+                }
+                Err(write_error(conflict, e.to_string()))
+            }
+        }
+    }
+
+    // the row of `user_id` in the legacy table, None once it is migrated
+    // or if the table was never deployed
+    async fn get_legacy(&self, user_id: &String) -> ResultE<Option<KeyPair>> {
+        let request = self
+            .client_dynamo
+            .get_item()
+            .table_name(KEYPAIRS_LEGACY_TABLE_NAME)
+            .key(
+                KEYPAIRS_USER_ID_FIELD_PK,
+                AttributeValue::S(user_id.to_string()),
+            );
+
+        match request.send().await {
+            Err(e) => {
+                let no_table = e
+                    .as_service_error()
+                    .map(|se| se.is_resource_not_found_exception())
+                    .unwrap_or(false);
+                if no_table {
+                    return Ok(None);
+                }
+                Err(write_error(false, e.to_string()))
+            }
+            Ok(data) => Ok(data.item.map(|doc| {
+                let mut keypair = KeyPair::new();
+                mapping_from_doc_to_keypair(&doc, &mut keypair);
+                keypair
+            })),
+        }
+    }
+
+    // deletes the row of `user_id` for `chain`, or its legacy row if `chain`
+    // is None. False if there wasn't one
+    async fn delete_row(&self, user_id: &String, chain: Option<&ChainFamily>) -> ResultE<bool> {
+        let mut request = self
+            .client_dynamo
+            .delete_item()
+            .key(
+                KEYPAIRS_USER_ID_FIELD_PK,
+                AttributeValue::S(user_id.to_string()),
+            )
+            .condition_expression(format!("attribute_exists({})", KEYPAIRS_USER_ID_FIELD_PK));
+        request = match chain {
+            Some(chain) => request.table_name(KEYPAIRS_TABLE_NAME).key(
+                KEYPAIRS_CHAIN_FIELD_SK,
+                AttributeValue::S(chain.to_string()),
+            ),
+            None => request.table_name(KEYPAIRS_LEGACY_TABLE_NAME),
+        };
+
+        match request.send().await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                let gone = e
+                    .as_service_error()
+                    .map(|se| {
+                        se.is_conditional_check_failed_exception()
+                            || (chain.is_none() && se.is_resource_not_found_exception())
+                    })
+                    .unwrap_or(false);
+                if gone {
+                    return Ok(false);
+                }
+                Err(write_error(false, e.to_string()))
+            }
+        }
+    }
+
+    // user id and chain family of the keypairs holding `address` in `table`
+    async fn address_keys(
+        &self,
+        table: &str,
+        address: &String,
+    ) -> ResultE<Vec<(String, ChainFamily)>> {
+        //addresses are kept in lowercase hex for every chain family
+        let address_av = AttributeValue::S(address.to_lowercase());
+        let request = self
+            .client_dynamo
+            .query()
+            .table_name(table)
+            .index_name(KEYPAIRS_ADDRESS_INDEX_NAME)
+            .key_condition_expression(format!("{} = :value", KEYPAIRS_ADDRESS_FIELD))
+            .expression_attribute_values(":value".to_string(), address_av)
+            //the index isn't unique, a second row means the address is ambiguous
+            .limit(2);
+
+        let results = match request.send().await {
+            Ok(data) => data,
+            Err(e) => {
+                let no_table = e
+                    .as_service_error()
+                    .map(|se| se.is_resource_not_found_exception())
+                    .unwrap_or(false);
+                if table == KEYPAIRS_LEGACY_TABLE_NAME && no_table {
+                    return Ok(Vec::new());
+                }
                 let mssag = format!(
                     "Error at [{}] - {} ",
                     Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
@@ -76,16 +235,75 @@ impl KeyPairRepository for KeyPairRepo {
                 tracing::error!(mssag);
                 return Err(KeyPairDynamoDBError(e.to_string()).into());
             }
+        };
+        let mut keys = Vec::new();
+        //the index projects keys only, the item is read from the table
+        for doc in results.items().unwrap_or_default() {
+            let user_id = doc
+                .get(KEYPAIRS_USER_ID_FIELD_PK)
+                .and_then(|v| v.as_s().ok());
+            let user_id = match user_id {
+                None => continue,
+                Some(id) => id.to_owned(),
+            };
+            //legacy rows are evm keys
+            let chain = match doc.get(KEYPAIRS_CHAIN_FIELD_SK).and_then(|v| v.as_s().ok()) {
+                None => ChainFamily::Evm,
+                Some(chain) => ChainFamily::from_str(chain)
+                    .map_err(|_| KeyPairDynamoDBError(format!("unknown chain {}", chain)))?,
+            };
+            keys.push((user_id, chain));
         }
+        Ok(keys)
     }
+}
 
-    async fn get_by_id(&self, user_id: &String) -> ResultE<Option<KeyPair>> {
+#[async_trait]
+impl KeyPairRepository for KeyPairRepo {
+    async fn add(&self, keypair: &KeyPair) -> ResultE<()> {
+        self.put(keypair, false).await
+    }
+
+    async fn update(&self, keypair: &KeyPair) -> ResultE<()> {
+        match self.put(keypair, true).await {
+            Err(e) if e.is::<KeyPairNoExistsError>() && *keypair.chain() == ChainFamily::Evm => {
+                //not migrated yet, updating moves it
+                if self.get_legacy(keypair.user_id()).await?.is_none() {
+                    return Err(e);
+                }
+                match self.put(keypair, false).await {
+                    Err(e) if !e.is::<KeyPairAlreadyExistsError>() => return Err(e),
+                    _ => {}
+                }
+                self.delete_row(keypair.user_id(), None).await?;
+                Ok(())
+            }
+            other => other,
+        }
+    }
+
+    async fn delete(&self, user_id: &String, chain: &ChainFamily) -> ResultE<()> {
+        let deleted = self.delete_row(user_id, Some(chain)).await?;
+        //an evm key not migrated yet is in the legacy table
+        let legacy_deleted = *chain == ChainFamily::Evm && self.delete_row(user_id, None).await?;
+        if !deleted && !legacy_deleted {
+            return Err(write_error(
+                true,
+                format!("{} has no {} keypair", user_id, chain),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn get_by_id(&self, user_id: &String, chain: &ChainFamily) -> ResultE<Option<KeyPair>> {
         let _id_av = AttributeValue::S(user_id.to_string());
+        let chain_av = AttributeValue::S(chain.to_string());
         let request = self
             .client_dynamo
             .get_item()
             .table_name(KEYPAIRS_TABLE_NAME)
-            .key(KEYPAIRS_USER_ID_FIELD_PK, _id_av.clone());
+            .key(KEYPAIRS_USER_ID_FIELD_PK, _id_av.clone())
+            .key(KEYPAIRS_CHAIN_FIELD_SK, chain_av);
 
         let results = request.send().await;
         if let Err(e) = results {
@@ -98,6 +316,8 @@ impl KeyPairRepository for KeyPairRepo {
             return Err(KeyPairDynamoDBError(e.to_string()).into());
         }
         match results.unwrap().item {
+            //evm keys may not be migrated yet
+            None if *chain == ChainFamily::Evm => self.get_legacy(user_id).await,
             None => Ok(None), //Err(KeyPairNoExistsError("id doesn't exist".to_string()).into()),
             Some(aux) => {
                 let mut keypair = KeyPair::new();
@@ -108,6 +328,177 @@ impl KeyPairRepository for KeyPairRepo {
             }
         }
     }
+
+    async fn get_by_address(&self, address: &String) -> ResultE<Option<KeyPair>> {
+        let mut keys = self.address_keys(KEYPAIRS_TABLE_NAME, address).await?;
+        if keys.is_empty() {
+            keys = self
+                .address_keys(KEYPAIRS_LEGACY_TABLE_NAME, address)
+                .await?;
+        }
+        if keys.len() > 1 {
+            let mssag = format!("address {} is held by several keypairs", address);
+            tracing::error!(mssag);
+            return Err(KeyPairAlreadyExistsError(mssag).into());
+        }
+        match keys.first() {
+            Some((user_id, chain)) => self.get_by_id(user_id, chain).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<KeyPair>> {
+        let mut request = self
+            .client_dynamo
+            .scan()
+            .table_name(KEYPAIRS_TABLE_NAME)
+            .limit(*page_size);
+
+        if let Some(crs) = cursor {
+            let start_key = decode_cursor(crs)
+                .ok_or(KeyPairDynamoDBError("malformed pagination cursor".to_string()))?;
+            request = request.set_exclusive_start_key(Some(start_key));
+        }
+
+        let results = request.send().await;
+        match results {
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(KeyPairDynamoDBError(e.to_string()).into());
+            }
+            Ok(data) => {
+                let mut queried = Vec::new();
+                if let Some(docs) = data.items() {
+                    for doc in docs {
+                        if is_hd_counter(doc) {
+                            continue;
+                        }
+                        let mut keypair = KeyPair::new();
+                        mapping_from_doc_to_keypair(doc, &mut keypair);
+                        queried.push(keypair);
+                    }
+                }
+                let next = next_cursor(data.last_evaluated_key());
+                Ok(Page::new(queried, next))
+            }
+        }
+    }
+
+    async fn next_derivation_index(&self, chain: &ChainFamily) -> ResultE<u32> {
+        let request = self
+            .client_dynamo
+            .update_item()
+            .table_name(KEYPAIRS_TABLE_NAME)
+            .key(
+                KEYPAIRS_USER_ID_FIELD_PK,
+                AttributeValue::S(KEYPAIRS_HD_COUNTER_USER_ID.to_string()),
+            )
+            .key(KEYPAIRS_CHAIN_FIELD_SK, AttributeValue::S(chain.to_string()))
+            .update_expression("ADD #idx :one")
+            .expression_attribute_names("#idx", KEYPAIRS_HD_NEXT_INDEX_FIELD)
+            .expression_attribute_values(":one", AttributeValue::N("1".to_string()))
+            .return_values(ReturnValue::UpdatedNew);
+
+        let results = request.send().await;
+        match results {
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(KeyPairDynamoDBError(e.to_string()).into());
+            }
+            Ok(data) => {
+                let next = data
+                    .attributes()
+                    .and_then(|attrs| attrs.get(KEYPAIRS_HD_NEXT_INDEX_FIELD))
+                    .and_then(|v| v.as_n().ok())
+                    .and_then(|v| u32::from_str(v).ok())
+                    .ok_or(KeyPairDynamoDBError("derivation counter missing".to_string()))?;
+                //bip32 indexes above 2^31 are the hardened ones
+                if next > (1 << 31) {
+                    let mssag = "derivation indexes exhausted".to_string();
+                    return Err(KeyPairDerivationError(mssag).into());
+                }
+                //the counter holds the next free one once incremented
+                Ok(next - 1)
+            }
+        }
+    }
+
+    async fn migrate_legacy(
+        &self,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<KeyPair>> {
+        let mut request = self
+            .client_dynamo
+            .scan()
+            .table_name(KEYPAIRS_LEGACY_TABLE_NAME)
+            .limit(*page_size);
+
+        if let Some(crs) = cursor {
+            let start_key = decode_cursor(crs)
+                .ok_or(KeyPairDynamoDBError("malformed pagination cursor".to_string()))?;
+            request = request.set_exclusive_start_key(Some(start_key));
+        }
+
+        let data = match request.send().await {
+            Ok(data) => data,
+            Err(e) => {
+                let no_table = e
+                    .as_service_error()
+                    .map(|se| se.is_resource_not_found_exception())
+                    .unwrap_or(false);
+                if no_table {
+                    return Ok(Page::new(Vec::new(), None));
+                }
+                return Err(write_error(false, e.to_string()));
+            }
+        };
+        let mut moved = Vec::new();
+        for doc in data.items().unwrap_or_default() {
+            let mut keypair = KeyPair::new();
+            mapping_from_doc_to_keypair(doc, &mut keypair);
+            //a row there already was written after this one
+            match self.put(&keypair, false).await {
+                Ok(()) => moved.push(keypair.clone()),
+                Err(e) if e.is::<KeyPairAlreadyExistsError>() => {}
+                Err(e) => return Err(e),
+            }
+            self.delete_row(keypair.user_id(), None).await?;
+        }
+        let next = next_cursor(data.last_evaluated_key());
+        Ok(Page::new(moved, next))
+    }
+}
+
+fn write_error(missing: bool, e: String) -> Box<dyn std::error::Error + Sync + Send> {
+    let mssag = format!(
+        "Error at [{}] - {} ",
+        Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+        e
+    );
+    tracing::error!(mssag);
+    if missing {
+        KeyPairNoExistsError("keypair doesn't exist".to_string()).into()
+    } else {
+        KeyPairDynamoDBError(e).into()
+    }
+}
+
+fn is_hd_counter(doc: &HashMap<String, AttributeValue>) -> bool {
+    match doc.get(KEYPAIRS_USER_ID_FIELD_PK) {
+        Some(AttributeValue::S(id)) => id == KEYPAIRS_HD_COUNTER_USER_ID,
+        _ => false,
+    }
 }
 
 fn iso8601(st: &DateTime<Utc>) -> String {
@@ -126,6 +517,13 @@ pub fn mapping_from_doc_to_keypair(doc: &HashMap<String, AttributeValue>, keypai
     //let uuid = Uuid::from_str(keypair_id).unwrap();
     keypair.set_user_id(&user_id);
 
+    //legacy rows, keyed by user alone, held evm keys only
+    let chain = match doc.get(KEYPAIRS_CHAIN_FIELD_SK) {
+        None => ChainFamily::Evm,
+        Some(chain) => ChainFamily::from_str(chain.as_s().unwrap()).unwrap(),
+    };
+    keypair.set_chain(&chain);
+
     let _address = doc.get(KEYPAIRS_ADDRESS_FIELD).unwrap();
     let address = _address.as_s().unwrap();
     keypair.set_address(address);
@@ -136,7 +534,16 @@ pub fn mapping_from_doc_to_keypair(doc: &HashMap<String, AttributeValue>, keypai
 
     let _private_key = doc.get(KEYPAIRS_PRIVATE_FIELD).unwrap();
     let private_key = _private_key.as_s().unwrap();
-    keypair.set_private_key(private_key);
+    keypair.set_private_key(&Secret::from(private_key.to_owned()));
+
+    if let Some(key_id) = doc.get(KEYPAIRS_KEY_ID_FIELD) {
+        keypair.set_key_id(key_id.as_s().unwrap());
+    }
+
+    if let Some(index) = doc.get(KEYPAIRS_DERIVATION_INDEX_FIELD) {
+        let index = u32::from_str(index.as_n().unwrap()).unwrap();
+        keypair.set_derivation_index(&index);
+    }
 
     let creation_time_t = doc.get(CREATIONTIME_FIELD_NAME);
     match creation_time_t {
@@ -2,7 +2,11 @@ pub mod block_tx;
 pub mod blockchain;
 pub mod contract;
 pub mod keypairs;
+pub mod pagination;
+pub mod prices;
 pub mod schema_block_tx;
 pub mod schema_blockchain;
 pub mod schema_contract;
 pub mod schema_keypairs;
+pub mod schema_user_data_keys;
+pub mod user_data_keys;
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::AttributeValue;
+use base64::{engine::general_purpose, Engine};
+
+// DynamoDB hands back the last evaluated key as a map of attribute values.
+// We expose it to callers as an url-safe string so it can travel through
+// http query params and come back untouched.
+pub fn encode_cursor(key: &HashMap<String, AttributeValue>) -> String {
+    let mut plain: HashMap<String, (String, String)> = HashMap::new();
+    for (name, value) in key {
+        match value {
+            AttributeValue::S(val) => {
+                plain.insert(name.clone(), ("S".to_string(), val.clone()));
+            }
+            AttributeValue::N(val) => {
+                plain.insert(name.clone(), ("N".to_string(), val.clone()));
+            }
+            _ => {}
+        }
+    }
+    let json = serde_json::to_string(&plain).unwrap();
+    general_purpose::URL_SAFE_NO_PAD.encode(json)
+}
+
+pub fn decode_cursor(cursor: &String) -> Option<HashMap<String, AttributeValue>> {
+    let json = general_purpose::URL_SAFE_NO_PAD.decode(cursor).ok()?;
+    let plain: HashMap<String, (String, String)> = serde_json::from_slice(&json).ok()?;
+
+    let mut key = HashMap::new();
+    for (name, (kind, val)) in plain {
+        let av = match kind.as_str() {
+            "S" => AttributeValue::S(val),
+            "N" => AttributeValue::N(val),
+            _ => return None,
+        };
+        key.insert(name, av);
+    }
+    Some(key)
+}
+
+pub fn next_cursor(last_key: Option<&HashMap<String, AttributeValue>>) -> Option<String> {
+    match last_key {
+        None => None,
+        Some(key) if key.is_empty() => None,
+        Some(key) => Some(encode_cursor(key)),
+    }
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use url::Url;
+
+use crate::errors::price::PriceSourceError;
+use crate::models::amount::Currency;
+use crate::models::fiat::FiatRate;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+/// Source of native coin / fiat exchange rates.
+#[async_trait]
+pub trait PriceRepository: Send + Sync + CloneBoxPriceRepository {
+    async fn rate(&self, currency: &Currency, fiat: &String) -> ResultE<FiatRate>;
+}
+
+impl fmt::Debug for dyn PriceRepository + Sync + Send {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PriceSource")
+    }
+}
+
+pub trait CloneBoxPriceRepository {
+    fn clone_box(&self) -> Box<dyn PriceRepository + Sync + Send>;
+}
+
+impl<T> CloneBoxPriceRepository for T
+where
+    T: 'static + PriceRepository + Clone + Send + Sync,
+{
+    fn clone_box(&self) -> Box<dyn PriceRepository + Send + Sync> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn PriceRepository + Send + Sync> {
+    fn clone(&self) -> Box<dyn PriceRepository + Send + Sync> {
+        self.clone_box()
+    }
+}
+
+// rates may come as json strings or numbers, strings keep every decimal
+fn rate_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Fixed rates read from a json file, i.e. `{ "ETH": { "EUR": "1843.27" }, "SUI": { "EUR": "0.52" } }`
+#[derive(Clone, Debug)]
+pub struct StaticPriceRepo {
+    source: String,
+    loaded_at: DateTime<Utc>,
+    rates: HashMap<String, HashMap<String, Value>>,
+}
+
+impl StaticPriceRepo {
+    pub fn from_file(path: &PathBuf) -> ResultE<StaticPriceRepo> {
+        let content = std::fs::read_to_string(path)?;
+        let mut repo = StaticPriceRepo::from_json(&content)?;
+        repo.source = format!("file:{}", path.display());
+        Ok(repo)
+    }
+
+    pub fn from_json(content: &String) -> ResultE<StaticPriceRepo> {
+        let rates: HashMap<String, HashMap<String, Value>> = serde_json::from_str(content)?;
+        Ok(StaticPriceRepo {
+            source: "static".to_string(),
+            loaded_at: Utc::now(),
+            rates,
+        })
+    }
+}
+
+#[async_trait]
+impl PriceRepository for StaticPriceRepo {
+    async fn rate(&self, currency: &Currency, fiat: &String) -> ResultE<FiatRate> {
+        let fiat = fiat.to_uppercase();
+        let rate = self
+            .rates
+            .get(&currency.to_string())
+            .and_then(|fiats| fiats.get(&fiat))
+            .and_then(rate_as_string)
+            .ok_or(PriceSourceError(format!(
+                "no {}/{} rate at {}",
+                currency, fiat, self.source
+            )))?;
+
+        Ok(FiatRate::new(
+            *currency,
+            &fiat,
+            &rate,
+            &self.source,
+            self.loaded_at,
+        )?)
+    }
+}
+
+#[derive(Deserialize)]
+struct HttpRate {
+    rate: Value,
+    quoted_at: Option<DateTime<Utc>>,
+}
+
+/// Rates served over http: `GET {url}/{currency}/{fiat}` answering
+/// `{ "rate": "1843.27", "quoted_at": "2023-07-20T10:00:00Z" }`, `quoted_at` optional.
+#[derive(Clone, Debug)]
+pub struct HttpPriceRepo {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl HttpPriceRepo {
+    pub fn new(url: &Url) -> HttpPriceRepo {
+        HttpPriceRepo {
+            url: url.to_owned(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceRepository for HttpPriceRepo {
+    #[tracing::instrument()]
+    async fn rate(&self, currency: &Currency, fiat: &String) -> ResultE<FiatRate> {
+        let fiat = fiat.to_uppercase();
+        let endpoint = format!(
+            "{}/{}/{}",
+            self.url.as_str().trim_end_matches('/'),
+            currency,
+            fiat
+        );
+
+        let response = self.client.get(endpoint.as_str()).send().await?;
+        if !response.status().is_success() {
+            return Err(PriceSourceError(format!(
+                "{} answered {}",
+                endpoint,
+                response.status()
+            ))
+            .into());
+        }
+        let body: HttpRate = response.json().await?;
+        let rate = rate_as_string(&body.rate)
+            .ok_or(PriceSourceError(format!("{} sent a malformed rate", endpoint)))?;
+
+        Ok(FiatRate::new(
+            *currency,
+            &fiat,
+            &rate,
+            &self.url.to_string(),
+            body.quoted_at.unwrap_or(Utc::now()),
+        )?)
+    }
+}
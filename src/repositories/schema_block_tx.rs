@@ -1,8 +1,9 @@
 use async_trait::async_trait;
 use aws_sdk_dynamodb::types::{
-    builders::StreamSpecificationBuilder, AttributeDefinition, BillingMode, GlobalSecondaryIndex,
-    KeySchemaElement, KeyType, Projection, ProjectionType, ScalarAttributeType, StreamViewType,
-    Tag,
+    builders::StreamSpecificationBuilder, AttributeDefinition, BillingMode,
+    CreateGlobalSecondaryIndexAction, GlobalSecondaryIndex, GlobalSecondaryIndexUpdate,
+    IndexStatus, KeySchemaElement, KeyType, Projection, ProjectionType, ScalarAttributeType,
+    StreamViewType, Tag,
 };
 use lib_config::{
     config::Config,
@@ -20,8 +21,100 @@ pub const TX_ASSET_ID_FIELD_PK: &str = "assetId";
 //pub const TX_TIMESTAMP_PK: &str = "timestamp";
 pub const TX_FIELD: &str = "tx";
 pub const TX_INDEX_NAME: &str = "tx_index";
+pub const TX_USER_ID_FIELD: &str = "user_id";
+pub const TX_USER_INDEX_NAME: &str = "user_index";
+pub const TX_CONTRACT_ID_FIELD: &str = "contract_id";
+pub const TX_CONTRACT_INDEX_NAME: &str = "contract_index";
+pub const TX_MINTED_STATUS_FIELD: &str = "minting_status";
+pub const TX_STATUS_INDEX_NAME: &str = "status_index";
+pub const TX_CREATION_DAY_FIELD: &str = "creation_day";
+pub const TX_CREATION_DAY_INDEX_NAME: &str = "creation_day_index";
+pub const TX_CREATION_TIME_FIELD: &str = "creation_time";
+// listing indexes added after the table was first deployed, with their
+// hash key and its type
+const TX_LISTING_INDEXES: [(&str, &str, ScalarAttributeType); 4] = [
+    (TX_USER_INDEX_NAME, TX_USER_ID_FIELD, ScalarAttributeType::S),
+    (
+        TX_CONTRACT_INDEX_NAME,
+        TX_CONTRACT_ID_FIELD,
+        ScalarAttributeType::N,
+    ),
+    (
+        TX_STATUS_INDEX_NAME,
+        TX_MINTED_STATUS_FIELD,
+        ScalarAttributeType::S,
+    ),
+    (
+        TX_CREATION_DAY_INDEX_NAME,
+        TX_CREATION_DAY_FIELD,
+        ScalarAttributeType::S,
+    ),
+];
 pub struct BlockTxSchema;
 
+impl BlockTxSchema {
+    // brings a table created before the listing indexes up to date. dynamodb
+    // builds one index at a time, so each call asks for the next missing one
+    // once the previous is active. Call it until it returns true
+    pub async fn add_missing_indexes(config: &Config) -> ResultE<bool> {
+        let client = aws_sdk_dynamodb::Client::new(config.aws_config());
+
+        let described = client
+            .describe_table()
+            .table_name(TX_TABLE_NAME)
+            .send()
+            .await?;
+        let existing = described
+            .table()
+            .and_then(|table| table.global_secondary_indexes())
+            .unwrap_or_default();
+        if existing
+            .iter()
+            .any(|index| index.index_status() != Some(&IndexStatus::Active))
+        {
+            return Ok(false);
+        }
+
+        let missing = TX_LISTING_INDEXES.iter().find(|(name, _, _)| {
+            !existing
+                .iter()
+                .any(|index| index.index_name() == Some(*name))
+        });
+        let (index_name, hash_field, hash_type) = match missing {
+            None => return Ok(true),
+            Some(index) => index,
+        };
+        let index = by_creation_time_index(index_name, hash_field);
+        let create = CreateGlobalSecondaryIndexAction::builder()
+            .index_name(*index_name)
+            .set_key_schema(index.key_schema().map(|keys| keys.to_vec()))
+            .set_projection(index.projection().cloned())
+            .build();
+
+        client
+            .update_table()
+            .table_name(TX_TABLE_NAME)
+            .attribute_definitions(
+                AttributeDefinition::builder()
+                    .attribute_name(*hash_field)
+                    .attribute_type(hash_type.clone())
+                    .build(),
+            )
+            .attribute_definitions(
+                AttributeDefinition::builder()
+                    .attribute_name(TX_CREATION_TIME_FIELD)
+                    .attribute_type(ScalarAttributeType::S)
+                    .build(),
+            )
+            .global_secondary_index_updates(
+                GlobalSecondaryIndexUpdate::builder().create(create).build(),
+            )
+            .send()
+            .await?;
+        Ok(false)
+    }
+}
+
 #[async_trait]
 impl Schema for BlockTxSchema {
     async fn create_schema(config: &Config) -> ResultE<()> {
@@ -40,6 +133,27 @@ impl Schema for BlockTxSchema {
             .attribute_type(ScalarAttributeType::S)
             .build();
 
+        let user_ad = AttributeDefinition::builder()
+            .attribute_name(TX_USER_ID_FIELD)
+            .attribute_type(ScalarAttributeType::S)
+            .build();
+        let contract_ad = AttributeDefinition::builder()
+            .attribute_name(TX_CONTRACT_ID_FIELD)
+            .attribute_type(ScalarAttributeType::N)
+            .build();
+        let status_ad = AttributeDefinition::builder()
+            .attribute_name(TX_MINTED_STATUS_FIELD)
+            .attribute_type(ScalarAttributeType::S)
+            .build();
+        let day_ad = AttributeDefinition::builder()
+            .attribute_name(TX_CREATION_DAY_FIELD)
+            .attribute_type(ScalarAttributeType::S)
+            .build();
+        let time_ad = AttributeDefinition::builder()
+            .attribute_name(TX_CREATION_TIME_FIELD)
+            .attribute_type(ScalarAttributeType::S)
+            .build();
+
         let ks = KeySchemaElement::builder()
             .attribute_name(TX_ASSET_ID_FIELD_PK)
             .key_type(KeyType::Hash)
@@ -64,15 +178,29 @@ impl Schema for BlockTxSchema {
             )
             .build();
 
+        let user_index = by_creation_time_index(TX_USER_INDEX_NAME, TX_USER_ID_FIELD);
+        let contract_index = by_creation_time_index(TX_CONTRACT_INDEX_NAME, TX_CONTRACT_ID_FIELD);
+        let status_index = by_creation_time_index(TX_STATUS_INDEX_NAME, TX_MINTED_STATUS_FIELD);
+        let day_index = by_creation_time_index(TX_CREATION_DAY_INDEX_NAME, TX_CREATION_DAY_FIELD);
+
         let op = client
             .create_table()
             .table_name(TX_TABLE_NAME)
             .key_schema(ks)
             //.key_schema(ks2)
             .global_secondary_indexes(second_index)
+            .global_secondary_indexes(user_index)
+            .global_secondary_indexes(contract_index)
+            .global_secondary_indexes(status_index)
+            .global_secondary_indexes(day_index)
             .attribute_definitions(asset_ad)
             //.attribute_definitions(time_ad)
             .attribute_definitions(tx_ad)
+            .attribute_definitions(user_ad)
+            .attribute_definitions(contract_ad)
+            .attribute_definitions(status_ad)
+            .attribute_definitions(day_ad)
+            .attribute_definitions(time_ad)
             .billing_mode(BillingMode::PayPerRequest)
             .stream_specification(
                 StreamSpecificationBuilder::default()
@@ -116,3 +244,28 @@ impl Schema for BlockTxSchema {
         Ok(())
     }
 }
+
+// all the listing indexes are sorted by creation time, so any of them can be
+// narrowed down to a date range. Full projection to avoid a second round-trip.
+fn by_creation_time_index(index_name: &str, hash_field: &str) -> GlobalSecondaryIndex {
+    GlobalSecondaryIndex::builder()
+        .index_name(index_name)
+        .key_schema(
+            KeySchemaElement::builder()
+                .attribute_name(hash_field)
+                .key_type(KeyType::Hash)
+                .build(),
+        )
+        .key_schema(
+            KeySchemaElement::builder()
+                .attribute_name(TX_CREATION_TIME_FIELD)
+                .key_type(KeyType::Range)
+                .build(),
+        )
+        .projection(
+            Projection::builder()
+                .projection_type(ProjectionType::All)
+                .build(),
+        )
+        .build()
+}
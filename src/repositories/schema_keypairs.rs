@@ -14,12 +14,21 @@ use lib_config::{
     schema::Schema,
 };
 
-pub const KEYPAIRS_TABLE_NAME: &str = "truly_users_keypairs";
+pub const KEYPAIRS_TABLE_NAME: &str = "truly_users_chain_keypairs";
+// keyed by user alone, it holds the evm keys stored before keypairs were
+// scoped per chain family. Read until `migrate_legacy` has emptied it
+pub const KEYPAIRS_LEGACY_TABLE_NAME: &str = "truly_users_keypairs";
 pub const KEYPAIRS_USER_ID_FIELD_PK: &str = "userId";
+pub const KEYPAIRS_CHAIN_FIELD_SK: &str = "chain";
 pub const KEYPAIRS_ADDRESS_FIELD: &str = "address";
 pub const KEYPAIRS_ADDRESS_INDEX_NAME: &str = "address_index";
 pub const KEYPAIRS_PUBLIC_FIELD: &str = "public_key_enc";
 pub const KEYPAIRS_PRIVATE_FIELD: &str = "private_key_enc";
+pub const KEYPAIRS_KEY_ID_FIELD: &str = "kms_key_id";
+pub const KEYPAIRS_DERIVATION_INDEX_FIELD: &str = "derivation_index";
+// reserved item per chain family holding the next free derivation index
+pub const KEYPAIRS_HD_COUNTER_USER_ID: &str = "#hd_counter";
+pub const KEYPAIRS_HD_NEXT_INDEX_FIELD: &str = "next_index";
 
 pub struct KeyPairSchema;
 #[async_trait]
@@ -35,11 +44,20 @@ impl Schema for KeyPairSchema {
             .attribute_name(KEYPAIRS_ADDRESS_FIELD)
             .attribute_type(ScalarAttributeType::S)
             .build();
+        let ad3 = AttributeDefinition::builder()
+            .attribute_name(KEYPAIRS_CHAIN_FIELD_SK)
+            .attribute_type(ScalarAttributeType::S)
+            .build();
 
         let ks_by_user_id = KeySchemaElement::builder()
             .attribute_name(KEYPAIRS_USER_ID_FIELD_PK)
             .key_type(KeyType::Hash)
             .build();
+        // a user holds one keypair per chain family
+        let ks_by_chain = KeySchemaElement::builder()
+            .attribute_name(KEYPAIRS_CHAIN_FIELD_SK)
+            .key_type(KeyType::Range)
+            .build();
 
         let second_index = GlobalSecondaryIndex::builder()
             .index_name(KEYPAIRS_ADDRESS_INDEX_NAME)
@@ -60,9 +78,11 @@ impl Schema for KeyPairSchema {
             .create_table()
             .table_name(KEYPAIRS_TABLE_NAME)
             .key_schema(ks_by_user_id)
+            .key_schema(ks_by_chain)
             .global_secondary_indexes(second_index)
             .attribute_definitions(ad1)
             .attribute_definitions(ad2)
+            .attribute_definitions(ad3)
             .billing_mode(BillingMode::PayPerRequest)
             .stream_specification(
                 StreamSpecificationBuilder::default()
@@ -0,0 +1,78 @@
+use crate::SERVICE;
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, BillingMode, KeySchemaElement, KeyType, ScalarAttributeType, Tag,
+};
+use lib_config::{
+    config::Config,
+    environment::{
+        ENV_VAR_ENVIRONMENT, ENV_VAR_PROJECT, ENV_VAR_PROJECT_LABEL, ENV_VAR_SERVICE_LABEL,
+    },
+    result::ResultE,
+    schema::Schema,
+};
+
+pub const USER_DATA_KEYS_TABLE_NAME: &str = "truly_users_data_keys";
+pub const USER_DATA_KEYS_USER_ID_FIELD_PK: &str = "userId";
+pub const USER_DATA_KEYS_WRAPPED_FIELD: &str = "wrapped_key";
+pub const USER_DATA_KEYS_KEY_ID_FIELD: &str = "kms_key_id";
+pub const USER_DATA_KEYS_CREATION_TIME_FIELD: &str = "creationTime";
+
+pub struct UserDataKeySchema;
+
+#[async_trait]
+impl Schema for UserDataKeySchema {
+    async fn create_schema(config: &Config) -> ResultE<()> {
+        let client = aws_sdk_dynamodb::Client::new(config.aws_config());
+        let id_ad = AttributeDefinition::builder()
+            .attribute_name(USER_DATA_KEYS_USER_ID_FIELD_PK)
+            .attribute_type(ScalarAttributeType::S)
+            .build();
+
+        let ks1 = KeySchemaElement::builder()
+            .attribute_name(USER_DATA_KEYS_USER_ID_FIELD_PK)
+            .key_type(KeyType::Hash)
+            .build();
+
+        let op = client
+            .create_table()
+            .table_name(USER_DATA_KEYS_TABLE_NAME)
+            .key_schema(ks1)
+            .attribute_definitions(id_ad)
+            .billing_mode(BillingMode::PayPerRequest)
+            // no stream: it would keep copies of keys meant to be shredded
+            .tags(
+                Tag::builder()
+                    .set_key(Some(ENV_VAR_ENVIRONMENT.to_string()))
+                    .set_value(Some(config.env_vars().environment().unwrap()))
+                    .build(),
+            )
+            .tags(
+                Tag::builder()
+                    .set_key(Some(ENV_VAR_PROJECT_LABEL.to_string()))
+                    .set_value(Some(ENV_VAR_PROJECT.to_string()))
+                    .build(),
+            )
+            .tags(
+                Tag::builder()
+                    .set_key(Some(ENV_VAR_SERVICE_LABEL.to_string()))
+                    .set_value(Some(SERVICE.to_string()))
+                    .build(),
+            );
+        let op = op.send().await;
+        match op {
+            Err(e) => return Err(e.into()),
+            Ok(_) => Ok(()),
+        }
+    }
+    async fn delete_schema(config: &Config) -> ResultE<()> {
+        let client = aws_sdk_dynamodb::Client::new(config.aws_config());
+        client
+            .delete_table()
+            .table_name(USER_DATA_KEYS_TABLE_NAME)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
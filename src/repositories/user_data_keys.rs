@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+
+use crate::errors::keypair::{KeyPairAlreadyExistsError, KeyPairDynamoDBError};
+use crate::models::user_data_key::UserDataKey;
+use async_trait::async_trait;
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+use chrono::{
+    prelude::{DateTime, Utc},
+    Local,
+};
+use lib_config::config::Config;
+
+use super::schema_user_data_keys::{
+    USER_DATA_KEYS_CREATION_TIME_FIELD, USER_DATA_KEYS_KEY_ID_FIELD, USER_DATA_KEYS_TABLE_NAME,
+    USER_DATA_KEYS_USER_ID_FIELD_PK, USER_DATA_KEYS_WRAPPED_FIELD,
+};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+#[async_trait]
+pub trait UserDataKeyRepository {
+    // fails with KeyPairAlreadyExistsError if the user already has one
+    async fn add(&self, data_key: &UserDataKey) -> ResultE<()>;
+    async fn update(&self, data_key: &UserDataKey) -> ResultE<()>;
+    async fn get_by_id(&self, user_id: &String) -> ResultE<Option<UserDataKey>>;
+    // true if there was a key to delete
+    async fn delete(&self, user_id: &String) -> ResultE<bool>;
+}
+
+#[derive(Clone, Debug)]
+pub struct UserDataKeyRepo {
+    client_dynamo: aws_sdk_dynamodb::Client,
+}
+
+impl UserDataKeyRepo {
+    pub fn new(conf: &Config) -> UserDataKeyRepo {
+        UserDataKeyRepo {
+            client_dynamo: aws_sdk_dynamodb::Client::new(conf.aws_config()),
+        }
+    }
+
+    async fn put(&self, data_key: &UserDataKey, existing: bool) -> ResultE<()> {
+        let condition = if existing {
+            format!("attribute_exists({})", USER_DATA_KEYS_USER_ID_FIELD_PK)
+        } else {
+            format!("attribute_not_exists({})", USER_DATA_KEYS_USER_ID_FIELD_PK)
+        };
+        let request = self
+            .client_dynamo
+            .put_item()
+            .table_name(USER_DATA_KEYS_TABLE_NAME)
+            .item(
+                USER_DATA_KEYS_USER_ID_FIELD_PK,
+                AttributeValue::S(data_key.user_id().to_string()),
+            )
+            .item(
+                USER_DATA_KEYS_WRAPPED_FIELD,
+                AttributeValue::S(data_key.wrapped_key().to_string()),
+            )
+            .item(
+                USER_DATA_KEYS_KEY_ID_FIELD,
+                AttributeValue::S(data_key.key_id().to_string()),
+            )
+            .item(
+                USER_DATA_KEYS_CREATION_TIME_FIELD,
+                AttributeValue::S(iso8601(data_key.creation_time())),
+            )
+            .condition_expression(condition);
+
+        match request.send().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let failed_check = e
+                    .as_service_error()
+                    .map(|se| se.is_conditional_check_failed_exception())
+                    .unwrap_or(false);
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                if failed_check && !existing {
+                    return Err(KeyPairAlreadyExistsError(data_key.user_id().to_string()).into());
+                }
+                return Err(KeyPairDynamoDBError(e.to_string()).into());
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl UserDataKeyRepository for UserDataKeyRepo {
+    async fn add(&self, data_key: &UserDataKey) -> ResultE<()> {
+        self.put(data_key, false).await
+    }
+
+    async fn update(&self, data_key: &UserDataKey) -> ResultE<()> {
+        self.put(data_key, true).await
+    }
+
+    async fn get_by_id(&self, user_id: &String) -> ResultE<Option<UserDataKey>> {
+        let request = self
+            .client_dynamo
+            .get_item()
+            .table_name(USER_DATA_KEYS_TABLE_NAME)
+            .key(
+                USER_DATA_KEYS_USER_ID_FIELD_PK,
+                AttributeValue::S(user_id.to_string()),
+            )
+            //a key read right after being shredded must not come back
+            .consistent_read(true);
+
+        let results = request.send().await;
+        if let Err(e) = results {
+            let mssag = format!(
+                "Error at [{}] - {} ",
+                Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                e
+            );
+            tracing::error!(mssag);
+            return Err(KeyPairDynamoDBError(e.to_string()).into());
+        }
+        match results.unwrap().item {
+            None => Ok(None),
+            Some(aux) => {
+                let mut data_key = UserDataKey::new();
+                mapping_from_doc_to_user_data_key(&aux, &mut data_key);
+                Ok(Some(data_key))
+            }
+        }
+    }
+
+    async fn delete(&self, user_id: &String) -> ResultE<bool> {
+        let request = self
+            .client_dynamo
+            .delete_item()
+            .table_name(USER_DATA_KEYS_TABLE_NAME)
+            .key(
+                USER_DATA_KEYS_USER_ID_FIELD_PK,
+                AttributeValue::S(user_id.to_string()),
+            )
+            .return_values(ReturnValue::AllOld);
+
+        match request.send().await {
+            Ok(output) => Ok(output.attributes().is_some()),
+            Err(e) => {
+                let mssag = format!(
+                    "Error at [{}] - {} ",
+                    Local::now().format("%m-%d-%Y %H:%M:%S").to_string(),
+                    e
+                );
+                tracing::error!(mssag);
+                return Err(KeyPairDynamoDBError(e.to_string()).into());
+            }
+        }
+    }
+}
+
+fn iso8601(st: &DateTime<Utc>) -> String {
+    let dt: DateTime<Utc> = st.clone().into();
+    format!("{}", dt.format("%+"))
+}
+
+fn from_iso8601(st: &String) -> DateTime<Utc> {
+    let aux = st.parse::<DateTime<Utc>>().unwrap();
+    aux
+}
+
+pub fn mapping_from_doc_to_user_data_key(
+    doc: &HashMap<String, AttributeValue>,
+    data_key: &mut UserDataKey,
+) {
+    let user_id = doc.get(USER_DATA_KEYS_USER_ID_FIELD_PK).unwrap();
+    data_key.set_user_id(user_id.as_s().unwrap());
+
+    let wrapped = doc.get(USER_DATA_KEYS_WRAPPED_FIELD).unwrap();
+    data_key.set_wrapped_key(wrapped.as_s().unwrap());
+
+    let key_id = doc.get(USER_DATA_KEYS_KEY_ID_FIELD).unwrap();
+    data_key.set_key_id(key_id.as_s().unwrap());
+
+    if let Some(creation_time) = doc.get(USER_DATA_KEYS_CREATION_TIME_FIELD) {
+        data_key.set_creation_time(&from_iso8601(creation_time.as_s().unwrap()));
+    }
+}
@@ -1,6 +1,8 @@
-use crate::models::block_tx::BlockchainTx;
+use crate::models::block_tx::{BlockchainTx, MintingStatus};
+use crate::models::page::Page;
 use crate::repositories::block_tx::{BlockchainTxRepo, BlockchainTxRepository};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
 
@@ -10,6 +12,38 @@ pub trait BlockchainTxManipulation {
     async fn update(&self, tx: &BlockchainTx) -> ResultE<()>;
     async fn get_by_id(&self, hash: &String) -> ResultE<BlockchainTx>;
     async fn get_by_asset_id(&self, asset_id: &Uuid) -> ResultE<BlockchainTx>;
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_user(
+        &self,
+        user_id: &String,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_contract(
+        &self,
+        contract_id: &u16,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_status(
+        &self,
+        status: &MintingStatus,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
+    async fn get_by_creation_time(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>>;
 }
 
 #[derive(Debug)]
@@ -42,6 +76,65 @@ impl BlockchainTxManipulation for BlockchainTxService {
     async fn get_by_asset_id(&self, asset_id: &Uuid) -> ResultE<BlockchainTx> {
         self.repository.get_by_asset_id(asset_id).await
     }
+
+    #[tracing::instrument()]
+    async fn list(&self, page_size: &i32, cursor: &Option<String>) -> ResultE<Page<BlockchainTx>> {
+        self.repository.list(page_size, cursor).await
+    }
+    #[tracing::instrument()]
+    async fn get_by_user(
+        &self,
+        user_id: &String,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        self.repository
+            .get_by_user(user_id, from, to, page_size, cursor)
+            .await
+    }
+
+    #[tracing::instrument()]
+    async fn get_by_contract(
+        &self,
+        contract_id: &u16,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        self.repository
+            .get_by_contract(contract_id, from, to, page_size, cursor)
+            .await
+    }
+
+    #[tracing::instrument()]
+    async fn get_by_status(
+        &self,
+        status: &MintingStatus,
+        from: &Option<DateTime<Utc>>,
+        to: &Option<DateTime<Utc>>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        self.repository
+            .get_by_status(status, from, to, page_size, cursor)
+            .await
+    }
+
+    #[tracing::instrument()]
+    async fn get_by_creation_time(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        page_size: &i32,
+        cursor: &Option<String>,
+    ) -> ResultE<Page<BlockchainTx>> {
+        self.repository
+            .get_by_creation_time(from, to, page_size, cursor)
+            .await
+    }
 }
 
 impl Clone for BlockchainTxService {
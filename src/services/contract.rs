@@ -6,6 +6,9 @@ use web3::{
 };
 
 /// run it after local ganache bootstrapped.
+/// Only for node-unlocked accounts and records nothing,
+/// `services::deployment::DeploymentService` signs with the owner key and
+/// registers the contract.
 
 pub async fn deploy_evm_contract_locally(
     url: &str,
@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::models::amount::{Amount, Currency, Unit};
+use crate::models::block_tx::{BlockchainTx, MintingStatus};
+use crate::models::fiat::FiatValuation;
+use crate::repositories::contract::{ContractRepo, ContractRepository};
+
+use super::block_tx::{BlockchainTxManipulation, BlockchainTxService};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+const PAGE_SIZE: i32 = 200;
+
+#[async_trait]
+pub trait CostReporting {
+    async fn cost_report(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        contract_id: &Option<u16>,
+    ) -> ResultE<CostReport>;
+}
+
+/// Aggregates the gas spent by completed mints, grouped by contract,
+/// blockchain and day. Amounts are kept in the base unit of each chain.
+#[derive(Debug)]
+pub struct CostReportService {
+    tx_service: BlockchainTxService,
+    contracts_repo: ContractRepo,
+}
+
+impl CostReportService {
+    pub fn new(tx_service: BlockchainTxService, contracts_repo: ContractRepo) -> CostReportService {
+        CostReportService {
+            tx_service,
+            contracts_repo,
+        }
+    }
+
+    async fn completed_txs(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        contract_id: &Option<u16>,
+    ) -> ResultE<Vec<BlockchainTx>> {
+        let mut found = Vec::new();
+        let mut cursor = None;
+        loop {
+            //mirrored registrations are indexed by their primary contract
+            //only, so the window is walked whole and their anchors expanded
+            let page = self
+                .tx_service
+                .get_by_creation_time(from, to, &PAGE_SIZE, &cursor)
+                .await?;
+            cursor = page.next_cursor().clone();
+            for tx in page.into_items().iter().flat_map(|row| row.per_contract()) {
+                if contract_id.is_some() && *tx.contract_id() != *contract_id {
+                    continue;
+                }
+                if tx.mint_status() == MintingStatus::CompletedSuccessfully && tx.cost().is_some() {
+                    found.push(tx);
+                }
+            }
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[async_trait]
+impl CostReporting for CostReportService {
+    #[tracing::instrument()]
+    async fn cost_report(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        contract_id: &Option<u16>,
+    ) -> ResultE<CostReport> {
+        let txs = self.completed_txs(from, to, contract_id).await?;
+
+        let mut blockchains: HashMap<u16, String> = HashMap::new();
+        let mut per_day: BTreeMap<GroupKey, Samples> = BTreeMap::new();
+        let mut per_window: BTreeMap<GroupKey, Samples> = BTreeMap::new();
+
+        for tx in txs {
+            let cost = tx.cost().unwrap();
+            let contract = tx.contract_id().unwrap_or_default();
+            if !blockchains.contains_key(&contract) {
+                let blockchain = match self.contracts_repo.get_by_id(&contract).await {
+                    Ok(cnt) => cnt.blockchain().to_owned(),
+                    Err(e) => {
+                        //still reported, grouped apart from the known chains
+                        let mssag = format!("contract {} of the cost report: {}", contract, e);
+                        tracing::error!(mssag);
+                        "unknown".to_string()
+                    }
+                };
+                blockchains.insert(contract, blockchain);
+            }
+            let blockchain = blockchains.get(&contract).unwrap().to_owned();
+
+            let key = GroupKey {
+                contract_id: contract,
+                blockchain,
+                currency: cost.currency(),
+                day: None,
+            };
+            let mut day_key = key.clone();
+            day_key.day = Some(tx.creation_time().date_naive());
+
+            let base = cost.base_value()?;
+            let gas = tx.gas_used().unwrap_or_default();
+            let fiat = match tx.fiat_cost() {
+                None => None,
+                Some(val) => Some((val.fiat().to_owned(), val.value_units()?)),
+            };
+            per_day.entry(day_key).or_default().push(base, gas, &fiat);
+            per_window.entry(key).or_default().push(base, gas, &fiat);
+        }
+
+        let mut days = Vec::new();
+        for (key, samples) in per_day {
+            days.push(samples.into_row(key)?);
+        }
+        let mut totals = Vec::new();
+        for (key, samples) in per_window {
+            totals.push(samples.into_row(key)?);
+        }
+
+        Ok(CostReport {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            generated_at: Utc::now(),
+            days,
+            totals,
+        })
+    }
+}
+
+impl Clone for CostReportService {
+    #[tracing::instrument()]
+    fn clone(&self) -> CostReportService {
+        let aux = CostReportService {
+            tx_service: self.tx_service.clone(),
+            contracts_repo: self.contracts_repo.clone(),
+        };
+        return aux;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct GroupKey {
+    contract_id: u16,
+    blockchain: String,
+    currency: Currency,
+    day: Option<NaiveDate>,
+}
+
+#[derive(Default)]
+struct Samples {
+    costs: Vec<u128>,
+    gas_used: u64,
+    fiat: BTreeMap<String, u128>,
+    fiat_valued: u64,
+}
+
+impl Samples {
+    fn push(&mut self, cost: u128, gas: u64, fiat: &Option<(String, u128)>) {
+        self.costs.push(cost);
+        self.gas_used += gas;
+        if let Some((code, value)) = fiat {
+            *self.fiat.entry(code.clone()).or_default() += value;
+            self.fiat_valued += 1;
+        }
+    }
+
+    fn into_row(mut self, key: GroupKey) -> ResultE<CostReportRow> {
+        self.costs.sort();
+        let unit = key.currency.base_unit();
+        let mut total = Amount::zero(key.currency);
+        for cost in &self.costs {
+            total = total.checked_add(&Amount::new(*cost, unit))?;
+        }
+        let count = self.costs.len() as u128;
+
+        Ok(CostReportRow {
+            day: key.day,
+            contract_id: key.contract_id,
+            blockchain: key.blockchain,
+            currency: key.currency,
+            unit,
+            transactions: self.costs.len() as u64,
+            gas_used: self.gas_used,
+            total,
+            average: Amount::new(total.value() / count, unit), //floor, in base units
+            p50: Amount::new(percentile(&self.costs, 50), unit),
+            p90: Amount::new(percentile(&self.costs, 90), unit),
+            p99: Amount::new(percentile(&self.costs, 99), unit),
+            fiat_valued: self.fiat_valued,
+            fiat_totals: self
+                .fiat
+                .into_iter()
+                .map(|(code, value)| (code, FiatValuation::format_value(value)))
+                .collect(),
+        })
+    }
+}
+
+// nearest-rank over already sorted samples
+fn percentile(sorted: &[u128], pct: usize) -> u128 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (pct * sorted.len() + 99) / 100;
+    sorted[rank.max(1) - 1]
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CostReportRow {
+    pub day: Option<NaiveDate>, //None for the whole window
+    pub contract_id: u16,
+    pub blockchain: String,
+    pub currency: Currency,
+    pub unit: Unit,
+    pub transactions: u64,
+    pub gas_used: u64,
+    pub total: Amount,
+    pub average: Amount,
+    pub p50: Amount,
+    pub p90: Amount,
+    pub p99: Amount,
+    pub fiat_valued: u64, //txs valued at completion time, older ones have no rate
+    pub fiat_totals: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct CostReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub days: Vec<CostReportRow>,
+    pub totals: Vec<CostReportRow>,
+}
+
+impl CostReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// One line per day and contract, followed by the window totals
+    /// (empty day column). Amounts are integers in the `unit` column,
+    /// fiat totals are decimals like `EUR 0.003;USD 0.0032`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "day,contract_id,blockchain,currency,unit,transactions,gas_used,total,average,p50,p90,p99,fiat_valued,fiat_totals\n",
+        );
+        for row in self.days.iter().chain(self.totals.iter()) {
+            let day = match row.day {
+                None => "".to_string(),
+                Some(d) => d.format("%Y-%m-%d").to_string(),
+            };
+            let fiat_totals = row
+                .fiat_totals
+                .iter()
+                .map(|(code, value)| format!("{} {}", code, value))
+                .collect::<Vec<String>>()
+                .join(";");
+            csv.push_str(
+                format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                    day,
+                    row.contract_id,
+                    csv_field(&row.blockchain),
+                    row.currency,
+                    row.unit,
+                    row.transactions,
+                    row.gas_used,
+                    row.total.value(),
+                    row.average.value(),
+                    row.p50.value(),
+                    row.p90.value(),
+                    row.p99.value(),
+                    row.fiat_valued,
+                    fiat_totals,
+                )
+                .as_str(),
+            );
+        }
+        csv
+    }
+}
+
+impl fmt::Display for CostReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", json!(self).to_string())
+    }
+}
+
+fn csv_field(value: &String) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.clone()
+    }
+}
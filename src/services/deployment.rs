@@ -0,0 +1,604 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lib_config::config::Config;
+use web3::ethabi::Token;
+use web3::transports::Http;
+use web3::types::{
+    Address, BlockNumber, Bytes, CallRequest, TransactionParameters, TransactionReceipt, U256, U64,
+};
+use web3::Web3;
+
+use crate::blockchains::chain::node_url;
+use crate::blockchains::ganache::{u256_to_u128, EvmArtifact};
+use crate::blockchains::key_cache::KeyCache;
+use crate::blockchains::signer::{
+    contract_owner_signer, owner_secret_address, sign_evm_transaction, Signer,
+};
+use crate::blockchains::sui::{
+    check_hasher_package, pick_gas_coin, publish_package, SuiPackage, PUBLISH_GAS_BUDGET,
+};
+use crate::errors::blockchain::BlockchainNoExistsError;
+use crate::errors::contract::ContractNoExistsError;
+use crate::errors::deployment::DeploymentError;
+use crate::models::amount::Amount;
+use crate::models::blockchain::Blockchain;
+use crate::models::contract::{Contract, ContractStatus, ContractVersion};
+use crate::models::keypair::ChainFamily;
+use crate::models::secret::Secret;
+use crate::repositories::blockchain::{BlockchainRepo, BlockchainRepository};
+use crate::repositories::contract::{ContractRepo, ContractRepository};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+#[async_trait]
+pub trait DeploymentManipulation {
+    async fn deploy_evm_contract(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract>;
+    async fn setup_evm_network(
+        &self,
+        blockchain: &Blockchain,
+        contract_id: &u16,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract>;
+    async fn deploy_evm_proxy(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract>;
+    async fn publish_sui_package(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        owner: &ContractOwner,
+        package: &SuiPackage,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract>;
+    async fn setup_sui_network(
+        &self,
+        blockchain: &Blockchain,
+        contract_id: &u16,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract>;
+}
+
+/// The owner of a new contract, the account deploying it: LightNFT takes
+/// its deployer as owner, and Sui hands it the `UpgradeCap`.
+#[derive(Clone, Debug)]
+pub enum ContractOwner {
+    /// hex secret for Evm, base64 `Keystore` for Sui, stored cyphered
+    /// under `key_id`
+    Secret { secret: Secret, key_id: String },
+    /// asymmetric KMS key id, nothing secret is stored
+    KmsKey(String),
+}
+
+/// Deploys LightNFT contracts, plain or behind an upgradeable proxy, and
+/// publishes the Sui hasher package from the owner account, signing
+/// wherever the owner key lives, and registers them once checked on-chain.
+#[derive(Debug)]
+pub struct DeploymentService {
+    contracts_repo: ContractRepo,
+    blockchains_repo: BlockchainRepo,
+    config: Config,
+}
+
+impl DeploymentService {
+    pub fn new(
+        contracts_repo: ContractRepo,
+        blockchains_repo: BlockchainRepo,
+        config: &Config,
+    ) -> DeploymentService {
+        DeploymentService {
+            contracts_repo,
+            blockchains_repo,
+            config: config.to_owned(),
+        }
+    }
+
+    // the blockchain a new contract goes to, refusing ids already taken
+    async fn target_blockchain(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        family: &ChainFamily,
+    ) -> ResultE<Blockchain> {
+        match self.contracts_repo.get_by_id(contract_id).await {
+            Ok(_) => {
+                let mssag = format!("contract {} exists already", contract_id);
+                return Err(DeploymentError(mssag).into());
+            }
+            Err(e) if e.is::<ContractNoExistsError>() => {}
+            Err(e) => return Err(e),
+        }
+        let blockchain = self.blockchains_repo.get_by_id(blockchain_id).await?;
+        if *blockchain.family() != Some(family.to_owned()) {
+            let mssag = format!("{} isn't a {} blockchain", blockchain_id, family);
+            return Err(DeploymentError(mssag).into());
+        }
+        Ok(blockchain)
+    }
+
+    // records `blockchain` unless known already
+    async fn add_blockchain(&self, blockchain: &Blockchain, family: &ChainFamily) -> ResultE<()> {
+        if *blockchain.family() != Some(family.to_owned()) {
+            let mssag = format!("{} isn't a {} blockchain", blockchain.id(), family);
+            return Err(DeploymentError(mssag).into());
+        }
+        match self.blockchains_repo.get_by_id(blockchain.id()).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.is::<BlockchainNoExistsError>() => {
+                self.blockchains_repo.add(blockchain).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // the record to store, all but the address, and the signer the backend
+    // will mint with: a record it can't use is never stored
+    async fn new_contract(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        family: &ChainFamily,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<(Contract, Box<dyn Signer + Sync + Send>)> {
+        let mut contract = Contract::new_c(
+            contract_id.to_owned(),
+            Utc::now(),
+            blockchain_id.to_owned(),
+            None,
+            None,
+            None,
+            None,
+            details.clone(),
+            ContractStatus::Enabled,
+        );
+        match owner {
+            ContractOwner::Secret { secret, key_id } => {
+                contract.set_owner_address(&owner_secret_address(family, secret)?);
+                contract.set_owner_secret(&secret.cypher(key_id, &self.config).await?);
+                contract.set_owner_secret_key_id(key_id);
+            }
+            ContractOwner::KmsKey(key_id) => contract.set_owner_signer_key_id(key_id),
+        }
+        let signer =
+            contract_owner_signer(&contract, family, &self.config, &KeyCache::default()).await?;
+        contract.set_owner_address(signer.address());
+        Ok((contract, signer))
+    }
+}
+
+fn deployment_error(e: impl fmt::Display) -> DeploymentError {
+    DeploymentError(e.to_string())
+}
+
+pub(crate) fn evm_client(blockchain: &Blockchain, config: &Config) -> ResultE<Web3<Http>> {
+    let url = node_url(blockchain, config);
+    let transport = Http::new(url.as_str()).map_err(deployment_error)?;
+    Ok(Web3::new(transport))
+}
+
+/// Signs `data` from `signer`, a call to `to` or a deployment if none, and
+/// waits for it to be mined without reverting.
+pub(crate) async fn send_evm_tx(
+    web3: &Web3<Http>,
+    signer: &(dyn Signer + Sync + Send),
+    to: Option<Address>,
+    data: Vec<u8>,
+    confirmations: usize,
+) -> ResultE<TransactionReceipt> {
+    let from = Address::from_str(signer.address().as_str())?;
+    let gas = web3
+        .eth()
+        .estimate_gas(
+            CallRequest {
+                from: Some(from),
+                to,
+                data: Some(Bytes(data.clone())),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .map_err(deployment_error)?;
+    let gas_price = web3.eth().gas_price().await.map_err(deployment_error)?;
+    let nonce = web3
+        .eth()
+        .transaction_count(from, Some(BlockNumber::Pending))
+        .await
+        .map_err(deployment_error)?;
+    let chain_id = web3.eth().chain_id().await.map_err(deployment_error)?;
+
+    let tx_params = TransactionParameters {
+        nonce: Some(nonce),
+        to,
+        gas,
+        gas_price: Some(gas_price),
+        value: U256::zero(),
+        data: Bytes(data),
+        chain_id: Some(chain_id.as_u64()),
+        ..Default::default()
+    };
+    let signed = sign_evm_transaction(web3, signer, tx_params).await?;
+    let receipt = web3
+        .send_raw_transaction_with_confirmation(
+            signed.raw_transaction,
+            Duration::from_secs(1),
+            confirmations,
+        )
+        .await
+        .map_err(deployment_error)?;
+    if receipt.status != Some(U64::one()) {
+        let mssag = format!("tx {:?} reverted", receipt.transaction_hash);
+        return Err(DeploymentError(mssag).into());
+    }
+    Ok(receipt)
+}
+
+/// Deploys `artifact` with `args` for its constructor, and checks the code
+/// left at its address is the artifact's.
+pub(crate) async fn deploy_evm_artifact(
+    web3: &Web3<Http>,
+    signer: &(dyn Signer + Sync + Send),
+    artifact: &EvmArtifact,
+    args: &[Token],
+    confirmations: usize,
+) -> ResultE<(Address, TransactionReceipt)> {
+    let mut data = artifact.bytecode().clone();
+    data.extend(web3::ethabi::encode(args));
+    let receipt = send_evm_tx(web3, signer, None, data, confirmations).await?;
+    let address = receipt.contract_address.ok_or(DeploymentError(format!(
+        "deployment {:?} created no contract",
+        receipt.transaction_hash
+    )))?;
+
+    //no immutables, what runs is a slice of what was sent
+    let deployed = web3
+        .eth()
+        .code(address, None)
+        .await
+        .map_err(deployment_error)?;
+    let matches = !deployed.0.is_empty()
+        && artifact
+            .bytecode()
+            .windows(deployed.0.len())
+            .any(|window| window == deployed.0.as_slice());
+    if !matches {
+        let mssag = format!("the code at {:?} isn't {}'s", address, artifact.name());
+        return Err(DeploymentError(mssag).into());
+    }
+    Ok((address, receipt))
+}
+
+/// Calls the view `function` of `artifact` deployed at `address`.
+pub(crate) async fn call_evm_view(
+    web3: &Web3<Http>,
+    artifact: &EvmArtifact,
+    address: Address,
+    function: &str,
+    args: &[Token],
+) -> ResultE<Vec<Token>> {
+    let function = artifact.function(function)?;
+    let output = web3
+        .eth()
+        .call(
+            CallRequest {
+                to: Some(address),
+                data: Some(Bytes(function.encode_input(args)?)),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+        .map_err(deployment_error)?;
+    Ok(function.decode_output(&output.0)?)
+}
+
+/// What `receipts` paid in gas, none if any of them doesn't tell.
+pub(crate) fn evm_cost(receipts: &[&TransactionReceipt]) -> ResultE<Option<Amount>> {
+    let mut total = Amount::wei(0);
+    for receipt in receipts {
+        let cost = match (receipt.gas_used, receipt.effective_gas_price) {
+            (Some(gas), Some(price)) => match gas.checked_mul(price) {
+                Some(cost) => Amount::wei(u256_to_u128(cost)?),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+        total = total.checked_add(&cost)?;
+    }
+    Ok(Some(total))
+}
+
+/// Implementation behind `proxy`, as its `LightNFTProxyAdmin` sees it.
+pub(crate) async fn evm_proxy_implementation(
+    web3: &Web3<Http>,
+    proxy_admin: Address,
+    proxy: Address,
+) -> ResultE<Address> {
+    let admin_artifact = EvmArtifact::light_nft_proxy_admin();
+    let output = call_evm_view(
+        web3,
+        &admin_artifact,
+        proxy_admin,
+        "getProxyImplementation",
+        &[Token::Address(proxy)],
+    )
+    .await?;
+    match output
+        .into_iter()
+        .next()
+        .and_then(|token| token.into_address())
+    {
+        Some(implementation) => Ok(implementation),
+        None => Err(DeploymentError(format!("{:?} has no implementation", proxy)).into()),
+    }
+}
+
+#[async_trait]
+impl DeploymentManipulation for DeploymentService {
+    /// Deploys a LightNFT contract signed by `owner` and records it as
+    /// `contract_id`, enabled, once the code at its address is checked.
+    /// Nothing is recorded if anything fails.
+    #[tracing::instrument()]
+    async fn deploy_evm_contract(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract> {
+        let blockchain = self
+            .target_blockchain(contract_id, blockchain_id, &ChainFamily::Evm)
+            .await?;
+        let (mut contract, signer) = self
+            .new_contract(
+                contract_id,
+                blockchain_id,
+                &ChainFamily::Evm,
+                owner,
+                details,
+            )
+            .await?;
+        let web3 = evm_client(&blockchain, &self.config)?;
+        let artifact = EvmArtifact::light_nft();
+        let (address, receipt) = deploy_evm_artifact(
+            &web3,
+            signer.as_ref(),
+            &artifact,
+            &[],
+            (*blockchain.confirmations()).into(),
+        )
+        .await?;
+
+        let address = format!("{:?}", address);
+        let tx = format!("{:?}", receipt.transaction_hash);
+        contract.set_address(&address);
+        if let Some(block) = receipt.block_number {
+            contract.set_deployment_block(&block.as_u64());
+        }
+        contract.add_version(&ContractVersion::new(
+            1,
+            &address,
+            &artifact.interface(),
+            &tx,
+        ))?;
+        self.contracts_repo.add(&contract).await?;
+        Ok(DeployedContract {
+            contract,
+            tx,
+            block_number: receipt.block_number.map(|num| num.as_u64()),
+            cost: evm_cost(&[&receipt])?,
+        })
+    }
+
+    /// Records `blockchain` unless known already, then deploys the first
+    /// contract on it.
+    #[tracing::instrument()]
+    async fn setup_evm_network(
+        &self,
+        blockchain: &Blockchain,
+        contract_id: &u16,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract> {
+        self.add_blockchain(blockchain, &ChainFamily::Evm).await?;
+        self.deploy_evm_contract(contract_id, blockchain.id(), owner, details)
+            .await
+    }
+
+    /// Deploys LightNFTUpgradeable behind a LightNFTProxy, administered by
+    /// a LightNFTProxyAdmin the owner holds, and records the proxy as
+    /// `contract_id` with its first version. Nothing is recorded if anything
+    /// fails, though the contracts deployed until then stay on-chain.
+    #[tracing::instrument()]
+    async fn deploy_evm_proxy(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract> {
+        let implementation_artifact = EvmArtifact::light_nft_upgradeable();
+        let proxy_artifact = EvmArtifact::light_nft_proxy();
+        let admin_artifact = EvmArtifact::light_nft_proxy_admin();
+        let blockchain = self
+            .target_blockchain(contract_id, blockchain_id, &ChainFamily::Evm)
+            .await?;
+        let (mut contract, signer) = self
+            .new_contract(
+                contract_id,
+                blockchain_id,
+                &ChainFamily::Evm,
+                owner,
+                details,
+            )
+            .await?;
+        let web3 = evm_client(&blockchain, &self.config)?;
+        let confirmations: usize = (*blockchain.confirmations()).into();
+
+        let (implementation, implementation_receipt) = deploy_evm_artifact(
+            &web3,
+            signer.as_ref(),
+            &implementation_artifact,
+            &[],
+            confirmations,
+        )
+        .await?;
+        let (proxy_admin, admin_receipt) =
+            deploy_evm_artifact(&web3, signer.as_ref(), &admin_artifact, &[], confirmations)
+                .await?;
+        //the owner deploys the proxy, initialize() makes it the LightNFT owner
+        let initialize = implementation_artifact
+            .function("initialize")?
+            .encode_input(&[])?;
+        let (proxy, proxy_receipt) = deploy_evm_artifact(
+            &web3,
+            signer.as_ref(),
+            &proxy_artifact,
+            &[
+                Token::Address(implementation),
+                Token::Address(proxy_admin),
+                Token::Bytes(initialize),
+            ],
+            confirmations,
+        )
+        .await?;
+        if evm_proxy_implementation(&web3, proxy_admin, proxy).await? != implementation {
+            let mssag = format!("{:?} doesn't run {:?}", proxy, implementation);
+            return Err(DeploymentError(mssag).into());
+        }
+
+        let tx = format!("{:?}", proxy_receipt.transaction_hash);
+        contract.set_address(&format!("{:?}", proxy));
+        contract.set_proxy_admin(&format!("{:?}", proxy_admin));
+        if let Some(block) = proxy_receipt.block_number {
+            contract.set_deployment_block(&block.as_u64());
+        }
+        contract.add_version(&ContractVersion::new(
+            1,
+            &format!("{:?}", implementation),
+            &implementation_artifact.interface(),
+            &format!("{:?}", implementation_receipt.transaction_hash),
+        ))?;
+        self.contracts_repo.add(&contract).await?;
+        Ok(DeployedContract {
+            contract,
+            tx,
+            block_number: proxy_receipt.block_number.map(|num| num.as_u64()),
+            cost: evm_cost(&[&implementation_receipt, &admin_receipt, &proxy_receipt])?,
+        })
+    }
+
+    /// Publishes `package` signed by `owner` and records it as
+    /// `contract_id`, enabled, paying gas from the richest coin of the
+    /// owner. The owner keeps the `UpgradeCap`, its id is recorded too.
+    /// Nothing is recorded if the package lacks the hasher functions.
+    #[tracing::instrument()]
+    async fn publish_sui_package(
+        &self,
+        contract_id: &u16,
+        blockchain_id: &String,
+        owner: &ContractOwner,
+        package: &SuiPackage,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract> {
+        let blockchain = self
+            .target_blockchain(contract_id, blockchain_id, &ChainFamily::Sui)
+            .await?;
+        let (mut contract, signer) = self
+            .new_contract(
+                contract_id,
+                blockchain_id,
+                &ChainFamily::Sui,
+                owner,
+                details,
+            )
+            .await?;
+
+        let url = node_url(&blockchain, &self.config);
+        let gas_coin = pick_gas_coin(&url, signer.address(), PUBLISH_GAS_BUDGET).await?;
+        let published = publish_package(&url, signer.as_ref(), package, &gas_coin).await?;
+        check_hasher_package(&url, &published.package_id).await?;
+
+        contract.set_address(&published.package_id);
+        contract.set_upgrade_cap(&published.upgrade_cap);
+        contract.add_version(&ContractVersion::new(
+            published.version,
+            &published.package_id,
+            &published.modules.join(","),
+            &published.digest,
+        ))?;
+        //the gas coin is the same object once paid from
+        contract.set_owner_cash(&gas_coin);
+        self.contracts_repo.add(&contract).await?;
+        Ok(DeployedContract {
+            contract,
+            tx: published.digest,
+            block_number: Some(published.epoch),
+            cost: published.cost,
+        })
+    }
+
+    /// Records `blockchain` unless known already, then publishes the hasher
+    /// package built at res/sui/truly on it.
+    #[tracing::instrument()]
+    async fn setup_sui_network(
+        &self,
+        blockchain: &Blockchain,
+        contract_id: &u16,
+        owner: &ContractOwner,
+        details: &Option<String>,
+    ) -> ResultE<DeployedContract> {
+        self.add_blockchain(blockchain, &ChainFamily::Sui).await?;
+        let package = SuiPackage::truly()?;
+        self.publish_sui_package(contract_id, blockchain.id(), owner, &package, details)
+            .await
+    }
+}
+
+impl Clone for DeploymentService {
+    #[tracing::instrument()]
+    fn clone(&self) -> DeploymentService {
+        let aux = DeploymentService {
+            contracts_repo: self.contracts_repo.clone(),
+            blockchains_repo: self.blockchains_repo.clone(),
+            config: self.config.clone(),
+        };
+        return aux;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DeployedContract {
+    pub contract: Contract,
+    pub tx: String,
+    pub block_number: Option<u64>,
+    pub cost: Option<Amount>,
+}
+
+impl fmt::Display for DeployedContract {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "contract {} at {} deployed by {}",
+            self.contract.id(),
+            self.contract.address().clone().unwrap_or_default(),
+            self.tx
+        )
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use fastcrypto::hash::{HashFunction, Sha256};
+use lib_config::config::Config;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use web3::signing::keccak256;
+
+use crate::blockchains::signer::{recover_evm_address, Signer};
+use crate::blockchains::user_keys::UserDataKeys;
+use crate::errors::signer::SignerError;
+use crate::models::block_tx::BlockchainTx;
+use crate::models::keypair::{ChainFamily, KeyPair};
+use crate::repositories::keypairs::{KeyPairRepo, KeyPairRepository};
+use crate::repositories::user_data_keys::UserDataKeyRepo;
+
+use super::block_tx::{BlockchainTxManipulation, BlockchainTxService};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+const PAGE_SIZE: i32 = 100;
+
+#[async_trait]
+pub trait ErasureManipulation {
+    async fn erase_user(&self, user_id: &String) -> ResultE<ErasureReceipt>;
+}
+
+/// Erasure requests: the user's data key is shredded, their keypairs
+/// deleted and their id scrubbed from the tx records. Tx hashes, tokens
+/// and addresses stay, they are public on-chain anyway and audits need
+/// them. HD derived keys can be derived again from the master mnemonic,
+/// the receipt says so instead of counting them as shredded. Running it
+/// again for the same user is safe.
+#[derive(Debug)]
+pub struct ErasureService {
+    keys_repo: KeyPairRepo,
+    user_keys: UserDataKeys,
+    tx_service: BlockchainTxService,
+    signer: Box<dyn Signer + Sync + Send>,
+}
+
+impl ErasureService {
+    /// Receipts are signed by `signer`, an EVM key so anyone can check them.
+    pub fn new(
+        keys_repo: KeyPairRepo,
+        tx_service: BlockchainTxService,
+        signer: Box<dyn Signer + Sync + Send>,
+        config: &Config,
+    ) -> ErasureService {
+        ErasureService {
+            keys_repo,
+            user_keys: UserDataKeys::new(UserDataKeyRepo::new(config), config),
+            tx_service,
+            signer,
+        }
+    }
+
+    async fn user_txs(&self, user_id: &String) -> ResultE<Vec<BlockchainTx>> {
+        let mut txs = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = self
+                .tx_service
+                .get_by_user(user_id, &None, &None, &PAGE_SIZE, &cursor)
+                .await?;
+            cursor = page.next_cursor().clone();
+            txs.append(&mut page.into_items());
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(txs)
+    }
+}
+
+#[async_trait]
+impl ErasureManipulation for ErasureService {
+    //the user id is exactly what mustn't end up in the logs
+    #[tracing::instrument(skip(user_id))]
+    async fn erase_user(&self, user_id: &String) -> ResultE<ErasureReceipt> {
+        if self.signer.chain_family() != ChainFamily::Evm {
+            return Err(SignerError("receipts are signed with evm keys".to_string()).into());
+        }
+        let mut receipt = ErasureReceipt::new(user_id);
+
+        //first of all, nothing sealed for this user can be opened from now on
+        receipt.data_key_shredded = self.user_keys.shred(user_id).await?;
+
+        for chain in [ChainFamily::Evm, ChainFamily::Sui] {
+            if let Some(keypair) = self.keys_repo.get_by_id(user_id, &chain).await? {
+                receipt.keypairs.push(ErasedKeyPair::new(&keypair));
+                self.keys_repo.delete(user_id, &chain).await?;
+            }
+        }
+
+        //collected first, scrubbed records drop out of the user index
+        for mut tx in self.user_txs(user_id).await? {
+            tx.clear_user_id();
+            self.tx_service.update(&tx).await?;
+            receipt.txs_scrubbed += 1;
+            if let Some(hash) = tx.tx() {
+                receipt.retained_tx_hashes.push(hash.to_owned());
+            }
+        }
+
+        receipt.erased_at = Utc::now();
+        receipt.signer = self.signer.address().to_owned();
+        let signature = self.signer.sign_digest(&receipt.digest()).await?;
+        receipt.signature = hex::encode(signature);
+        Ok(receipt)
+    }
+}
+
+impl Clone for ErasureService {
+    #[tracing::instrument()]
+    fn clone(&self) -> ErasureService {
+        let aux = ErasureService {
+            keys_repo: self.keys_repo.clone(),
+            user_keys: self.user_keys.clone(),
+            tx_service: self.tx_service.clone(),
+            signer: self.signer.clone(),
+        };
+        return aux;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ErasedKeyPair {
+    pub chain: ChainFamily,
+    // false for keys stored before per-user data keys, those were only deleted
+    pub shredded: bool,
+    // hd derived keys hold no key material, deleting the row only forgets the
+    // user's index: the master mnemonic still derives the key, never shredded
+    pub recoverable: bool,
+}
+
+impl ErasedKeyPair {
+    pub fn new(keypair: &KeyPair) -> ErasedKeyPair {
+        let recoverable = keypair.derivation_index().is_some();
+        ErasedKeyPair {
+            chain: keypair.chain().clone(),
+            shredded: !recoverable && keypair.private_key().is_user_sealed(),
+            recoverable,
+        }
+    }
+}
+
+/// Proof of an erasure. `subject` is the sha256 of the user id, so the
+/// receipt can be kept without keeping who it was about; `signature`
+/// covers every other field and is checked with `verify`.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ErasureReceipt {
+    pub subject: String,
+    pub erased_at: DateTime<Utc>,
+    pub data_key_shredded: bool,
+    pub keypairs: Vec<ErasedKeyPair>,
+    pub txs_scrubbed: u64,
+    pub retained_tx_hashes: Vec<String>,
+    pub signer: String,
+    pub signature: String,
+}
+
+impl ErasureReceipt {
+    pub fn new(user_id: &String) -> ErasureReceipt {
+        ErasureReceipt {
+            subject: ErasureReceipt::subject_of(user_id),
+            erased_at: Utc::now(),
+            data_key_shredded: false,
+            keypairs: Vec::new(),
+            txs_scrubbed: 0,
+            retained_tx_hashes: Vec::new(),
+            signer: "".to_string(),
+            signature: "".to_string(),
+        }
+    }
+
+    pub fn subject_of(user_id: &String) -> String {
+        hex::encode(Sha256::digest(user_id.as_bytes()).digest)
+    }
+
+    /// What gets signed: keccak256 of the receipt as json, signature blank.
+    pub fn digest(&self) -> [u8; 32] {
+        let mut unsigned = self.clone();
+        unsigned.signature = "".to_string();
+        keccak256(json!(unsigned).to_string().as_bytes())
+    }
+
+    pub fn verify(&self) -> ResultE<bool> {
+        let signature = hex::decode(&self.signature)?;
+        let address = recover_evm_address(&self.digest(), &signature)?;
+        Ok(address == self.signer)
+    }
+}
+
+impl fmt::Display for ErasureReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", json!(self).to_string())
+    }
+}
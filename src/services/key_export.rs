@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use lib_config::config::Config;
+use serde::{Deserialize, Serialize};
+
+use crate::blockchains::hd_wallet::HdWallet;
+use crate::blockchains::keystore::{decrypt_keystore, encrypt_keystore, key_details};
+use crate::blockchains::user_keys::UserDataKeys;
+use crate::errors::keypair::{
+    KeyPairAddressMismatchError, KeyPairAlreadyExistsError, KeyPairNoExistsError,
+};
+use crate::errors::keystore::KeystoreError;
+use crate::models::keypair::{ChainFamily, KeyPair};
+use crate::models::secret::Secret;
+use crate::repositories::keypairs::{KeyPairRepo, KeyPairRepository};
+use crate::repositories::user_data_keys::UserDataKeyRepo;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+// shorter passphrases make the scrypt cost pointless
+const MIN_PASSPHRASE_LEN: usize = 12;
+
+#[async_trait]
+pub trait KeyExportManipulation {
+    async fn export_keypair(
+        &self,
+        user_id: &String,
+        chain: &ChainFamily,
+        passphrase: &Secret,
+    ) -> ResultE<KeyExport>;
+    async fn import_keypair(
+        &self,
+        user_id: &String,
+        chain: &ChainFamily,
+        document: &String,
+        passphrase: &Secret,
+    ) -> ResultE<KeyPair>;
+}
+
+/// Self-custody: hands a user's key over as a passphrase protected V3
+/// keystore, and takes keys generated elsewhere under custody. The
+/// passphrase is the user's, it is never stored.
+#[derive(Debug)]
+pub struct KeyExportService {
+    keys_repo: KeyPairRepo,
+    user_keys: UserDataKeys,
+    hd_wallet: Option<HdWallet>,
+    config: Config,
+}
+
+impl KeyExportService {
+    pub fn new(keys_repo: KeyPairRepo, config: &Config) -> KeyExportService {
+        KeyExportService {
+            keys_repo,
+            user_keys: UserDataKeys::new(UserDataKeyRepo::new(config), config),
+            hd_wallet: None,
+            config: config.to_owned(),
+        }
+    }
+
+    /// Needed to export keys derived from the master mnemonic.
+    pub fn with_hd_wallet(mut self, hd_wallet: &HdWallet) -> KeyExportService {
+        self.hd_wallet = Some(hd_wallet.clone());
+        self
+    }
+
+    async fn private_key(&self, keypair: &KeyPair) -> ResultE<Secret> {
+        if let Some(index) = keypair.derivation_index() {
+            let hd_wallet = self.hd_wallet.as_ref().ok_or(KeystoreError(
+                "derived keys need the hd wallet to be exported".to_string(),
+            ))?;
+            let derived = hd_wallet.derive(keypair.chain(), *index).await?;
+            return Ok(derived.secret().to_owned());
+        }
+        if keypair.private_key().is_user_sealed() {
+            return self
+                .user_keys
+                .open(keypair.user_id(), keypair.private_key())
+                .await;
+        }
+        let key_id = keypair
+            .key_id()
+            .clone()
+            .unwrap_or(self.config.env_vars().kms_key_id().unwrap());
+        keypair.private_key().uncypher(&key_id, &self.config).await
+    }
+}
+
+#[async_trait]
+impl KeyExportManipulation for KeyExportService {
+    #[tracing::instrument(skip(passphrase))]
+    async fn export_keypair(
+        &self,
+        user_id: &String,
+        chain: &ChainFamily,
+        passphrase: &Secret,
+    ) -> ResultE<KeyExport> {
+        check_passphrase(passphrase)?;
+        let keypair = self
+            .keys_repo
+            .get_by_id(user_id, chain)
+            .await?
+            .ok_or(KeyPairNoExistsError(format!("{} {}", user_id, chain)))?;
+
+        let private_key = self.private_key(&keypair).await?;
+        let (address, _) = key_details(chain, &private_key)?;
+        //registrations went to the stored address, a wallet without it is no use
+        if address.to_lowercase() != keypair.address().to_lowercase() {
+            let mssag = format!(
+                "{} {} key is at {}, registrations at {}",
+                user_id,
+                chain,
+                address,
+                keypair.address()
+            );
+            tracing::error!(mssag);
+            return Err(KeyPairAddressMismatchError(mssag).into());
+        }
+        let document = encrypt_keystore(chain, &private_key, passphrase)?;
+        Ok(KeyExport {
+            chain: chain.to_owned(),
+            address,
+            format: KeyExportFormat::of(chain),
+            document,
+        })
+    }
+
+    #[tracing::instrument(skip(document, passphrase))]
+    async fn import_keypair(
+        &self,
+        user_id: &String,
+        chain: &ChainFamily,
+        document: &String,
+        passphrase: &Secret,
+    ) -> ResultE<KeyPair> {
+        if self.keys_repo.get_by_id(user_id, chain).await?.is_some() {
+            let mssag = format!("{} already holds a {} key", user_id, chain);
+            return Err(KeyPairAlreadyExistsError(mssag).into());
+        }
+        let private_key = decrypt_keystore(chain, document, passphrase)?;
+        let (address, public_key) = key_details(chain, &private_key)?;
+        if self.keys_repo.get_by_address(&address).await?.is_some() {
+            return Err(KeyPairAlreadyExistsError(address).into());
+        }
+
+        let key_id = self.config.env_vars().kms_key_id().unwrap();
+        let sealed = self.user_keys.seal(user_id, &key_id, &private_key).await?;
+
+        let mut keypair = KeyPair::new();
+        keypair.set_user_id(user_id);
+        keypair.set_chain(chain);
+        keypair.set_address(&address);
+        keypair.set_public_key(&public_key);
+        keypair.set_private_key(&sealed);
+        keypair.set_key_id(&key_id);
+        keypair.set_creation_time(&Utc::now());
+        keypair.set_last_update_time(&Utc::now());
+        self.keys_repo.add(&keypair).await?;
+        Ok(keypair)
+    }
+}
+
+impl Clone for KeyExportService {
+    #[tracing::instrument()]
+    fn clone(&self) -> KeyExportService {
+        let aux = KeyExportService {
+            keys_repo: self.keys_repo.clone(),
+            user_keys: self.user_keys.clone(),
+            hd_wallet: self.hd_wallet.clone(),
+            config: self.config.clone(),
+        };
+        return aux;
+    }
+}
+
+fn check_passphrase(passphrase: &Secret) -> ResultE<()> {
+    if passphrase.expose().chars().count() < MIN_PASSPHRASE_LEN {
+        let mssag = format!("passphrases need {} characters", MIN_PASSPHRASE_LEN);
+        return Err(KeystoreError(mssag).into());
+    }
+    Ok(())
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum KeyExportFormat {
+    /// Web3 Secret Storage v3, imported by any Ethereum wallet
+    EthereumKeystoreV3,
+    /// v3 encryption over a Sui keystore entry (flag || key, base64),
+    /// add the decrypted entry to `sui.keystore`
+    SuiKeystoreEntryV3,
+}
+
+impl KeyExportFormat {
+    pub fn of(chain: &ChainFamily) -> KeyExportFormat {
+        match chain {
+            ChainFamily::Evm => KeyExportFormat::EthereumKeystoreV3,
+            ChainFamily::Sui => KeyExportFormat::SuiKeystoreEntryV3,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct KeyExport {
+    pub chain: ChainFamily,
+    pub address: String,
+    pub format: KeyExportFormat,
+    pub document: String,
+}
@@ -0,0 +1,304 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lib_config::config::Config;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::blockchains::hd_wallet::HdWallet;
+use crate::blockchains::key_cache::KeyCache;
+use crate::blockchains::user_keys::UserDataKeys;
+use crate::models::contract::Contract;
+use crate::models::keypair::KeyPair;
+use crate::models::secret::Secret;
+use crate::repositories::contract::{ContractRepo, ContractRepository};
+use crate::repositories::keypairs::{KeyPairRepo, KeyPairRepository};
+use crate::repositories::user_data_keys::UserDataKeyRepo;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+const PAGE_SIZE: i32 = 50;
+
+#[async_trait]
+pub trait KeyRotationManipulation {
+    async fn rotate(
+        &self,
+        new_key_id: &String,
+        resume_from: &Option<RotationProgress>,
+        on_progress: &(dyn Fn(&RotationProgress) + Sync + Send),
+    ) -> ResultE<RotationProgress>;
+}
+
+/// Re-encrypts every user keypair and contract owner secret under a new
+/// KMS key, then the HD master mnemonic if there is one. Items already
+/// under the new key are skipped, so running it again after a failure is
+/// safe; passing the last reported progress avoids scanning again the
+/// pages already done.
+#[derive(Debug)]
+pub struct KeyRotationService {
+    keys_repo: KeyPairRepo,
+    contracts_repo: ContractRepo,
+    config: Config,
+    key_cache: Option<KeyCache>,
+    user_keys: UserDataKeys,
+    hd_wallet: Option<HdWallet>,
+}
+
+impl KeyRotationService {
+    pub fn new(
+        keys_repo: KeyPairRepo,
+        contracts_repo: ContractRepo,
+        config: Config,
+    ) -> KeyRotationService {
+        let user_keys = UserDataKeys::new(UserDataKeyRepo::new(&config), &config);
+        KeyRotationService {
+            keys_repo,
+            contracts_repo,
+            config,
+            key_cache: None,
+            user_keys,
+            hd_wallet: None,
+        }
+    }
+
+    /// Owner secrets decrypted under a rotated key are dropped from `key_cache`.
+    pub fn with_key_cache(mut self, key_cache: &KeyCache) -> KeyRotationService {
+        self.key_cache = Some(key_cache.clone());
+        self
+    }
+
+    /// Its master mnemonic is cyphered again last, see `RotationProgress`.
+    pub fn with_hd_wallet(mut self, hd_wallet: &HdWallet) -> KeyRotationService {
+        self.hd_wallet = Some(hd_wallet.clone());
+        self
+    }
+
+    // items written before the key id was recorded are under the env key
+    fn legacy_key_id(&self) -> String {
+        self.config.env_vars().kms_key_id().unwrap()
+    }
+
+    async fn recypher(&self, cyphered: &Secret, from: &String, to: &String) -> ResultE<Secret> {
+        let plain = cyphered.uncypher(from, &self.config).await?;
+        plain.cypher(to, &self.config).await
+    }
+
+    async fn rotate_keypair(&self, keypair: &KeyPair, new_key_id: &String) -> ResultE<bool> {
+        //derived keys hold no ciphertext, the master mnemonic is rotated last
+        if keypair.derivation_index().is_some() {
+            return Ok(false);
+        }
+        let old_key_id = keypair.key_id().clone().unwrap_or(self.legacy_key_id());
+        if old_key_id == *new_key_id {
+            return Ok(false);
+        }
+        let mut rotated = keypair.clone();
+        //sealed keys stay as they are, only the user's data key is wrapped again
+        if keypair.private_key().is_user_sealed() {
+            if !self.user_keys.rewrap(keypair.user_id(), new_key_id).await? {
+                return Ok(false); //shredded, nothing to rotate
+            }
+            rotated.set_key_id(new_key_id);
+            rotated.set_last_update_time(&Utc::now());
+            self.keys_repo.update(&rotated).await?;
+            return Ok(true);
+        }
+        rotated.set_private_key(
+            &self
+                .recypher(keypair.private_key(), &old_key_id, new_key_id)
+                .await?,
+        );
+        //older rows had the public key cyphered too, it is kept in clear now
+        if !keypair.private_key().is_envelope() {
+            let public_key = Secret::from(keypair.public_key().to_owned());
+            rotated.set_public_key(
+                public_key
+                    .uncypher(&old_key_id, &self.config)
+                    .await?
+                    .expose(),
+            );
+        }
+        rotated.set_key_id(new_key_id);
+        rotated.set_last_update_time(&Utc::now());
+        self.keys_repo.update(&rotated).await?;
+        Ok(true)
+    }
+
+    async fn rotate_contract(&self, contract: &Contract, new_key_id: &String) -> ResultE<bool> {
+        let secret = match contract.owner_secret() {
+            None => return Ok(false),
+            Some(sct) => sct,
+        };
+        let old_key_id = contract
+            .owner_secret_key_id()
+            .clone()
+            .unwrap_or(self.legacy_key_id());
+        if old_key_id == *new_key_id {
+            return Ok(false);
+        }
+        let mut rotated = contract.clone();
+        rotated.set_owner_secret(&self.recypher(secret, &old_key_id, new_key_id).await?);
+        rotated.set_owner_secret_key_id(new_key_id);
+        self.contracts_repo.update(&rotated).await?;
+        if let Some(cache) = &self.key_cache {
+            cache.invalidate(&old_key_id);
+        }
+        Ok(true)
+    }
+
+    fn outcome(progress: &mut RotationProgress, item: String, res: ResultE<bool>) {
+        match res {
+            Ok(true) => progress.rotated += 1,
+            Ok(false) => progress.skipped += 1,
+            Err(e) => {
+                tracing::error!("key rotation failed for {}: {}", item, e);
+                progress.failed.push(item);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl KeyRotationManipulation for KeyRotationService {
+    #[tracing::instrument(skip(on_progress))]
+    async fn rotate(
+        &self,
+        new_key_id: &String,
+        resume_from: &Option<RotationProgress>,
+        on_progress: &(dyn Fn(&RotationProgress) + Sync + Send),
+    ) -> ResultE<RotationProgress> {
+        let mut progress = match resume_from {
+            Some(prg) if prg.new_key_id == *new_key_id => prg.clone(),
+            _ => RotationProgress::new(new_key_id),
+        };
+
+        while progress.phase == RotationPhase::KeyPairs {
+            let page = self.keys_repo.list(&PAGE_SIZE, &progress.cursor).await?;
+            for keypair in page.items() {
+                let res = self.rotate_keypair(keypair, new_key_id).await;
+                let item = format!("keypair {}", keypair.user_id());
+                KeyRotationService::outcome(&mut progress, item, res);
+            }
+            progress.cursor = page.next_cursor().clone();
+            if progress.cursor.is_none() {
+                progress.phase = RotationPhase::Contracts;
+            }
+            on_progress(&progress);
+        }
+
+        while progress.phase == RotationPhase::Contracts {
+            let page = self
+                .contracts_repo
+                .list(&PAGE_SIZE, &progress.cursor)
+                .await?;
+            for contract in page.items() {
+                let res = self.rotate_contract(contract, new_key_id).await;
+                let item = format!("contract {}", contract.id());
+                KeyRotationService::outcome(&mut progress, item, res);
+            }
+            progress.cursor = page.next_cursor().clone();
+            if progress.cursor.is_none() {
+                progress.phase = RotationPhase::MasterMnemonic;
+            }
+            on_progress(&progress);
+        }
+
+        if progress.phase == RotationPhase::MasterMnemonic {
+            if let Some(wallet) = &self.hd_wallet {
+                if wallet.key_id() == new_key_id {
+                    progress.skipped += 1;
+                } else {
+                    match wallet.rotate(new_key_id).await {
+                        Ok(rotated) => {
+                            progress.mnemonic_cyphered =
+                                Some(rotated.mnemonic_cyphered().expose().to_owned());
+                            progress.rotated += 1;
+                        }
+                        Err(e) => {
+                            tracing::error!("key rotation failed for master mnemonic: {}", e);
+                            progress.failed.push("master mnemonic".to_string());
+                        }
+                    }
+                }
+            }
+            progress.phase = RotationPhase::Done;
+            on_progress(&progress);
+        }
+
+        Ok(progress)
+    }
+}
+
+impl Clone for KeyRotationService {
+    #[tracing::instrument()]
+    fn clone(&self) -> KeyRotationService {
+        let aux = KeyRotationService {
+            keys_repo: self.keys_repo.clone(),
+            contracts_repo: self.contracts_repo.clone(),
+            config: self.config.clone(),
+            key_cache: self.key_cache.clone(),
+            user_keys: self.user_keys.clone(),
+            hd_wallet: self.hd_wallet.clone(),
+        };
+        return aux;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum RotationPhase {
+    KeyPairs,
+    Contracts,
+    MasterMnemonic,
+    Done,
+}
+
+impl fmt::Display for RotationPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RotationPhase::KeyPairs => write!(f, "KeyPairs"),
+            RotationPhase::Contracts => write!(f, "Contracts"),
+            RotationPhase::MasterMnemonic => write!(f, "MasterMnemonic"),
+            RotationPhase::Done => write!(f, "Done"),
+        }
+    }
+}
+
+/// Where a rotation is. It can be persisted and handed back to `rotate`
+/// to resume; `failed` items are left under their old key.
+/// `mnemonic_cyphered` is the master mnemonic under the new key: store it
+/// in place of the old one, nothing else keeps it.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RotationProgress {
+    pub new_key_id: String,
+    pub phase: RotationPhase,
+    pub cursor: Option<String>,
+    pub rotated: u64,
+    pub skipped: u64,
+    pub failed: Vec<String>,
+    pub mnemonic_cyphered: Option<String>,
+}
+
+impl RotationProgress {
+    pub fn new(new_key_id: &String) -> RotationProgress {
+        RotationProgress {
+            new_key_id: new_key_id.to_owned(),
+            phase: RotationPhase::KeyPairs,
+            cursor: None,
+            rotated: 0,
+            skipped: 0,
+            failed: Vec::new(),
+            mnemonic_cyphered: None,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.phase == RotationPhase::Done
+    }
+}
+
+impl fmt::Display for RotationProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", json!(self).to_string())
+    }
+}
@@ -1,3 +1,11 @@
 pub mod block_tx;
 pub mod contract;
+pub mod cost_report;
+pub mod deployment;
+pub mod erasure;
+pub mod key_export;
+pub mod key_rotation;
 pub mod nfts;
+pub mod owner_backup;
+pub mod reconciliation;
+pub mod upgrade;
@@ -3,16 +3,25 @@ use std::{fmt, str::FromStr};
 use async_trait::async_trait;
 use chrono::Utc;
 use lib_config::config::Config;
+use log::warn;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::blockchains::chain::NFTsRepository;
+use crate::blockchains::hd_wallet::HdWallet;
+use crate::blockchains::mirror::MirrorSet;
+use crate::blockchains::router::ContractRouter;
+use crate::errors::blockchain::BlockchainBackendError;
+use crate::errors::keypair::KeyPairAlreadyExistsError;
 use crate::errors::nft::{
     TokenHasBeenMintedAlreadyError, TokenMintingProcessHasBeenInitiatedError,
-    TokenNotSuccessfullyMintedPreviously,
+    TokenNotAnchoredOnRequiredChainsError, TokenNotSuccessfullyMintedPreviously,
 };
-use crate::models::block_tx::{BlockchainTx, BlockchainTxBuilder, MintingStatus};
+use crate::models::block_tx::{BlockchainTx, BlockchainTxBuilder, ChainAnchor, MintingStatus};
+use crate::models::fiat::FiatValuation;
+use crate::models::keypair::{ChainFamily, KeyPair};
 use crate::repositories::keypairs::{KeyPairRepo, KeyPairRepository};
+use crate::repositories::prices::PriceRepository;
 //use lib_licenses::models::asset::{Asset, MintingStatus};
 //use lib_licenses::services::assets::{AssetManipulation, AssetService};
 //use lib_licenses::services::owners::{OwnerManipulation, OwnerService};
@@ -38,6 +47,17 @@ pub trait NFTsManipulation {
         hash_algo: &String,
         counter: &u64,
     ) -> ResultE<BlockchainTx>;
+    // as try_mint, `category` steers the contract when routing by category
+    async fn try_mint_in_category(
+        &self,
+        asset_id: &Uuid,
+        user_id: &String,
+        category: &Option<String>,
+        price: &Option<u64>,
+        hash: &String,
+        hash_algo: &String,
+        counter: &u64,
+    ) -> ResultE<BlockchainTx>;
     async fn get(&self, asset_id: &Uuid) -> ResultE<NTFContentInfo>;
 }
 
@@ -49,6 +69,11 @@ pub struct NFTsService {
     //owner_service: OwnerService,
     tx_service: BlockchainTxService,
     config: Config,
+    prices: Option<Box<dyn PriceRepository + Sync + Send>>,
+    fiat: String,
+    hd_wallet: Option<HdWallet>,
+    router: Option<ContractRouter>,
+    mirrors: Option<MirrorSet>,
 }
 
 impl NFTsService {
@@ -67,6 +92,257 @@ impl NFTsService {
             //owner_service,
             config,
             tx_service,
+            prices: None,
+            fiat: String::new(),
+            hd_wallet: None,
+            router: None,
+            mirrors: None,
+        }
+    }
+
+    /// New user keys are derived from `wallet` instead of being generated
+    /// by the blockchain backend.
+    pub fn with_hd_wallet(mut self, wallet: HdWallet) -> NFTsService {
+        self.hd_wallet = Some(wallet);
+        self
+    }
+
+    /// New mints go to the contract `router` picks. The backend given at
+    /// `new` still serves reads of what it minted before.
+    pub fn with_router(mut self, router: ContractRouter) -> NFTsService {
+        self.router = Some(router);
+        self
+    }
+
+    /// Every registration is minted on all the contracts of `mirrors`, the
+    /// router is left unused. The record reads as a mint on the first
+    /// required contract, with an anchor per contract.
+    pub fn with_mirrors(mut self, mirrors: MirrorSet) -> NFTsService {
+        self.mirrors = Some(mirrors);
+        self
+    }
+
+    /// Values every completed mint in `fiat` (i.e. "EUR") with the rates of `prices`.
+    pub fn with_price_source(
+        mut self,
+        prices: Box<dyn PriceRepository + Sync + Send>,
+        fiat: &String,
+    ) -> NFTsService {
+        self.prices = Some(prices);
+        self.fiat = fiat.to_uppercase();
+        self
+    }
+
+    // reads go to the contract that minted the asset
+    fn backend_of(&self, tx: &BlockchainTx) -> ResultE<&Box<dyn NFTsRepository + Sync + Send>> {
+        let contract_id = match tx.contract_id() {
+            None => return Ok(&self.blockchain),
+            Some(id) if *id == self.blockchain.contract_id() => return Ok(&self.blockchain),
+            Some(id) => id,
+        };
+        self.router
+            .as_ref()
+            .and_then(|router| router.backend(contract_id))
+            .or_else(|| {
+                self.mirrors
+                    .as_ref()
+                    .and_then(|mirrors| mirrors.backend(contract_id))
+            })
+            .ok_or(BlockchainBackendError(format!("contract {} isn't served", contract_id)).into())
+    }
+
+    // the user's key for the chain of `backend`, created on first use
+    async fn user_keypair(
+        &self,
+        user_id: &String,
+        backend: &Box<dyn NFTsRepository + Sync + Send>,
+    ) -> ResultE<KeyPair> {
+        let chain = backend.chain_family();
+        if let Some(key) = self.keys_repo.get_by_id(user_id, &chain).await? {
+            return Ok(key);
+        }
+        let (key, is_new) = match &self.hd_wallet {
+            None => backend.create_keypair(user_id).await?,
+            Some(wallet) => {
+                let index = self.keys_repo.next_derivation_index(&chain).await?;
+                (wallet.derive_keypair(user_id, &chain, index).await?, true)
+            }
+        };
+        if is_new {
+            match self.keys_repo.add(&key).await {
+                //a concurrent first mint stored its own, that one is kept
+                Err(e) if e.is::<KeyPairAlreadyExistsError>() => {
+                    return match self.keys_repo.get_by_id(user_id, &chain).await? {
+                        Some(stored) => Ok(stored),
+                        None => Err(e),
+                    };
+                }
+                other => other?,
+            }
+        }
+        Ok(key)
+    }
+
+    // mints on every mirrored contract but those a previous attempt
+    // already anchored the registration on; a chain failing doesn't stop
+    // the others
+    async fn mint_mirrored(
+        &self,
+        mirrors: &MirrorSet,
+        asset_id: &Uuid,
+        user_id: &String,
+        price: &Option<u64>,
+        hash: &String,
+        hash_algo: &String,
+        counter: &u64,
+    ) -> ResultE<BlockchainTx> {
+        let primary = mirrors
+            .contract_ids()
+            .into_iter()
+            .find(|id| mirrors.is_required(id))
+            .unwrap();
+        let previous = self.tx_service.get_by_asset_id(asset_id).await?;
+        let done = |id: &u16| {
+            previous
+                .anchor(id)
+                .map(|anchor| anchor.mint_status() == MintingStatus::CompletedSuccessfully)
+                .unwrap_or(false)
+        };
+
+        let mut ttxx = if done(&primary) {
+            previous.clone()
+        } else {
+            let mut btx = BlockchainTxBuilder::new()
+                .asset_id(asset_id.to_owned())
+                .user_id(user_id)
+                .build();
+            btx.set_contract_id(&primary);
+            btx
+        };
+        let kept: Vec<ChainAnchor> = previous
+            .anchors()
+            .iter()
+            .filter(|anchor| done(anchor.contract_id()))
+            .cloned()
+            .collect();
+        ttxx.set_anchors(&kept);
+        ttxx.set_minted_status(MintingStatus::Started);
+        for backend in mirrors.backends() {
+            let id = backend.contract_id();
+            if !done(&id) {
+                let chain = backend.chain_family();
+                ttxx.set_anchor(&ChainAnchor::new(&id, &chain, mirrors.is_required(&id)));
+            }
+        }
+        self.tx_service.add(&ttxx).await?;
+
+        for backend in mirrors.backends() {
+            let id = backend.contract_id();
+            if done(&id) {
+                continue;
+            }
+            let anchor = ttxx.anchor(&id).unwrap().clone();
+            let minted = match self.user_keypair(user_id, backend).await {
+                Err(e) => Err(e),
+                Ok(key) => {
+                    backend
+                        .add(asset_id, &key, hash, hash_algo, price, counter)
+                        .await
+                }
+            };
+            match minted {
+                Ok(tx) => {
+                    ttxx.set_anchor(&anchor.completed(&tx));
+                    if id == primary {
+                        let anchors = ttxx.anchors().clone();
+                        let creation_time = ttxx.creation_time().to_owned();
+                        ttxx = tx;
+                        ttxx.set_user_id(user_id);
+                        ttxx.set_creation_time(&creation_time);
+                        ttxx.set_anchors(&anchors);
+                        ttxx.set_minted_status(MintingStatus::Started);
+                        self.value_in_fiat(&mut ttxx).await;
+                    }
+                }
+                Err(e) => {
+                    warn!("{} not anchored on contract {}: {}", asset_id, id, e);
+                    ttxx.set_anchor(&anchor.failed(&e.to_string()));
+                }
+            }
+            //what went through is kept if the process dies halfway
+            self.tx_service.update(&ttxx).await?;
+        }
+
+        let status = ttxx.anchors_status();
+        ttxx.set_minted_status(status.clone());
+        self.tx_service.update(&ttxx).await?;
+        if status == MintingStatus::CompletedSuccessfully {
+            return Ok(ttxx);
+        }
+        let missing = ttxx
+            .anchors()
+            .iter()
+            .filter(|anchor| {
+                anchor.required() && anchor.mint_status() != MintingStatus::CompletedSuccessfully
+            })
+            .map(|anchor| anchor.contract_id().to_owned())
+            .collect();
+        Err(TokenNotAnchoredOnRequiredChainsError(asset_id.to_owned(), missing).into())
+    }
+
+    // what the chain of `anchor` holds, an unreadable chain doesn't hide the rest
+    async fn anchor_info(&self, tx: &BlockchainTx, anchor: &ChainAnchor) -> NTFAnchorInfo {
+        let mut info = NTFAnchorInfo {
+            contract_id: anchor.contract_id().to_owned(),
+            chain: anchor.chain().to_owned(),
+            required: anchor.required(),
+            status: anchor.mint_status(),
+            tx: anchor.tx().clone(),
+            token: None,
+            hash_file: None,
+            state: None,
+        };
+        if anchor.mint_status() != MintingStatus::CompletedSuccessfully {
+            return info;
+        }
+        let anchored = tx.anchored_tx(anchor);
+        let backend = match self.backend_of(&anchored) {
+            Ok(backend) => backend,
+            Err(e) => {
+                warn!("{} anchor unread: {}", tx.asset_id(), e);
+                return info;
+            }
+        };
+        info.token = backend.token(&anchored);
+        if let Some(token) = &info.token {
+            match backend.get(token).await {
+                Ok(content) => {
+                    info.hash_file = Some(content.hashFile);
+                    info.state = Some(match content.state {
+                        Some(sts) => NTFState::from_str(&sts.to_string()).unwrap(),
+                        None => NTFState::Active,
+                    });
+                }
+                Err(e) => warn!("{} anchor {} unread: {}", tx.asset_id(), token, e),
+            }
+        }
+        info
+    }
+
+    // a missing rate must never fail a mint that is already on-chain
+    async fn value_in_fiat(&self, tx: &mut BlockchainTx) {
+        let (prices, cost) = match (&self.prices, tx.cost()) {
+            (Some(prices), Some(cost)) => (prices, cost.to_owned()),
+            _ => return,
+        };
+        let rate_op = prices.rate(&cost.currency(), &self.fiat).await;
+        let valuation: ResultE<FiatValuation> = match rate_op {
+            Err(e) => Err(e),
+            Ok(rate) => rate.value_of(&cost).map_err(|e| e.into()),
+        };
+        match valuation {
+            Ok(val) => tx.set_fiat_cost(&val),
+            Err(e) => warn!("no fiat value for {}: {}", tx.asset_id(), e),
         }
     }
 }
@@ -153,39 +429,52 @@ impl NFTsManipulation for NFTsService {
         hash: &String,
         hash_algo: &String,
         counter: &u64,
+    ) -> ResultE<BlockchainTx> {
+        self.try_mint_in_category(asset_id, user_id, &None, price, hash, hash_algo, counter)
+            .await
+    }
+
+    #[tracing::instrument()]
+    async fn try_mint_in_category(
+        &self,
+        asset_id: &Uuid,
+        user_id: &String,
+        category: &Option<String>,
+        price: &Option<u64>,
+        hash: &String,
+        hash_algo: &String,
+        counter: &u64,
     ) -> ResultE<BlockchainTx> {
         self
             .prechecks_before_minting_tx(asset_id, price)
             .await?;
 
-        let user_wallet_address;
+        if let Some(mirrors) = &self.mirrors {
+            return self
+                .mint_mirrored(mirrors, asset_id, user_id, price, hash, hash_algo, counter)
+                .await;
+        }
 
-        let user_wallet_address_op = self.keys_repo.get_by_id(user_id).await?;
-        match user_wallet_address_op {
-            None => {
-                let aux = self.blockchain.create_keypair(user_id).await?;
-                user_wallet_address = aux.0;
-                if aux.1 {
-                    self.keys_repo.add(&user_wallet_address).await?;
-                }
-            }
-            Some(aux) => {
-                user_wallet_address = aux;
-            }
+        let backend = match &self.router {
+            None => &self.blockchain,
+            Some(router) => router.pick(category)?,
         };
 
+        let user_wallet_address = self.user_keypair(user_id, backend).await?;
+
         //self.asset_service
         //    .mint_status(asset_id, &None, MintingStatus::Started)
         //    .await?;
 
-        let btx = BlockchainTxBuilder::new()
+        let mut btx = BlockchainTxBuilder::new()
             .asset_id(asset_id.to_owned())
+            .user_id(user_id)
             .mint_status(MintingStatus::Started)
             .build();
+        btx.set_contract_id(&backend.contract_id());
         self.tx_service.add(&btx).await?;
 
-        let transaction_op = self
-            .blockchain
+        let transaction_op = backend
             .add(
                 asset_id,
                 &user_wallet_address,
@@ -196,6 +485,10 @@ impl NFTsManipulation for NFTsService {
             )
             .await;
 
+        if let (Some(router), Err(e)) = (&self.router, &transaction_op) {
+            router.report_failure(&backend.contract_id(), e.as_ref());
+        }
+
         match transaction_op {
             Err(e) => {
                 //let asset = self.asset_service.get_by_id(asset_id).await?;
@@ -236,8 +529,14 @@ impl NFTsManipulation for NFTsService {
             }
             Ok(mut ttxx) => {
                 //let mut ttxx = self.tx_service.get_by_asset_id(asset_id).await?;
+                ttxx.set_user_id(user_id);
+                ttxx.set_creation_time(btx.creation_time());
                 ttxx.set_minted_status(MintingStatus::CompletedSuccessfully);
+                self.value_in_fiat(&mut ttxx).await;
                 self.tx_service.update(&ttxx).await?;
+                if let Some(router) = &self.router {
+                    router.report_success(&ttxx);
+                }
                 Ok(ttxx)
                 /*
                 let tx_res = transaction.tx().clone();
@@ -267,21 +566,36 @@ impl NFTsManipulation for NFTsService {
             .into());
         }
         let successfully = tx.unwrap();
-        let token = successfully.tx().clone().unwrap();
+        //mirrored registrations are read from the first chain holding them
+        let read = match successfully
+            .anchors()
+            .iter()
+            .find(|anchor| anchor.mint_status() == MintingStatus::CompletedSuccessfully)
+        {
+            None => successfully.clone(),
+            Some(anchor) => successfully.anchored_tx(anchor),
+        };
+        let backend = self.backend_of(&read)?;
+        let token = backend.token(&read).unwrap();
 
-        let aux = self.blockchain.get(&token).await?;
+        let aux = backend.get(&token).await?;
         let state;
         if let Some(sts) = aux.state {
             state = NTFState::from_str(&sts.to_string()).unwrap()
         } else {
             state = NTFState::Active;
         }
+        let mut anchors = Vec::new();
+        for anchor in successfully.anchors() {
+            anchors.push(self.anchor_info(&successfully, anchor).await);
+        }
         let res = NTFContentInfo {
             hash_file: aux.hashFile,
             hash_algorithm: aux.hashAlgo,
             uri: aux.uri,
             price: aux.price,
             state,
+            anchors,
         };
         Ok(res)
     }
@@ -297,6 +611,11 @@ impl Clone for NFTsService {
             //asset_service: self.asset_service.clone(),
             config: self.config.clone(),
             tx_service: self.tx_service.clone(),
+            prices: self.prices.clone(),
+            fiat: self.fiat.clone(),
+            hd_wallet: self.hd_wallet.clone(),
+            router: self.router.clone(),
+            mirrors: self.mirrors.clone(),
         };
         return aux;
     }
@@ -309,6 +628,21 @@ pub struct NTFContentInfo {
     pub uri: Option<String>,
     pub price: Option<u64>,
     pub state: NTFState,
+    // one per chain of a mirrored registration, none otherwise
+    pub anchors: Vec<NTFAnchorInfo>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct NTFAnchorInfo {
+    pub contract_id: u16,
+    pub chain: ChainFamily,
+    pub required: bool,
+    pub status: MintingStatus,
+    pub tx: Option<String>,
+    pub token: Option<String>,
+    // as read from the chain, None while not anchored or unreadable
+    pub hash_file: Option<String>,
+    pub state: Option<NTFState>,
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
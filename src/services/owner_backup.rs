@@ -0,0 +1,160 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lib_config::config::Config;
+
+use crate::blockchains::signer::owner_secret_address;
+use crate::blockchains::shamir::{combine_shares, split_secret};
+use crate::errors::backup::BackupShareError;
+use crate::models::contract::Contract;
+use crate::models::keypair::ChainFamily;
+use crate::models::secret::Secret;
+use crate::repositories::contract::{ContractRepo, ContractRepository};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+#[async_trait]
+pub trait OwnerBackupManipulation {
+    async fn backup_owner_secret(
+        &self,
+        contract_id: &u16,
+        threshold: u8,
+        custodians: u8,
+    ) -> ResultE<OwnerBackup>;
+    async fn recover_owner_secret(
+        &self,
+        contract_id: &u16,
+        chain: &ChainFamily,
+        shares: &Vec<Secret>,
+        key_id: &String,
+    ) -> ResultE<Contract>;
+}
+
+/// Offline backup of contract owner secrets, for when the KMS key or the
+/// account holding it is lost. The secret is split in Shamir shares handed
+/// to custodians; enough of them bring it back, cyphered under a new key.
+#[derive(Debug)]
+pub struct OwnerBackupService {
+    contracts_repo: ContractRepo,
+    config: Config,
+}
+
+impl OwnerBackupService {
+    pub fn new(contracts_repo: ContractRepo, config: &Config) -> OwnerBackupService {
+        OwnerBackupService {
+            contracts_repo,
+            config: config.to_owned(),
+        }
+    }
+
+    fn label(contract_id: &u16) -> String {
+        format!("contract-{}", contract_id)
+    }
+}
+
+#[async_trait]
+impl OwnerBackupManipulation for OwnerBackupService {
+    /// `threshold` of the `custodians` shares rebuild the secret.
+    #[tracing::instrument()]
+    async fn backup_owner_secret(
+        &self,
+        contract_id: &u16,
+        threshold: u8,
+        custodians: u8,
+    ) -> ResultE<OwnerBackup> {
+        let contract = self.contracts_repo.get_by_id(contract_id).await?;
+        //kms signer keys never leave kms, there is nothing to split
+        let cyphered = contract
+            .owner_secret()
+            .clone()
+            .ok_or(BackupShareError(format!(
+                "contract {} holds no owner secret",
+                contract_id
+            )))?;
+        let key_id = contract
+            .owner_secret_key_id()
+            .clone()
+            .unwrap_or(self.config.env_vars().kms_key_id().unwrap());
+        let secret = cyphered.uncypher(&key_id, &self.config).await?;
+
+        let label = OwnerBackupService::label(contract_id);
+        let shares = split_secret(&secret, &label, threshold, custodians)?;
+        //the custodians are gone by the time a broken split shows up
+        let rebuilt = combine_shares(&label, &shares[0..threshold as usize])?;
+        if rebuilt.expose() != secret.expose() {
+            return Err(BackupShareError("shares don't rebuild the secret".to_string()).into());
+        }
+        Ok(OwnerBackup {
+            contract_id: contract_id.to_owned(),
+            owner_address: contract.owner_address().clone(),
+            threshold,
+            created_at: Utc::now(),
+            shares,
+        })
+    }
+
+    /// Rebuilds the owner secret and stores it cyphered under `key_id`.
+    /// Shares are checked against the contract's owner address first.
+    #[tracing::instrument(skip(shares))]
+    async fn recover_owner_secret(
+        &self,
+        contract_id: &u16,
+        chain: &ChainFamily,
+        shares: &Vec<Secret>,
+        key_id: &String,
+    ) -> ResultE<Contract> {
+        let mut contract = self.contracts_repo.get_by_id(contract_id).await?;
+        let secret = combine_shares(&OwnerBackupService::label(contract_id), shares)?;
+
+        let address = owner_secret_address(chain, &secret)?;
+        match contract.owner_address() {
+            Some(owner) if owner.to_lowercase() != address.to_lowercase() => {
+                let mssag = format!("shares rebuild {}, the owner is {}", address, owner);
+                return Err(BackupShareError(mssag).into());
+            }
+            Some(_) => {}
+            None => contract.set_owner_address(&address),
+        }
+
+        contract.set_owner_secret(&secret.cypher(key_id, &self.config).await?);
+        contract.set_owner_secret_key_id(key_id);
+        self.contracts_repo.update(&contract).await?;
+        Ok(contract)
+    }
+}
+
+impl Clone for OwnerBackupService {
+    #[tracing::instrument()]
+    fn clone(&self) -> OwnerBackupService {
+        let aux = OwnerBackupService {
+            contracts_repo: self.contracts_repo.clone(),
+            config: self.config.clone(),
+        };
+        return aux;
+    }
+}
+
+/// One share per custodian, in order. Shares are `Secret`s, they never end
+/// up in logs; `shares[i].expose()` is what custodian `i` keeps.
+#[derive(Clone, Debug)]
+pub struct OwnerBackup {
+    pub contract_id: u16,
+    pub owner_address: Option<String>,
+    pub threshold: u8,
+    pub created_at: DateTime<Utc>,
+    pub shares: Vec<Secret>,
+}
+
+impl fmt::Display for OwnerBackup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "contract {}: {} of {} shares, {}",
+            self.contract_id,
+            self.threshold,
+            self.shares.len(),
+            self.created_at
+        )
+    }
+}
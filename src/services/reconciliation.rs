@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::blockchains::chain::{ContentState, NFTsRepository};
+use crate::models::block_tx::{BlockchainTx, MintingStatus};
+
+use super::block_tx::{BlockchainTxManipulation, BlockchainTxService};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+const PAGE_SIZE: i32 = 100;
+
+#[async_trait]
+pub trait ReconciliationManipulation {
+    async fn reconcile(&self) -> ResultE<ReconciliationReport>;
+}
+
+/// Audits the off-chain records in `truly_blockchain_txs` against the
+/// contract the blockchain backend points to, mirrored registrations
+/// through their anchor on it.
+#[derive(Debug)]
+pub struct ReconciliationService {
+    blockchain: Box<dyn NFTsRepository + Sync + Send>,
+    tx_service: BlockchainTxService,
+}
+
+impl ReconciliationService {
+    pub fn new(
+        blockchain: Box<dyn NFTsRepository + Sync + Send>,
+        tx_service: BlockchainTxService,
+    ) -> ReconciliationService {
+        ReconciliationService {
+            blockchain,
+            tx_service,
+        }
+    }
+
+    async fn check_tx(&self, tx: &BlockchainTx) -> Vec<Discrepancy> {
+        let mut found = Vec::new();
+        let asset_id = tx.asset_id().to_owned();
+
+        let token = match self.blockchain.token(tx) {
+            None => {
+                found.push(Discrepancy::new(
+                    asset_id,
+                    None,
+                    DiscrepancyKind::MissingOnChain,
+                    None,
+                    Some("record has no on-chain reference".to_string()),
+                ));
+                return found;
+            }
+            Some(tkn) => tkn,
+        };
+
+        let content = match self.blockchain.get(&token).await {
+            Err(e) => {
+                found.push(Discrepancy::new(
+                    asset_id,
+                    Some(token),
+                    DiscrepancyKind::MissingOnChain,
+                    None,
+                    Some(e.to_string()),
+                ));
+                return found;
+            }
+            Ok(cnt) => cnt,
+        };
+
+        if let Some(hash) = tx.hash_file() {
+            if *hash != content.hashFile {
+                found.push(Discrepancy::new(
+                    asset_id,
+                    Some(token.clone()),
+                    DiscrepancyKind::HashMismatch,
+                    Some(hash.clone()),
+                    Some(content.hashFile.clone()),
+                ));
+            }
+        }
+
+        //not every contract keeps the algorithm on-chain
+        if let Some(algorithm) = tx.hash_algorithm() {
+            if !content.hashAlgo.is_empty() && *algorithm != content.hashAlgo {
+                found.push(Discrepancy::new(
+                    asset_id,
+                    Some(token.clone()),
+                    DiscrepancyKind::AlgorithmMismatch,
+                    Some(algorithm.clone()),
+                    Some(content.hashAlgo.clone()),
+                ));
+            }
+        }
+
+        if let Some(ContentState::Inactive) = content.state {
+            found.push(Discrepancy::new(
+                asset_id,
+                Some(token),
+                DiscrepancyKind::UnexpectedState,
+                Some(ContentState::Active.to_string()),
+                Some(ContentState::Inactive.to_string()),
+            ));
+        }
+
+        found
+    }
+}
+
+#[async_trait]
+impl ReconciliationManipulation for ReconciliationService {
+    #[tracing::instrument()]
+    async fn reconcile(&self) -> ResultE<ReconciliationReport> {
+        let contract_id = self.blockchain.contract_id();
+        let mut report = ReconciliationReport::new(contract_id);
+
+        let mut known_assets = HashSet::new();
+        let mut unfinished = HashSet::new();
+
+        let mut cursor = None;
+        loop {
+            let page = self.tx_service.list(&PAGE_SIZE, &cursor).await?;
+
+            for row in page.items() {
+                //mirrored registrations are one row, anchored on every contract
+                let tx = row
+                    .per_contract()
+                    .into_iter()
+                    .find(|tx| *tx.contract_id() == Some(contract_id));
+                let tx = match tx {
+                    None => {
+                        if row.mint_status() == MintingStatus::CompletedSuccessfully {
+                            report.skipped += 1;
+                        }
+                        continue;
+                    }
+                    Some(tx) => tx,
+                };
+                let asset_id = tx.asset_id().to_string();
+                if tx.mint_status() != MintingStatus::CompletedSuccessfully {
+                    unfinished.insert(asset_id);
+                    continue;
+                }
+                known_assets.insert(asset_id);
+
+                report.checked += 1;
+                let mut discrepancies = self.check_tx(&tx).await;
+                if discrepancies.is_empty() {
+                    report.matched += 1;
+                } else {
+                    report.discrepancies.append(&mut discrepancies);
+                }
+            }
+
+            cursor = page.next_cursor().clone();
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        //the other way around: everything the contract says it minted must be tracked
+        let on_chain = self.blockchain.minted_asset_ids().await?;
+        for asset_id in on_chain {
+            if known_assets.contains(&asset_id) {
+                continue;
+            }
+            let kind = if unfinished.contains(&asset_id) {
+                DiscrepancyKind::StatusMismatch
+            } else {
+                DiscrepancyKind::MissingOffChain
+            };
+            report.discrepancies.push(Discrepancy {
+                asset_id: Uuid::parse_str(asset_id.as_str()).ok(),
+                token: Some(asset_id),
+                kind,
+                expected: None,
+                found: None,
+            });
+        }
+
+        report.finished_at = Utc::now();
+        Ok(report)
+    }
+}
+
+impl Clone for ReconciliationService {
+    #[tracing::instrument()]
+    fn clone(&self) -> ReconciliationService {
+        let aux = ReconciliationService {
+            blockchain: self.blockchain.clone(),
+            tx_service: self.tx_service.clone(),
+        };
+        return aux;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ReconciliationReport {
+    pub contract_id: u16,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub checked: u64,
+    pub matched: u64,
+    pub skipped: u64,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReconciliationReport {
+    pub fn new(contract_id: u16) -> ReconciliationReport {
+        ReconciliationReport {
+            contract_id,
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            checked: 0,
+            matched: 0,
+            skipped: 0,
+            discrepancies: Vec::new(),
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+impl fmt::Display for ReconciliationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", json!(self).to_string())
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Discrepancy {
+    pub asset_id: Option<Uuid>,
+    pub token: Option<String>,
+    pub kind: DiscrepancyKind,
+    pub expected: Option<String>,
+    pub found: Option<String>,
+}
+
+impl Discrepancy {
+    fn new(
+        asset_id: Uuid,
+        token: Option<String>,
+        kind: DiscrepancyKind,
+        expected: Option<String>,
+        found: Option<String>,
+    ) -> Discrepancy {
+        Discrepancy {
+            asset_id: Some(asset_id),
+            token,
+            kind,
+            expected,
+            found,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum DiscrepancyKind {
+    HashMismatch,
+    AlgorithmMismatch,
+    UnexpectedState,
+    MissingOnChain,
+    MissingOffChain,
+    StatusMismatch,
+}
+
+impl fmt::Display for DiscrepancyKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiscrepancyKind::HashMismatch => write!(f, "Hash mismatch"),
+            DiscrepancyKind::AlgorithmMismatch => write!(f, "Algorithm mismatch"),
+            DiscrepancyKind::UnexpectedState => write!(f, "Unexpected state"),
+            DiscrepancyKind::MissingOnChain => write!(f, "Missing on-chain"),
+            DiscrepancyKind::MissingOffChain => write!(f, "Missing off-chain"),
+            DiscrepancyKind::StatusMismatch => write!(f, "Status mismatch"),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lib_config::config::Config;
+use url::Url;
+use web3::ethabi::Token;
+use web3::types::Address;
+
+use crate::blockchains::chain::node_url;
+use crate::blockchains::ganache::EvmArtifact;
+use crate::blockchains::key_cache::KeyCache;
+use crate::blockchains::signer::contract_owner_signer;
+use crate::blockchains::sui::{
+    check_hasher_package, package_modules, pick_gas_coin, upgrade_cap_package, upgrade_package,
+    SuiPackage, PUBLISH_GAS_BUDGET,
+};
+use crate::errors::contract::ContractUpgradeError;
+use crate::errors::deployment::DeploymentError;
+use crate::models::blockchain::Blockchain;
+use crate::models::contract::{Contract, ContractVersion};
+use crate::models::keypair::ChainFamily;
+use crate::repositories::blockchain::{BlockchainRepo, BlockchainRepository};
+use crate::repositories::contract::{ContractRepo, ContractRepository};
+
+use super::deployment::{
+    deploy_evm_artifact, evm_client, evm_cost, evm_proxy_implementation, send_evm_tx,
+    DeployedContract,
+};
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+#[async_trait]
+pub trait UpgradeManipulation {
+    async fn upgrade_sui_package(
+        &self,
+        contract_id: &u16,
+        package: &SuiPackage,
+    ) -> ResultE<DeployedContract>;
+    async fn upgrade_evm_proxy(
+        &self,
+        contract_id: &u16,
+        implementation: &EvmArtifact,
+    ) -> ResultE<DeployedContract>;
+}
+
+/// Moves registered contracts to new code, keeping their id and address:
+/// new Sui package versions through the `UpgradeCap`, new implementations
+/// behind EVM proxies. Every upgrade adds a version to the contract record.
+#[derive(Debug)]
+pub struct UpgradeService {
+    contracts_repo: ContractRepo,
+    blockchains_repo: BlockchainRepo,
+    config: Config,
+}
+
+impl UpgradeService {
+    pub fn new(
+        contracts_repo: ContractRepo,
+        blockchains_repo: BlockchainRepo,
+        config: &Config,
+    ) -> UpgradeService {
+        UpgradeService {
+            contracts_repo,
+            blockchains_repo,
+            config: config.to_owned(),
+        }
+    }
+
+    // the contract to upgrade and its blockchain, refused before anything
+    // is sent if it can't take a new version
+    async fn upgradable(
+        &self,
+        contract_id: &u16,
+        family: &ChainFamily,
+    ) -> ResultE<(Contract, Blockchain)> {
+        let contract = self.contracts_repo.get_by_id(contract_id).await?;
+        if contract.status().is_final() {
+            let mssag = format!("contract {} is {}", contract_id, contract.status());
+            return Err(ContractUpgradeError(mssag).into());
+        }
+        let blockchain = self
+            .blockchains_repo
+            .get_by_id(contract.blockchain())
+            .await?;
+        if *blockchain.family() != Some(family.to_owned()) {
+            let mssag = format!("contract {} isn't on a {} blockchain", contract_id, family);
+            return Err(ContractUpgradeError(mssag).into());
+        }
+        Ok((contract, blockchain))
+    }
+
+    // the package the UpgradeCap of `contract` is at. An upgrade that made
+    // it on chain but not to the record, as the update or the hasher check
+    // failed, is recorded here without its tx, so retrying the upgrade
+    // recovers from it
+    async fn reconciled_sui_package(
+        &self,
+        contract: &mut Contract,
+        url: &Url,
+        upgrade_cap: &String,
+    ) -> ResultE<String> {
+        let (package_id, version) = upgrade_cap_package(url, upgrade_cap).await?;
+        let recorded = contract.current_version().map(|v| v.version).unwrap_or(0);
+        if version < recorded {
+            let mssag = format!(
+                "contract {} records version {}, its UpgradeCap is at {}",
+                contract.id(),
+                recorded,
+                version
+            );
+            return Err(ContractUpgradeError(mssag).into());
+        }
+        if version > recorded {
+            let modules = package_modules(url, &package_id).await?;
+            let mssag = format!(
+                "contract {} recorded up to version {}, recording {} from chain",
+                contract.id(),
+                recorded,
+                version
+            );
+            tracing::warn!(mssag);
+            contract.add_version(&ContractVersion {
+                version,
+                code_address: package_id.clone(),
+                interface: modules.join(","),
+                tx: None,
+                from: Utc::now(),
+            })?;
+        }
+        Ok(package_id)
+    }
+}
+
+#[async_trait]
+impl UpgradeManipulation for UpgradeService {
+    /// Publishes `package` as the next version of the package of
+    /// `contract_id`, signed by its owner, and records it once the hasher
+    /// functions are checked. Mints go to the new version from then on.
+    /// Versions the chain is at but the record missed, from a call that
+    /// failed after upgrading, are recorded first.
+    #[tracing::instrument()]
+    async fn upgrade_sui_package(
+        &self,
+        contract_id: &u16,
+        package: &SuiPackage,
+    ) -> ResultE<DeployedContract> {
+        let (mut contract, blockchain) = self.upgradable(contract_id, &ChainFamily::Sui).await?;
+        let upgrade_cap = match contract.upgrade_cap() {
+            None => {
+                let mssag = format!("contract {} has no UpgradeCap recorded", contract_id);
+                return Err(ContractUpgradeError(mssag).into());
+            }
+            Some(cap) => cap.to_owned(),
+        };
+        let signer = contract_owner_signer(
+            &contract,
+            &ChainFamily::Sui,
+            &self.config,
+            &KeyCache::default(),
+        )
+        .await?;
+
+        let url = node_url(&blockchain, &self.config);
+        //upgrades extend the latest version, the one on chain
+        let package_id = self
+            .reconciled_sui_package(&mut contract, &url, &upgrade_cap)
+            .await?;
+        let gas_coin = pick_gas_coin(&url, signer.address(), PUBLISH_GAS_BUDGET).await?;
+        let published = upgrade_package(
+            &url,
+            signer.as_ref(),
+            package,
+            &package_id,
+            &upgrade_cap,
+            &gas_coin,
+        )
+        .await?;
+        check_hasher_package(&url, &published.package_id).await?;
+
+        contract.add_version(&ContractVersion::new(
+            published.version,
+            &published.package_id,
+            &published.modules.join(","),
+            &published.digest,
+        ))?;
+        self.contracts_repo.update(&contract).await?;
+        Ok(DeployedContract {
+            contract,
+            tx: published.digest,
+            block_number: Some(published.epoch),
+            cost: published.cost,
+        })
+    }
+
+    /// Deploys `implementation` and points the proxy of `contract_id` at
+    /// it through its proxy admin, signed by the owner. State stays at the
+    /// proxy: new implementations only append state variables.
+    #[tracing::instrument()]
+    async fn upgrade_evm_proxy(
+        &self,
+        contract_id: &u16,
+        implementation: &EvmArtifact,
+    ) -> ResultE<DeployedContract> {
+        let (mut contract, blockchain) = self.upgradable(contract_id, &ChainFamily::Evm).await?;
+        let proxy_admin = match contract.proxy_admin() {
+            None => {
+                let mssag = format!(
+                    "contract {} isn't behind a proxy, deploy a new contract instead",
+                    contract_id
+                );
+                return Err(ContractUpgradeError(mssag).into());
+            }
+            Some(admin) => Address::from_str(admin.as_str())?,
+        };
+        let proxy = match contract.address() {
+            None => {
+                let mssag = format!("contract {} has no proxy address recorded", contract_id);
+                return Err(ContractUpgradeError(mssag).into());
+            }
+            Some(proxy) => Address::from_str(proxy.as_str())?,
+        };
+        let admin_artifact = EvmArtifact::light_nft_proxy_admin();
+        let signer = contract_owner_signer(
+            &contract,
+            &ChainFamily::Evm,
+            &self.config,
+            &KeyCache::default(),
+        )
+        .await?;
+        let web3 = evm_client(&blockchain, &self.config)?;
+        let confirmations: usize = (*blockchain.confirmations()).into();
+
+        let (new_implementation, deploy_receipt) =
+            deploy_evm_artifact(&web3, signer.as_ref(), implementation, &[], confirmations).await?;
+        let upgrade = admin_artifact
+            .function("upgrade")?
+            .encode_input(&[Token::Address(proxy), Token::Address(new_implementation)])?;
+        let upgrade_receipt = send_evm_tx(
+            &web3,
+            signer.as_ref(),
+            Some(proxy_admin),
+            upgrade,
+            confirmations,
+        )
+        .await?;
+        if evm_proxy_implementation(&web3, proxy_admin, proxy).await? != new_implementation {
+            let mssag = format!("{:?} doesn't run {:?}", proxy, new_implementation);
+            return Err(DeploymentError(mssag).into());
+        }
+
+        let tx = format!("{:?}", upgrade_receipt.transaction_hash);
+        let version = contract.current_version().map(|v| v.version).unwrap_or(0) + 1;
+        contract.add_version(&ContractVersion::new(
+            version,
+            &format!("{:?}", new_implementation),
+            &implementation.interface(),
+            &tx,
+        ))?;
+        self.contracts_repo.update(&contract).await?;
+        Ok(DeployedContract {
+            contract,
+            tx,
+            block_number: upgrade_receipt.block_number.map(|num| num.as_u64()),
+            cost: evm_cost(&[&deploy_receipt, &upgrade_receipt])?,
+        })
+    }
+}
+
+impl Clone for UpgradeService {
+    #[tracing::instrument()]
+    fn clone(&self) -> UpgradeService {
+        let aux = UpgradeService {
+            contracts_repo: self.contracts_repo.clone(),
+            blockchains_repo: self.blockchains_repo.clone(),
+            config: self.config.clone(),
+        };
+        return aux;
+    }
+}
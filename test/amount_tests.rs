@@ -0,0 +1,41 @@
+use lib_blockchain::models::amount::{Amount, Currency, Unit};
+use spectral::{assert_that, result::ResultAssertions};
+
+#[test]
+fn amount_exact_conversions_test() {
+    let cost = Amount::wei(21_000 * 1_000_000_000);
+
+    assert_eq!(cost.convert(Unit::Gwei).unwrap(), Amount::new(21_000, Unit::Gwei));
+    assert_eq!(cost.to_decimal_string(Unit::Ether).unwrap(), "0.000021");
+    assert_that!(&cost.convert(Unit::Ether)).is_err();
+    assert_that!(&cost.convert(Unit::Mist)).is_err();
+
+    let sui = Amount::new(3, Unit::Sui);
+    assert_eq!(sui.to_base().unwrap(), Amount::mist(3_000_000_000));
+    assert_eq!(sui.currency(), Currency::Sui);
+}
+
+#[test]
+fn amount_decimal_parsing_test() {
+    //decimal strings parse exactly down to the base unit
+    let legacy = Amount::from_decimal_str("0.000021", Unit::Gwei).unwrap();
+    assert_eq!(legacy, Amount::wei(21));
+
+    let whole = Amount::from_decimal_str("1.5", Unit::Ether).unwrap();
+    assert_eq!(whole, Amount::wei(1_500_000_000_000_000_000));
+
+    assert_that!(&Amount::from_decimal_str("0.0000000001", Unit::Gwei)).is_err();
+    assert_that!(&Amount::from_decimal_str("-1", Unit::Wei)).is_err();
+}
+
+#[test]
+fn amount_arithmetic_test() {
+    let a = Amount::new(1, Unit::Gwei);
+    let b = Amount::wei(5);
+    assert_eq!(a.checked_add(&b).unwrap(), Amount::wei(1_000_000_005));
+    assert_that!(&a.checked_add(&Amount::mist(1))).is_err();
+    assert_that!(&Amount::wei(u128::MAX).checked_mul(2)).is_err();
+
+    let json = serde_json::to_string(&b).unwrap();
+    assert_eq!(json, r#"{"value":"5","unit":"Wei"}"#);
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::env;
+
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement,
+    KeyType, Projection, ProjectionType, ScalarAttributeType,
+};
+use chrono::{TimeZone, Utc};
+use lib_blockchain::models::amount::{Amount, Unit};
+use lib_blockchain::models::block_tx::MintingStatus;
+use lib_blockchain::repositories::block_tx::{
+    mapping_from_doc_to_blockchain, BlockchainTxRepo, BlockchainTxRepository, TX_COST,
+    TX_COST_UNIT, TX_CREATION_TIME, TX_CURRENCY, TX_GAS_USED, TX_LAST_UPDATE_TIME,
+    TX_MINTED_STATUS_FIELD_NAME,
+};
+use lib_blockchain::repositories::schema_block_tx::{
+    BlockTxSchema, TX_ASSET_ID_FIELD_PK, TX_FIELD, TX_INDEX_NAME, TX_TABLE_NAME, TX_USER_ID_FIELD,
+};
+use lib_config::config::Config;
+use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
+use lib_config::infra::build_local_stack_connection;
+use testcontainers::*;
+
+// a mint as the evm backend stored it before amounts were exact
+fn legacy_row(cost: &str, currency: Option<&str>) -> HashMap<String, AttributeValue> {
+    let mut doc = HashMap::from([
+        (
+            TX_ASSET_ID_FIELD_PK.to_string(),
+            AttributeValue::S("1d4b3a5c-8f2e-4d0a-9c57-3e1f0b2a6d11".to_string()),
+        ),
+        (
+            TX_CREATION_TIME.to_string(),
+            AttributeValue::S("2023-05-04T10:11:12+00:00".to_string()),
+        ),
+        (
+            TX_LAST_UPDATE_TIME.to_string(),
+            AttributeValue::S("2023-05-04T10:11:40+00:00".to_string()),
+        ),
+        (
+            TX_GAS_USED.to_string(),
+            AttributeValue::N("21000".to_string()),
+        ),
+        (TX_COST.to_string(), AttributeValue::N(cost.to_string())),
+    ]);
+    if let Some(val) = currency {
+        doc.insert(TX_CURRENCY.to_string(), AttributeValue::S(val.to_string()));
+    }
+    doc
+}
+
+// what the evm backend stored, wei_to_gwei(gas) * wei_to_gwei(price)
+fn legacy_cost(gas: u128, price: u128) -> String {
+    let cost = (gas as f64 / 1_000_000_000.0) * (price as f64 / 1_000_000_000.0);
+    cost.to_string()
+}
+
+#[test]
+fn legacy_cost_rows_test() {
+    //21000 gas at 20 gwei, float noise included
+    let cost = legacy_cost(21000, 20_000_000_000);
+    assert_eq!(cost, "0.00041999999999999996");
+    let tx = mapping_from_doc_to_blockchain(&legacy_row(&cost, Some("gweis")));
+    assert_eq!(*tx.cost(), Some(Amount::wei(420_000_000_000_000)));
+
+    let tx = mapping_from_doc_to_blockchain(&legacy_row(&cost, None));
+    assert_eq!(*tx.cost(), Some(Amount::wei(420_000_000_000_000)));
+
+    let cost = legacy_cost(148_231, 1_875_000_007);
+    let tx = mapping_from_doc_to_blockchain(&legacy_row(&cost, Some("gweis")));
+    assert_eq!(*tx.cost(), Some(Amount::wei(148_231 * 1_875_000_007)));
+
+    //as dynamo may hand numbers back
+    let tx = mapping_from_doc_to_blockchain(&legacy_row("4.2E-5", Some("gweis")));
+    assert_eq!(*tx.cost(), Some(Amount::wei(42_000_000_000_000)));
+
+    //to the nearest wei
+    let tx = mapping_from_doc_to_blockchain(&legacy_row("0.0000000000000000006", None));
+    assert_eq!(*tx.cost(), Some(Amount::wei(1)));
+
+    //bad amounts or units don't come back as a cost
+    let tx = mapping_from_doc_to_blockchain(&legacy_row(&cost, Some("doubloons")));
+    assert!(tx.cost().is_none());
+    let tx = mapping_from_doc_to_blockchain(&legacy_row("0.0004.2", None));
+    assert!(tx.cost().is_none());
+
+    //rows with a unit are exact
+    let mut row = legacy_row("420000", None);
+    row.insert(
+        TX_COST_UNIT.to_string(),
+        AttributeValue::S("gwei".to_string()),
+    );
+    let tx = mapping_from_doc_to_blockchain(&row);
+    assert_eq!(*tx.cost(), Some(Amount::new(420_000, Unit::Gwei)));
+}
+
+#[tokio::test]
+async fn block_tx_listing_indexes_migration_test(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+
+    let docker = clients::Cli::default();
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("dynamodb");
+    let node = docker.run(local_stack);
+    let host_port = node.get_host_port_ipv4(4566);
+
+    let shared_config = build_local_stack_connection(host_port).await;
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    //the table as deployed before the listing indexes
+    let client = aws_sdk_dynamodb::Client::new(config.aws_config());
+    client
+        .create_table()
+        .table_name(TX_TABLE_NAME)
+        .key_schema(
+            KeySchemaElement::builder()
+                .attribute_name(TX_ASSET_ID_FIELD_PK)
+                .key_type(KeyType::Hash)
+                .build(),
+        )
+        .global_secondary_indexes(
+            GlobalSecondaryIndex::builder()
+                .index_name(TX_INDEX_NAME)
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .attribute_name(TX_FIELD)
+                        .key_type(KeyType::Hash)
+                        .build(),
+                )
+                .projection(
+                    Projection::builder()
+                        .projection_type(ProjectionType::KeysOnly)
+                        .build(),
+                )
+                .build(),
+        )
+        .attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name(TX_ASSET_ID_FIELD_PK)
+                .attribute_type(ScalarAttributeType::S)
+                .build(),
+        )
+        .attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name(TX_FIELD)
+                .attribute_type(ScalarAttributeType::S)
+                .build(),
+        )
+        .billing_mode(BillingMode::PayPerRequest)
+        .send()
+        .await?;
+    let mut doc = legacy_row("0.00041999999999999996", Some("gweis"));
+    doc.insert(
+        TX_USER_ID_FIELD.to_string(),
+        AttributeValue::S("user-1".to_string()),
+    );
+    doc.insert(
+        TX_MINTED_STATUS_FIELD_NAME.to_string(),
+        AttributeValue::S(MintingStatus::CompletedSuccessfully.to_string()),
+    );
+    client
+        .put_item()
+        .table_name(TX_TABLE_NAME)
+        .set_item(Some(doc))
+        .send()
+        .await?;
+
+    let mut migrated = false;
+    for _ in 0..10 {
+        migrated = BlockTxSchema::add_missing_indexes(&config).await?;
+        if migrated {
+            break;
+        }
+    }
+    assert!(migrated);
+
+    let repo = BlockchainTxRepo::new(&config);
+    let from = Utc.with_ymd_and_hms(2023, 5, 4, 0, 0, 0).unwrap();
+    let to = Utc.with_ymd_and_hms(2023, 5, 5, 0, 0, 0).unwrap();
+    let listed = repo.get_by_creation_time(&from, &to, &10, &None).await?;
+    assert!(listed.items().is_empty()); //no creation day yet
+    let by_user = repo
+        .get_by_user(&"user-1".to_string(), &None, &None, &10, &None)
+        .await?;
+    assert_eq!(by_user.items().len(), 1);
+
+    let filled = repo.backfill_creation_day(&10, &None).await?;
+    assert_eq!(filled.items().len(), 1);
+    assert!(!filled.has_next());
+    let again = repo.backfill_creation_day(&10, &None).await?;
+    assert!(again.items().is_empty());
+
+    let listed = repo.get_by_creation_time(&from, &to, &10, &None).await?;
+    assert_eq!(listed.items().len(), 1);
+    assert_eq!(
+        *listed.items()[0].cost(),
+        Some(Amount::wei(420_000_000_000_000))
+    );
+
+    Ok(())
+}
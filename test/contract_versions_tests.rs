@@ -0,0 +1,45 @@
+use chrono::{Duration, Utc};
+use lib_blockchain::models::contract::{Contract, ContractStatus, ContractVersion};
+use spectral::{assert_that, result::ResultAssertions};
+
+fn version(number: u64, code_address: &str, hours_ago: i64) -> ContractVersion {
+    ContractVersion {
+        version: number,
+        code_address: code_address.to_string(),
+        interface: "hasher".to_string(),
+        tx: None,
+        from: Utc::now() - Duration::hours(hours_ago),
+    }
+}
+
+#[test]
+fn contract_version_history_test() {
+    let mut contract = Contract::new();
+    contract.set_address(&"0x5dd2".to_string());
+    contract.set_status(&ContractStatus::Enabled);
+    //contracts older than the history run what is at their address
+    assert!(contract.current_version().is_none());
+    assert_eq!(contract.code_address(), Some("0x5dd2".to_string()));
+
+    contract.add_version(&version(1, "0x5dd2", 48)).unwrap();
+    contract.add_version(&version(2, "0x77a1", 24)).unwrap();
+    assert_eq!(contract.versions().len(), 2);
+    assert_eq!(contract.code_address(), Some("0x77a1".to_string()));
+    assert_eq!(*contract.address(), Some("0x5dd2".to_string()));
+
+    //what was in effect per period
+    let at = |hours_ago: i64| Utc::now() - Duration::hours(hours_ago);
+    assert!(contract.version_at(&at(72)).is_none());
+    assert_eq!(contract.version_at(&at(36)).unwrap().version, 1);
+    assert_eq!(contract.version_at(&at(1)).unwrap().version, 2);
+
+    //versions only go up
+    assert_that!(contract.add_version(&version(2, "0x99b3", 0))).is_err();
+    assert_that!(contract.add_version(&version(3, "0x99b3", 30))).is_err();
+    assert_eq!(contract.versions().len(), 2);
+
+    //retired contracts aren't upgraded
+    contract.change_status(&ContractStatus::Retired).unwrap();
+    assert_that!(contract.add_version(&version(3, "0x99b3", 0))).is_err();
+    assert_eq!(contract.current_version().unwrap().version, 2);
+}
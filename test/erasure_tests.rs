@@ -0,0 +1,31 @@
+use lib_blockchain::models::keypair::{ChainFamily, KeyPair};
+use lib_blockchain::models::secret::Secret;
+use lib_blockchain::services::erasure::ErasedKeyPair;
+
+#[test]
+fn erased_keypairs_test() {
+    //sealed under the user's data key, gone with it
+    let mut sealed = KeyPair::new();
+    sealed.set_chain(&ChainFamily::Evm);
+    sealed.set_private_key(&Secret::from("usr1:a2V5:c2VhbGVk".to_string()));
+    let erased = ErasedKeyPair::new(&sealed);
+    assert!(erased.shredded);
+    assert!(!erased.recoverable);
+
+    //stored before per-user data keys, only deleted
+    let mut legacy = KeyPair::new();
+    legacy.set_chain(&ChainFamily::Evm);
+    legacy.set_private_key(&Secret::from("cHJpdmF0ZQ==".to_string()));
+    let erased = ErasedKeyPair::new(&legacy);
+    assert!(!erased.shredded);
+    assert!(!erased.recoverable);
+
+    //the master mnemonic still derives it
+    let mut derived = KeyPair::new();
+    derived.set_chain(&ChainFamily::Sui);
+    derived.set_derivation_index(&7);
+    let erased = ErasedKeyPair::new(&derived);
+    assert_eq!(erased.chain, ChainFamily::Sui);
+    assert!(!erased.shredded);
+    assert!(erased.recoverable);
+}
@@ -0,0 +1,77 @@
+use chrono::Utc;
+use lib_blockchain::blockchains::factory::check_contract_fits;
+use lib_blockchain::errors::blockchain::BlockchainBackendError;
+use lib_blockchain::models::blockchain::Blockchain;
+use lib_blockchain::models::contract::{Contract, ContractStatus};
+use lib_blockchain::models::keypair::ChainFamily;
+use lib_blockchain::models::secret::Secret;
+use spectral::{assert_that, result::ResultAssertions};
+use url::Url;
+
+const SUI_PACKAGE: &str = "0x5dd2881df4e9f44495a4d44dc6d24ec486c7f2c13b0701b68462b34f79530f57";
+const SUI_COIN: &str = "0x0000000000000000000000000000000000000000000000000000000000000005";
+const EVM_ADDRESS: &str = "0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1";
+
+fn blockchain(family: Option<ChainFamily>) -> Blockchain {
+    Blockchain::new(
+        "chain".to_string(),
+        Url::parse("http://localhost:8545").unwrap(),
+        "no-api-key".to_string(),
+        0,
+        Url::parse("http://localhost/explorer").unwrap(),
+        "no-api-key-explorer".to_string(),
+        family,
+    )
+}
+
+fn contract(address: &str, owner_cash: Option<String>) -> Contract {
+    Contract::new_c(
+        1,
+        Utc::now(),
+        "chain".to_string(),
+        Some(address.to_string()),
+        Some(EVM_ADDRESS.to_string()),
+        Some(Secret::from("cyphered".to_string())),
+        owner_cash,
+        None,
+        ContractStatus::Enabled,
+    )
+}
+
+#[test]
+fn factory_checks_contract_fits_chain_test() {
+    let evm = blockchain(Some(ChainFamily::Evm));
+    let sui = blockchain(Some(ChainFamily::Sui));
+
+    let evm_contract = contract(EVM_ADDRESS, None);
+    assert_eq!(
+        check_contract_fits(&evm_contract, &evm).unwrap(),
+        ChainFamily::Evm
+    );
+    let sui_contract = contract(SUI_PACKAGE, Some(SUI_COIN.to_string()));
+    assert_eq!(
+        check_contract_fits(&sui_contract, &sui).unwrap(),
+        ChainFamily::Sui
+    );
+
+    //sui pays gas from the owner's coin
+    let no_cash = contract(SUI_PACKAGE, None);
+    let res = check_contract_fits(&no_cash, &sui);
+    assert_that!(res).is_err();
+    assert!(res.err().unwrap().is::<BlockchainBackendError>());
+    let empty_cash = contract(SUI_PACKAGE, Some("".to_string()));
+    assert_that!(check_contract_fits(&empty_cash, &sui)).is_err();
+
+    //records pointing at the wrong kind of chain
+    assert_that!(check_contract_fits(&sui_contract, &evm)).is_err();
+    assert_that!(check_contract_fits(&evm_contract, &sui)).is_err();
+
+    //blockchains recorded before the family was
+    assert_that!(check_contract_fits(&evm_contract, &blockchain(None))).is_err();
+
+    let mut ownerless = Contract::new();
+    ownerless.set_address(&EVM_ADDRESS.to_string());
+    assert_that!(check_contract_fits(&ownerless, &evm)).is_err();
+    ownerless.set_owner_signer_key_id(&"kms-key".to_string());
+    assert_that!(check_contract_fits(&ownerless, &evm)).is_ok();
+}
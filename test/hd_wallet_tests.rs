@@ -0,0 +1,58 @@
+use crate::nfts_tests::MNEMONIC_TEST;
+use lib_blockchain::blockchains::hd_wallet::{derive_from_mnemonic, HdWallet};
+use lib_blockchain::models::keypair::ChainFamily;
+use lib_blockchain::models::secret::Secret;
+use spectral::{assert_that, result::ResultAssertions};
+
+#[test]
+fn hd_wallet_evm_derivation_test() {
+    let mnemonic = Secret::from(MNEMONIC_TEST.to_string());
+
+    //same accounts `ganache --deterministic` hands out
+    let first = derive_from_mnemonic(&mnemonic, &ChainFamily::Evm, 0).unwrap();
+    assert_eq!(
+        first.address(),
+        "0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1"
+    );
+    assert_eq!(
+        first.secret().expose(),
+        "4f3edf983ac636a65a842ce7c78d9aa706d3b113bce9c46f30d7d21715b23b1d"
+    );
+
+    let second = derive_from_mnemonic(&mnemonic, &ChainFamily::Evm, 1).unwrap();
+    assert_eq!(
+        second.address(),
+        "0xffcf8fdee72ac11b5c542428b35eef5769c409f0"
+    );
+
+    assert_eq!(
+        HdWallet::derivation_path(&ChainFamily::Evm, 7),
+        "m/44'/60'/0'/0/7"
+    );
+}
+
+#[test]
+fn hd_wallet_sui_derivation_test() {
+    let mnemonic = Secret::from(MNEMONIC_TEST.to_string());
+
+    let first = derive_from_mnemonic(&mnemonic, &ChainFamily::Sui, 0).unwrap();
+    let again = derive_from_mnemonic(&mnemonic, &ChainFamily::Sui, 0).unwrap();
+    let second = derive_from_mnemonic(&mnemonic, &ChainFamily::Sui, 1).unwrap();
+
+    assert_eq!(first.address(), again.address());
+    assert_eq!(first.secret().expose(), again.secret().expose());
+    assert_ne!(first.address(), second.address());
+    assert!(first.address().starts_with("0x"));
+
+    assert_eq!(
+        HdWallet::derivation_path(&ChainFamily::Sui, 7),
+        "m/44'/784'/7'/0'/0'"
+    );
+
+    let wrong = derive_from_mnemonic(
+        &Secret::from("not a mnemonic".to_string()),
+        &ChainFamily::Sui,
+        0,
+    );
+    assert_that!(&wrong).is_err();
+}
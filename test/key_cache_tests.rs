@@ -0,0 +1,70 @@
+use lib_blockchain::blockchains::key_cache::KeyCache;
+use lib_blockchain::models::secret::Secret;
+use lib_config::config::Config;
+use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
+use lib_config::infra::{build_local_stack_connection, create_key, cypher_with_secret_key};
+use spectral::{assert_that, result::ResultAssertions};
+use std::env;
+use std::time::Duration;
+use testcontainers::*;
+
+#[tokio::test]
+async fn key_cache_test() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env::set_var("RUST_LOG", "debug");
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+
+    let docker = clients::Cli::default();
+
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("kms");
+    let node = docker.run(local_stack);
+    let host_port = node.get_host_port_ipv4(4566);
+
+    let shared_config = build_local_stack_connection(host_port).await;
+
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    let key_id = create_key(&config).await?;
+    let first =
+        Secret::from(cypher_with_secret_key("first secret", key_id.as_str(), &config).await?);
+    let second =
+        Secret::from(cypher_with_secret_key("second secret", key_id.as_str(), &config).await?);
+
+    let cache = KeyCache::new(Duration::from_secs(2), 1);
+    let shared = cache.clone();
+    let plain = cache.uncypher(&first, &key_id, &config).await?;
+    assert_eq!(plain.expose(), "first secret");
+    assert_eq!(shared.len(), 1);
+
+    //kms down, cached keys still sign
+    let kms = aws_sdk_kms::Client::new(config.aws_config());
+    kms.disable_key().key_id(&key_id).send().await?;
+    let plain = shared.uncypher(&first, &key_id, &config).await?;
+    assert_eq!(plain.expose(), "first secret");
+    assert_that!(&cache.uncypher(&second, &key_id, &config).await).is_err();
+
+    //bounded, the oldest one goes first
+    kms.enable_key().key_id(&key_id).send().await?;
+    cache.uncypher(&second, &key_id, &config).await?;
+    assert_eq!(cache.len(), 1);
+    kms.disable_key().key_id(&key_id).send().await?;
+    assert_that!(&cache.uncypher(&first, &key_id, &config).await).is_err();
+    assert_that!(&cache.uncypher(&second, &key_id, &config).await).is_ok();
+
+    //rotated keys are dropped on request
+    cache.invalidate(&key_id);
+    assert!(cache.is_empty());
+    assert_that!(&cache.uncypher(&second, &key_id, &config).await).is_err();
+
+    //and expire anyway
+    kms.enable_key().key_id(&key_id).send().await?;
+    cache.uncypher(&second, &key_id, &config).await?;
+    kms.disable_key().key_id(&key_id).send().await?;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert_that!(&cache.uncypher(&second, &key_id, &config).await).is_err();
+    assert!(cache.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::env;
+
+use aws_sdk_dynamodb::types::{
+    AttributeDefinition, AttributeValue, BillingMode, GlobalSecondaryIndex, KeySchemaElement,
+    KeyType, Projection, ProjectionType, ScalarAttributeType,
+};
+use lib_blockchain::errors::keypair::{KeyPairAlreadyExistsError, KeyPairNoExistsError};
+use lib_blockchain::models::keypair::{ChainFamily, KeyPair};
+use lib_blockchain::repositories::keypairs::{
+    mapping_from_doc_to_keypair, KeyPairRepo, KeyPairRepository, CREATIONTIME_FIELD_NAME,
+};
+use lib_blockchain::repositories::schema_keypairs::{
+    KeyPairSchema, KEYPAIRS_ADDRESS_FIELD, KEYPAIRS_ADDRESS_INDEX_NAME, KEYPAIRS_CHAIN_FIELD_SK,
+    KEYPAIRS_LEGACY_TABLE_NAME, KEYPAIRS_PRIVATE_FIELD, KEYPAIRS_PUBLIC_FIELD,
+    KEYPAIRS_USER_ID_FIELD_PK,
+};
+use lib_config::config::Config;
+use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
+use lib_config::infra::build_local_stack_connection;
+use lib_config::schema::Schema;
+use spectral::{assert_that, result::ResultAssertions};
+use testcontainers::*;
+
+#[test]
+fn keypair_rows_without_chain_test() {
+    //a row as stored before keypairs were scoped per chain family
+    let mut doc = HashMap::from([
+        (
+            KEYPAIRS_USER_ID_FIELD_PK.to_string(),
+            AttributeValue::S("user-1".to_string()),
+        ),
+        (
+            KEYPAIRS_ADDRESS_FIELD.to_string(),
+            AttributeValue::S("0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1".to_string()),
+        ),
+        (
+            KEYPAIRS_PUBLIC_FIELD.to_string(),
+            AttributeValue::S("cHVibGlj".to_string()),
+        ),
+        (
+            KEYPAIRS_PRIVATE_FIELD.to_string(),
+            AttributeValue::S("cHJpdmF0ZQ==".to_string()),
+        ),
+        (
+            CREATIONTIME_FIELD_NAME.to_string(),
+            AttributeValue::S("2023-05-04T10:11:12+00:00".to_string()),
+        ),
+    ]);
+    let mut keypair = KeyPair::new();
+    mapping_from_doc_to_keypair(&doc, &mut keypair);
+    assert_eq!(*keypair.chain(), ChainFamily::Evm);
+    assert_eq!(keypair.user_id(), "user-1");
+    assert_eq!(
+        keypair.address(),
+        "0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1"
+    );
+
+    doc.insert(
+        KEYPAIRS_CHAIN_FIELD_SK.to_string(),
+        AttributeValue::S(ChainFamily::Sui.to_string()),
+    );
+    let mut keypair = KeyPair::new();
+    mapping_from_doc_to_keypair(&doc, &mut keypair);
+    assert_eq!(*keypair.chain(), ChainFamily::Sui);
+}
+
+#[tokio::test]
+async fn keypairs_legacy_table_migration_test(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+
+    let docker = clients::Cli::default();
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("dynamodb");
+    let node = docker.run(local_stack);
+    let host_port = node.get_host_port_ipv4(4566);
+
+    let shared_config = build_local_stack_connection(host_port).await;
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    let creation = KeyPairSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    //the table as deployed before keypairs were scoped per chain family
+    let client = aws_sdk_dynamodb::Client::new(config.aws_config());
+    client
+        .create_table()
+        .table_name(KEYPAIRS_LEGACY_TABLE_NAME)
+        .key_schema(
+            KeySchemaElement::builder()
+                .attribute_name(KEYPAIRS_USER_ID_FIELD_PK)
+                .key_type(KeyType::Hash)
+                .build(),
+        )
+        .global_secondary_indexes(
+            GlobalSecondaryIndex::builder()
+                .index_name(KEYPAIRS_ADDRESS_INDEX_NAME)
+                .key_schema(
+                    KeySchemaElement::builder()
+                        .attribute_name(KEYPAIRS_ADDRESS_FIELD)
+                        .key_type(KeyType::Hash)
+                        .build(),
+                )
+                .projection(
+                    Projection::builder()
+                        .projection_type(ProjectionType::KeysOnly)
+                        .build(),
+                )
+                .build(),
+        )
+        .attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name(KEYPAIRS_USER_ID_FIELD_PK)
+                .attribute_type(ScalarAttributeType::S)
+                .build(),
+        )
+        .attribute_definitions(
+            AttributeDefinition::builder()
+                .attribute_name(KEYPAIRS_ADDRESS_FIELD)
+                .attribute_type(ScalarAttributeType::S)
+                .build(),
+        )
+        .billing_mode(BillingMode::PayPerRequest)
+        .send()
+        .await?;
+    let address = "0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1".to_string();
+    client
+        .put_item()
+        .table_name(KEYPAIRS_LEGACY_TABLE_NAME)
+        .item(
+            KEYPAIRS_USER_ID_FIELD_PK,
+            AttributeValue::S("user-1".to_string()),
+        )
+        .item(KEYPAIRS_ADDRESS_FIELD, AttributeValue::S(address.clone()))
+        .item(
+            KEYPAIRS_PUBLIC_FIELD,
+            AttributeValue::S("cHVibGlj".to_string()),
+        )
+        .item(
+            KEYPAIRS_PRIVATE_FIELD,
+            AttributeValue::S("cHJpdmF0ZQ==".to_string()),
+        )
+        .send()
+        .await?;
+
+    //legacy keys are read as evm keys until migrated
+    let repo = KeyPairRepo::new(&config);
+    let legacy = repo
+        .get_by_id(&"user-1".to_string(), &ChainFamily::Evm)
+        .await?;
+    assert_eq!(legacy.unwrap().address(), &address);
+    let sui = repo
+        .get_by_id(&"user-1".to_string(), &ChainFamily::Sui)
+        .await?;
+    assert!(sui.is_none());
+    let by_address = repo.get_by_address(&address).await?;
+    assert_eq!(by_address.unwrap().user_id(), "user-1");
+
+    let migrated = repo.migrate_legacy(&10, &None).await?;
+    assert_eq!(migrated.items().len(), 1);
+    assert!(!migrated.has_next());
+    assert_eq!(*migrated.items()[0].chain(), ChainFamily::Evm);
+    let left = client
+        .scan()
+        .table_name(KEYPAIRS_LEGACY_TABLE_NAME)
+        .send()
+        .await?;
+    assert_eq!(left.count(), 0);
+    let again = repo.migrate_legacy(&10, &None).await?;
+    assert!(again.items().is_empty());
+
+    let keypair = repo
+        .get_by_id(&"user-1".to_string(), &ChainFamily::Evm)
+        .await?
+        .unwrap();
+    assert_eq!(keypair.address(), &address);
+    assert_eq!(repo.list(&10, &None).await?.items().len(), 1);
+
+    //a second first mint doesn't overwrite the stored key
+    let mut other = keypair.clone();
+    other.set_address(&"0x0000000000000000000000000000000000000001".to_string());
+    let added = repo.add(&other).await;
+    assert_that!(&added).is_err();
+    assert!(added.err().unwrap().is::<KeyPairAlreadyExistsError>());
+    let kept = repo.get_by_address(&address).await?;
+    assert_eq!(kept.unwrap().user_id(), "user-1");
+
+    repo.delete(&"user-1".to_string(), &ChainFamily::Evm)
+        .await?;
+    let deleted = repo.delete(&"user-1".to_string(), &ChainFamily::Evm).await;
+    assert!(deleted.err().unwrap().is::<KeyPairNoExistsError>());
+
+    Ok(())
+}
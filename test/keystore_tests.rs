@@ -0,0 +1,82 @@
+use crate::nfts_tests::MNEMONIC_TEST;
+use lib_blockchain::blockchains::hd_wallet::derive_from_mnemonic;
+use lib_blockchain::blockchains::keystore::{decrypt_keystore, encrypt_keystore, key_details};
+use lib_blockchain::errors::keystore::KeystoreError;
+use lib_blockchain::models::keypair::ChainFamily;
+use lib_blockchain::models::secret::Secret;
+use serde_json::Value;
+use spectral::{assert_that, result::ResultAssertions};
+
+#[test]
+fn keystore_evm_round_trip_test() {
+    let mnemonic = Secret::from(MNEMONIC_TEST.to_string());
+    let key = derive_from_mnemonic(&mnemonic, &ChainFamily::Evm, 0).unwrap();
+    let passphrase = Secret::from("correct horse battery".to_string());
+
+    let (address, _) = key_details(&ChainFamily::Evm, key.secret()).unwrap();
+    assert_eq!(address, "0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1");
+
+    let document = encrypt_keystore(&ChainFamily::Evm, key.secret(), &passphrase).unwrap();
+    let json: Value = serde_json::from_str(&document).unwrap();
+    assert_eq!(json["version"], 3);
+    assert_eq!(json["address"], "90f8bf6a479f320ead074411a4b0e7944ea8c9c1");
+    assert_eq!(json["crypto"]["cipher"], "aes-128-ctr");
+    assert_eq!(json["crypto"]["kdf"], "scrypt");
+    assert!(!document.contains(key.secret().expose()));
+
+    let back = decrypt_keystore(&ChainFamily::Evm, &document, &passphrase).unwrap();
+    assert_eq!(back.expose(), key.secret().expose());
+
+    let wrong = Secret::from("wrong horse battery".to_string());
+    let res = decrypt_keystore(&ChainFamily::Evm, &document, &wrong);
+    assert_that!(res).is_err();
+    assert!(res.err().unwrap().is::<KeystoreError>());
+
+    //a document claiming somebody else's address is refused
+    let tampered = document.replace(
+        "90f8bf6a479f320ead074411a4b0e7944ea8c9c1",
+        "ffcf8fdee72ac11b5c542428b35eef5769c409f0",
+    );
+    let res = decrypt_keystore(&ChainFamily::Evm, &tampered, &passphrase);
+    assert_that!(res).is_err();
+}
+
+#[test]
+fn keystore_sui_round_trip_test() {
+    let mnemonic = Secret::from(MNEMONIC_TEST.to_string());
+    let key = derive_from_mnemonic(&mnemonic, &ChainFamily::Sui, 0).unwrap();
+    let passphrase = Secret::from("correct horse battery".to_string());
+
+    let (address, public_key) = key_details(&ChainFamily::Sui, key.secret()).unwrap();
+    assert_eq!(&address, key.address());
+    assert_eq!(&public_key, key.public_key());
+
+    let document = encrypt_keystore(&ChainFamily::Sui, key.secret(), &passphrase).unwrap();
+    let back = decrypt_keystore(&ChainFamily::Sui, &document, &passphrase).unwrap();
+    assert_eq!(back.expose(), key.secret().expose());
+
+    //a sui document isn't an evm key, nor the other way round
+    let res = decrypt_keystore(&ChainFamily::Evm, &document, &passphrase);
+    assert_that!(res).is_err();
+
+    let res = decrypt_keystore(&ChainFamily::Sui, &"{}".to_string(), &passphrase);
+    assert_that!(res).is_err();
+}
+
+#[test]
+fn keystore_scrypt_limits_test() {
+    let mnemonic = Secret::from(MNEMONIC_TEST.to_string());
+    let key = derive_from_mnemonic(&mnemonic, &ChainFamily::Evm, 0).unwrap();
+    let passphrase = Secret::from("correct horse battery".to_string());
+    let document = encrypt_keystore(&ChainFamily::Evm, key.secret(), &passphrase).unwrap();
+
+    //costs a hostile document could ask for are refused before deriving
+    for (param, value) in [("n", 1u64 << 21), ("r", 9), ("p", 5)] {
+        let mut json: Value = serde_json::from_str(&document).unwrap();
+        json["crypto"]["kdfparams"][param] = Value::from(value);
+        let res = decrypt_keystore(&ChainFamily::Evm, &json.to_string(), &passphrase);
+        let err = res.err().unwrap();
+        assert!(err.is::<KeystoreError>());
+        assert!(err.to_string().contains("scrypt parameters above"));
+    }
+}
@@ -3,3 +3,35 @@ mod nfts_ganache_tests;
 mod nfts_tests;
 
 mod nfts_sui_tests;
+
+mod amount_tests;
+
+mod prices_tests;
+
+mod hd_wallet_tests;
+
+mod signer_tests;
+
+mod secret_tests;
+
+mod key_cache_tests;
+
+mod keystore_tests;
+
+mod shamir_tests;
+
+mod factory_tests;
+
+mod router_tests;
+
+mod mirror_tests;
+
+mod contract_versions_tests;
+
+mod block_tx_tests;
+
+mod keypairs_tests;
+
+mod nfts_routing_tests;
+
+mod erasure_tests;
@@ -0,0 +1,113 @@
+use lib_blockchain::blockchains::mirror::MirrorSet;
+use lib_blockchain::models::amount::{Amount, Unit};
+use lib_blockchain::models::block_tx::{BlockchainTx, ChainAnchor, MintingStatus};
+use lib_blockchain::models::keypair::ChainFamily;
+use spectral::{assert_that, result::ResultAssertions};
+
+use crate::router_tests::backends;
+
+fn minted_on(contract_id: u16, hash: &str) -> BlockchainTx {
+    let mut tx = BlockchainTx::default();
+    tx.set_contract_id(&contract_id);
+    tx.set_tx(&hash.to_string());
+    tx.set_block_number(&7);
+    tx.set_cost(&Amount::new(1500, Unit::Mist));
+    tx
+}
+
+#[test]
+fn mirrored_anchors_status_test() {
+    let mut tx = BlockchainTx::default();
+    tx.set_minted_status(MintingStatus::Started);
+    tx.set_contract_id(&1);
+    //single chain registrations keep their own status
+    assert_eq!(tx.anchors_status(), MintingStatus::Started);
+
+    let evm = ChainAnchor::new(&1, &ChainFamily::Evm, true);
+    let sui = ChainAnchor::new(&2, &ChainFamily::Sui, true);
+    let extra = ChainAnchor::new(&3, &ChainFamily::Evm, false);
+    tx.set_anchor(&evm);
+    tx.set_anchor(&sui);
+    tx.set_anchor(&extra);
+    assert_eq!(tx.anchors().len(), 3);
+    assert_eq!(tx.anchors_status(), MintingStatus::Started);
+
+    tx.set_anchor(&evm.clone().completed(&minted_on(1, "0xabc")));
+    tx.set_anchor(&extra.clone().failed(&"out of gas".to_string()));
+    assert_eq!(tx.anchors().len(), 3);
+    assert_eq!(tx.anchors_status(), MintingStatus::Started);
+
+    //a required chain failing fails the whole
+    tx.set_anchor(&sui.clone().failed(&"node unreachable".to_string()));
+    assert_eq!(tx.anchors_status(), MintingStatus::Error);
+
+    //optional chains don't hold it back
+    tx.set_anchor(&sui.clone().completed(&minted_on(2, "0x5ee")));
+    assert_eq!(tx.anchors_status(), MintingStatus::CompletedSuccessfully);
+
+    //what the sui backend needs to find its token
+    let anchored = tx.anchored_tx(tx.anchor(&2).unwrap());
+    assert_eq!(*anchored.contract_id(), Some(2));
+    assert_eq!(*anchored.tx(), Some("0x5ee".to_string()));
+    assert_eq!(*anchored.cost(), Some(Amount::new(1500, Unit::Mist)));
+    assert_eq!(anchored.mint_status(), MintingStatus::CompletedSuccessfully);
+    assert!(anchored.anchors().is_empty());
+    assert_eq!(anchored.asset_id(), tx.asset_id());
+
+    let failed = tx.anchor(&3).unwrap();
+    assert_eq!(*failed.tx_error(), Some("out of gas".to_string()));
+    assert!(!failed.required());
+}
+
+#[test]
+fn mirrored_rows_per_contract_test() {
+    //single chain registrations are their own
+    let single = minted_on(1, "0xabc");
+    assert_eq!(single.per_contract(), vec![single.clone()]);
+
+    //the row of a mirrored one holds the details of its first contract's tx
+    let mut tx = minted_on(1, "0xabc");
+    tx.set_gas_used(&21000);
+    tx.set_from(&"0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1".to_string());
+    let evm = ChainAnchor::new(&1, &ChainFamily::Evm, true).completed(&tx);
+    let sui = ChainAnchor::new(&2, &ChainFamily::Sui, true).completed(&minted_on(2, "0x5ee"));
+    tx.set_anchor(&evm);
+    tx.set_anchor(&sui);
+
+    let per_contract = tx.per_contract();
+    assert_eq!(per_contract.len(), 2);
+    let first = &per_contract[0];
+    assert_eq!(*first.contract_id(), Some(1));
+    assert_eq!(*first.tx(), Some("0xabc".to_string()));
+    assert_eq!(*first.gas_used(), Some(21000));
+    assert!(first.from().is_some());
+    let mirrored = &per_contract[1];
+    assert_eq!(*mirrored.contract_id(), Some(2));
+    assert_eq!(*mirrored.tx(), Some("0x5ee".to_string()));
+    assert_eq!(*mirrored.cost(), Some(Amount::new(1500, Unit::Mist)));
+    assert_eq!(mirrored.mint_status(), MintingStatus::CompletedSuccessfully);
+    assert!(mirrored.gas_used().is_none());
+    assert!(mirrored.from().is_none());
+    assert!(mirrored.fiat_cost().is_none());
+}
+
+#[test]
+fn mirror_set_test() {
+    let mirrors = MirrorSet::new(backends(), vec![1, 2]).unwrap();
+    assert_eq!(mirrors.contract_ids(), vec![1, 2]);
+    assert!(mirrors.is_required(&2));
+    assert_eq!(
+        mirrors.backend(&2).unwrap().chain_family(),
+        ChainFamily::Sui
+    );
+    assert!(mirrors.backend(&3).is_none());
+
+    let optional_sui = MirrorSet::new(backends(), vec![1]).unwrap();
+    assert!(!optional_sui.is_required(&2));
+
+    let mut twice = backends();
+    twice.append(&mut backends());
+    assert_that!(MirrorSet::new(twice, vec![1])).is_err();
+    assert_that!(MirrorSet::new(backends(), Vec::new())).is_err();
+    assert_that!(MirrorSet::new(backends(), vec![1, 3])).is_err();
+}
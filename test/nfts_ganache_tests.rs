@@ -2,25 +2,40 @@ use crate::nfts_tests::MNEMONIC_TEST;
 use chrono::Utc;
 use ethers::utils::Ganache;
 use lib_blockchain::blockchains::chain::CloneBoxNFTsRepository;
-use lib_blockchain::blockchains::ganache::GanacheBlockChain;
-use lib_blockchain::models::block_tx::MintingStatus;
+use lib_blockchain::blockchains::factory::nfts_backend;
+use lib_blockchain::blockchains::ganache::EvmArtifact;
+use lib_blockchain::blockchains::hd_wallet::{derive_from_mnemonic, HdWallet};
+use lib_blockchain::blockchains::signer::LocalKeySigner;
+use lib_blockchain::errors::contract::ContractNotWritableError;
+use lib_blockchain::models::block_tx::{BlockchainTxBuilder, MintingStatus};
 use lib_blockchain::models::blockchain::Blockchain;
-use lib_blockchain::models::contract::{Contract, ContractStatus};
+use lib_blockchain::models::contract::ContractStatus;
+use lib_blockchain::models::secret::Secret;
 use lib_blockchain::repositories::block_tx::BlockchainTxRepo;
 use lib_blockchain::repositories::blockchain::{BlockchainRepo, BlockchainRepository};
 use lib_blockchain::repositories::contract::{ContractRepo, ContractRepository};
-use lib_blockchain::repositories::keypairs::KeyPairRepo;
+use lib_blockchain::models::keypair::ChainFamily;
+use lib_blockchain::repositories::keypairs::{KeyPairRepo, KeyPairRepository};
 use lib_blockchain::repositories::schema_block_tx::BlockTxSchema;
 use lib_blockchain::repositories::schema_contract::ContractSchema;
 use lib_blockchain::repositories::schema_keypairs::KeyPairSchema;
+use lib_blockchain::repositories::schema_user_data_keys::UserDataKeySchema;
 use lib_blockchain::services::block_tx::{BlockchainTxManipulation, BlockchainTxService};
-use lib_blockchain::services::contract::deploy_evm_contract_locally;
+use lib_blockchain::services::cost_report::{CostReportService, CostReporting};
+use lib_blockchain::services::deployment::{
+    ContractOwner, DeploymentManipulation, DeploymentService,
+};
+use lib_blockchain::services::erasure::{ErasureManipulation, ErasureService};
+use lib_blockchain::services::key_export::{KeyExportManipulation, KeyExportService};
+use lib_blockchain::services::key_rotation::{KeyRotationManipulation, KeyRotationService};
 use lib_blockchain::services::nfts::{NFTsManipulation, NFTsService, NTFState};
+use lib_blockchain::services::owner_backup::{OwnerBackupManipulation, OwnerBackupService};
+use lib_blockchain::services::reconciliation::{ReconciliationManipulation, ReconciliationService};
+use lib_blockchain::services::upgrade::{UpgradeManipulation, UpgradeService};
 use lib_config::config::Config;
 use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
 use lib_config::infra::{
     build_local_stack_connection, create_key, create_secret_manager_with_values,
-    cypher_with_secret_key,
 };
 
 use lib_blockchain::repositories::schema_blockchain::BlockchainSchema;
@@ -30,6 +45,20 @@ use spectral::{assert_that, result::ResultAssertions};
 use std::env;
 use testcontainers::*;
 use url::Url;
+use uuid::Uuid;
+
+// an asset registered and waiting to be minted
+async fn registered(
+    tx_service: &BlockchainTxService,
+) -> Result<Uuid, Box<dyn std::error::Error + Send + Sync>> {
+    let asset_id = Uuid::new_v4();
+    let btx = BlockchainTxBuilder::new()
+        .asset_id(asset_id)
+        .mint_status(MintingStatus::NeverMinted)
+        .build();
+    tx_service.add(&btx).await?;
+    Ok(asset_id)
+}
 
 #[tokio::test]
 async fn create_contract_and_mint_nft_test_sync_ganache(
@@ -84,6 +113,9 @@ async fn create_contract_and_mint_nft_test_sync_ganache(
     let creation = KeyPairSchema::create_schema(&config).await;
     assert_that(&creation).is_ok();
 
+    let creation = UserDataKeySchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
     let creation = BlockTxSchema::create_schema(&config).await;
     assert_that(&creation).is_ok();
 
@@ -112,21 +144,13 @@ async fn create_contract_and_mint_nft_test_sync_ganache(
     let contract_owner_secret: &str =
         "4f3edf983ac636a65a842ce7c78d9aa706d3b113bce9c46f30d7d21715b23b1d"; // example fake secret key
     let key_id = config.env_vars().kms_key_id().unwrap();
-    let contract_owner_secret_cyphered =
-        cypher_with_secret_key(contract_owner_secret, key_id.as_str(), &config).await?;
     let contract_owner_address = "0x90F8bf6A479f320ead074411a4B0e7944Ea8c9C1".to_string(); //address based on the previous fake secret key
 
     //create blockchain ganache object and contract
     let block_chains_repo = BlockchainRepo::new(&config.clone());
     let contracts_repo = ContractRepo::new(&config.clone());
 
-    //create contract and deploy to blockchain
     let url = ganache.endpoint();
-
-    let contract_address =
-        deploy_evm_contract_locally(url.as_str(), contract_owner_address.clone()).await?;
-    //let contract_address = deploy_contract_ethers(url.as_str(), &contract_owner_wallet).await?;
-
     let confirmations = 0;
     let blochain_id = "ganache".to_string();
 
@@ -137,41 +161,91 @@ async fn create_contract_and_mint_nft_test_sync_ganache(
         confirmations,
         Url::parse("http://localhost/explorer").unwrap().clone(),
         "no-api-key-explorer".to_string(),
+        Some(ChainFamily::Evm),
     );
-    block_chains_repo.add(&ganache_entity).await?;
 
     let contact_id = 1;
 
-    let contract_entity = Contract::new_c(
-        contact_id,
-        Utc::now(),
-        blochain_id.to_owned(),
-        Some(contract_address),
-        Some(contract_owner_address),
-        Some(contract_owner_secret_cyphered),
-        Some("".to_string()),
-        Some("no-details".to_string()),
-        ContractStatus::Enabled,
+    //deploy signed by the owner key and register, in one go
+    let deployment =
+        DeploymentService::new(contracts_repo.clone(), block_chains_repo.clone(), &config);
+    let owner = ContractOwner::Secret {
+        secret: Secret::from(contract_owner_secret.to_string()),
+        key_id: key_id.clone(),
+    };
+    let deployed = deployment
+        .setup_evm_network(
+            &ganache_entity,
+            &contact_id,
+            &owner,
+            &Some("no-details".to_string()),
+        )
+        .await?;
+    assert!(deployed.block_number.is_some());
+    assert!(deployed.cost.is_some());
+    let contract_entity = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(*contract_entity.status(), ContractStatus::Enabled);
+    assert_eq!(contract_entity.address(), deployed.contract.address());
+    let owner_address = contract_entity.owner_address().clone().unwrap();
+    assert_eq!(
+        owner_address.to_lowercase(),
+        contract_owner_address.to_lowercase()
     );
-    contracts_repo.add(&contract_entity).await?;
+    assert_eq!(
+        contract_entity.owner_secret_key_id().clone(),
+        Some(key_id.clone())
+    );
+    let stored_secret = contract_entity.owner_secret().clone().unwrap();
+    assert_ne!(stored_secret.expose(), contract_owner_secret);
+    let stored_secret = stored_secret.uncypher(&key_id, &config).await?;
+    assert_eq!(stored_secret.expose(), contract_owner_secret);
+
+    //plain LightNFT contracts have a single version, new code means a new contract
+    let first_version = contract_entity.current_version().unwrap();
+    assert_eq!(first_version.version, 1);
+    assert_eq!(
+        Some(first_version.code_address.clone()),
+        *contract_entity.address()
+    );
+    let upgrades = UpgradeService::new(contracts_repo.clone(), block_chains_repo.clone(), &config);
+    let upgrade = upgrades
+        .upgrade_evm_proxy(&contact_id, &EvmArtifact::light_nft())
+        .await;
+    assert_that!(&upgrade).is_err();
+
+    //ids are never reused
+    let again = deployment
+        .deploy_evm_contract(&contact_id, &blochain_id, &owner, &None)
+        .await;
+    assert_that!(&again).is_err();
+
+    let enabled = contracts_repo
+        .get_by_blockchain(&blochain_id, &ContractStatus::Enabled)
+        .await?;
+    assert_eq!(enabled.len(), 1);
+    let blockchains = block_chains_repo.list(&10, &None).await?;
+    assert_eq!(blockchains.items().len(), 1);
+    assert!(!blockchains.has_next());
 
     new_configuration.set_contract_id(contact_id);
     config.set_env_vars(&new_configuration);
 
-    let blockchain = GanacheBlockChain::new(&config.clone(), &contracts_repo, &block_chains_repo)
+    //the blockchain record says which backend serves the contract
+    let blockchain = nfts_backend(&contact_id, &config, &contracts_repo, &block_chains_repo)
         .await
         .unwrap();
+    assert_eq!(blockchain.chain_family(), ChainFamily::Evm);
 
     let nft_service = NFTsService::new(
         blockchain.clone_box(),
-        repo_keys,
+        repo_keys.clone(),
         tx_service.clone(),
         config.to_owned(),
     );
 
     let asset_price: u64 = 2000;
 
-    let asset_id = uuid::Uuid::new_v4();
+    let asset_id = registered(&tx_service).await?;
     let mint_op = nft_service
         .try_mint(
             &asset_id,
@@ -207,5 +281,387 @@ async fn create_contract_and_mint_nft_test_sync_ganache(
     let txs_op = tx_service.get_by_asset_id(&asset_id).await;
     assert_that!(&txs_op).is_ok();
 
+    let evm_key = repo_keys.get_by_id(&"user1".to_string(), &ChainFamily::Evm).await?;
+    assert!(evm_key.is_some());
+    let sui_key = repo_keys.get_by_id(&"user1".to_string(), &ChainFamily::Sui).await?;
+    assert!(sui_key.is_none());
+
+    let evm_key = evm_key.unwrap();
+
+    //the exported wallet holds what was minted to the user
+    let export = KeyExportService::new(repo_keys.clone(), &config)
+        .export_keypair(
+            &"user1".to_string(),
+            &ChainFamily::Evm,
+            &Secret::from("a passphrase long enough".to_string()),
+        )
+        .await?;
+    assert_eq!(
+        export.address.to_lowercase(),
+        evm_key.address().to_lowercase()
+    );
+
+    //events bring addresses back, in any case
+    let by_address = repo_keys
+        .get_by_address(&evm_key.address().to_uppercase().replace("0X", "0x"))
+        .await?;
+    assert_eq!(by_address.unwrap().user_id(), "user1");
+    let nobody = repo_keys
+        .get_by_address(&"0x0000000000000000000000000000000000000001".to_string())
+        .await?;
+    assert!(nobody.is_none());
+
+    let mut moved = evm_key.clone();
+    moved.set_user_id(&"user2".to_string());
+    assert_that!(&repo_keys.update(&moved).await).is_err(); //update never creates
+    repo_keys.add(&moved).await?;
+    moved.set_last_update_time(&Utc::now());
+    assert_that!(&repo_keys.update(&moved).await).is_ok();
+    assert_eq!(repo_keys.list(&10, &None).await?.items().len(), 2);
+    repo_keys.delete(&"user2".to_string(), &ChainFamily::Evm).await?;
+    assert_that!(&repo_keys.delete(&"user2".to_string(), &ChainFamily::Evm).await).is_err();
+    assert!(repo_keys
+        .get_by_id(&"user2".to_string(), &ChainFamily::Evm)
+        .await?
+        .is_none());
+
+    let by_user_op = tx_service
+        .get_by_user(&"user1".to_string(), &None, &None, &10, &None)
+        .await;
+    assert_that!(&by_user_op).is_ok();
+    assert_eq!(by_user_op.unwrap().items().len(), 1);
+
+    let by_status_op = tx_service
+        .get_by_status(
+            &MintingStatus::CompletedSuccessfully,
+            &Some(Utc::now() - chrono::Duration::hours(1)),
+            &None,
+            &10,
+            &None,
+        )
+        .await;
+    assert_that!(&by_status_op).is_ok();
+    assert_eq!(by_status_op.unwrap().items().len(), 1);
+
+    let reconciliation = ReconciliationService::new(blockchain.clone_box(), tx_service.clone());
+    let report_op = reconciliation.reconcile().await;
+    assert_that!(&report_op).is_ok();
+    let report = report_op.unwrap();
+    assert_eq!(report.checked, 1);
+    assert!(report.is_clean());
+
+    let costs = CostReportService::new(tx_service.clone(), contracts_repo.clone());
+    let costs_op = costs
+        .cost_report(
+            &(Utc::now() - chrono::Duration::hours(1)),
+            &Utc::now(),
+            &None,
+        )
+        .await;
+    assert_that!(&costs_op).is_ok();
+    let costs = costs_op.unwrap();
+    assert_eq!(costs.totals.len(), 1);
+    assert_eq!(costs.totals[0].transactions, 1);
+    assert_eq!(costs.totals[0].total, costs.totals[0].p50);
+    assert_eq!(costs.to_csv().lines().count(), 3); //header + day + window
+
+    let rotated_key_id = create_key(&config).await?;
+    let rotation =
+        KeyRotationService::new(repo_keys.clone(), contracts_repo.clone(), config.clone());
+    let rotation_op = rotation.rotate(&rotated_key_id, &None, &|_| {}).await;
+    assert_that!(&rotation_op).is_ok();
+    let progress = rotation_op.unwrap();
+    assert!(progress.is_done());
+    assert!(progress.failed.is_empty());
+    assert_eq!(progress.rotated, 2); //user1 keypair + contract owner secret
+
+    let again = rotation.rotate(&rotated_key_id, &None, &|_| {}).await.unwrap();
+    assert_eq!(again.rotated, 0);
+    assert_eq!(again.skipped, 2);
+
+    //the master mnemonic goes last, derived keys stay the same
+    let wallet =
+        HdWallet::from_mnemonic(&Secret::from(MNEMONIC_TEST.to_string()), &key_id, &config)
+            .await?;
+    let derived = wallet.derive(&ChainFamily::Evm, 3).await?;
+    let with_wallet = rotation
+        .clone()
+        .with_hd_wallet(&wallet)
+        .rotate(&rotated_key_id, &None, &|_| {})
+        .await?;
+    assert!(with_wallet.is_done());
+    assert_eq!(with_wallet.rotated, 1);
+    let mnemonic_cyphered = Secret::from(with_wallet.mnemonic_cyphered.unwrap());
+    let rotated_wallet = HdWallet::new(&mnemonic_cyphered, &rotated_key_id, &config);
+    let rederived = rotated_wallet.derive(&ChainFamily::Evm, 3).await?;
+    assert_eq!(rederived.address(), derived.address());
+
+    let contract = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(contract.owner_secret_key_id().clone(), Some(rotated_key_id));
+
+    //owner secret split among three custodians, any two bring it back
+    let backup = OwnerBackupService::new(contracts_repo.clone(), &config);
+    let owner_backup = backup.backup_owner_secret(&contact_id, 2, 3).await?;
+    assert_eq!(owner_backup.shares.len(), 3);
+    let two_shares = owner_backup.shares[1..].to_vec();
+    let recovered = backup
+        .recover_owner_secret(&contact_id, &ChainFamily::Evm, &two_shares, &key_id)
+        .await?;
+    assert_eq!(recovered.owner_secret_key_id().clone(), Some(key_id.clone()));
+    let contract = contracts_repo.get_by_id(&contact_id).await?;
+    let owner_secret = contract.owner_secret().clone().unwrap();
+    let owner_secret = owner_secret.uncypher(&key_id, &config).await?;
+    assert_eq!(owner_secret.expose(), contract_owner_secret);
+    let wrong_chain = backup
+        .recover_owner_secret(&contact_id, &ChainFamily::Sui, &two_shares, &key_id)
+        .await;
+    assert_that!(&wrong_chain).is_err();
+
+    //user1 asks to be forgotten
+    let receipt_key = derive_from_mnemonic(
+        &Secret::from(MNEMONIC_TEST.to_string()),
+        &ChainFamily::Evm,
+        1,
+    )?;
+    let receipt_signer = LocalKeySigner::new(&ChainFamily::Evm, receipt_key.secret())?;
+    let erasure = ErasureService::new(
+        repo_keys.clone(),
+        tx_service.clone(),
+        Box::new(receipt_signer),
+        &config,
+    );
+    let receipt = erasure.erase_user(&"user1".to_string()).await?;
+    assert!(receipt.data_key_shredded);
+    assert_eq!(receipt.keypairs.len(), 1);
+    assert!(receipt.keypairs[0].shredded);
+    assert!(!receipt.keypairs[0].recoverable);
+    assert_eq!(receipt.txs_scrubbed, 1);
+    assert_eq!(receipt.retained_tx_hashes, vec![content2.clone()]);
+    assert!(!receipt.to_string().contains("user1"));
+    assert!(receipt.verify()?);
+    let mut forged = receipt.clone();
+    forged.txs_scrubbed = 0;
+    assert!(!forged.verify()?);
+
+    let evm_key = repo_keys.get_by_id(&"user1".to_string(), &ChainFamily::Evm).await?;
+    assert!(evm_key.is_none());
+    let by_user = tx_service
+        .get_by_user(&"user1".to_string(), &None, &None, &10, &None)
+        .await?;
+    assert!(by_user.items().is_empty());
+    let kept = tx_service.get_by_asset_id(&asset_id).await?;
+    assert!(kept.user_id().is_none());
+    assert_eq!(kept.tx().clone(), Some(content2));
+
+    //nothing left to erase, still a receipt
+    let again = erasure.erase_user(&"user1".to_string()).await?;
+    assert!(!again.data_key_shredded);
+    assert_eq!(again.txs_scrubbed, 0);
+
+    //deprecated: what was minted is still readable, nothing new is minted
+    let deprecated = contracts_repo
+        .change_status(&contact_id, &ContractStatus::ReadOnly)
+        .await?;
+    assert_eq!(deprecated.status_history().len(), 1);
+    assert!(deprecated.status_time().is_some());
+    let unminted = registered(&tx_service).await?;
+    let refused = nft_service
+        .try_mint(
+            &unminted,
+            &"user3".to_string(),
+            &Some(asset_price),
+            &"hash3".to_string(),
+            &"md5".to_string(),
+            &0,
+        )
+        .await;
+    assert_that!(&refused).is_err();
+    assert!(refused.err().unwrap().is::<ContractNotWritableError>());
+    assert_that!(&nft_service.get(&asset_id).await).is_ok();
+
+    let retired = contracts_repo
+        .change_status(&contact_id, &ContractStatus::Retired)
+        .await?;
+    assert_eq!(retired.status_history().len(), 2);
+    let back = contracts_repo
+        .change_status(&contact_id, &ContractStatus::Enabled)
+        .await;
+    assert_that!(&back).is_err();
+    let stored = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(stored.status(), &ContractStatus::Retired);
+    assert_eq!(stored.status_history(), retired.status_history());
+
+    //a write from a read older than the status change keeps the status
+    let mut stale = contract.clone();
+    stale.set_details(&"stale details".to_string());
+    contracts_repo.update(&stale).await?;
+    let stored = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(stored.status(), &ContractStatus::Retired);
+    assert_eq!(stored.status_history(), retired.status_history());
+    assert_eq!(stored.details().clone(), Some("stale details".to_string()));
+    assert_eq!(stored.owner_secret_key_id(), contract.owner_secret_key_id());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxy_contract_mint_and_upgrade_test_ganache(
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+
+    let docker = clients::Cli::default();
+
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("dynamodb,secretsmanager,kms");
+    let node = docker.run(local_stack);
+    let host_port = node.get_host_port_ipv4(4566);
+
+    let shared_config = build_local_stack_connection(host_port).await;
+
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    let new_key_id = create_key(&config).await?;
+    env::set_var("KMS_KEY_ID", new_key_id.clone());
+
+    let secrets_json = r#"
+    {
+        "HMAC_SECRET" : "localtest_hmac_1234RGsdfg#$%",
+        "JWT_TOKEN_BASE": "localtest_jwt_sd543ERGds235$%^"
+    }
+    "#;
+    create_secret_manager_with_values(secrets_json, &config).await?;
+
+    config.load_secrets().await;
+
+    let creation = BlockchainSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    let creation = ContractSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    let creation = KeyPairSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    let creation = UserDataKeySchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    let creation = BlockTxSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    let repo_tx = BlockchainTxRepo::new(&config.clone());
+    let tx_service = BlockchainTxService::new(repo_tx);
+    let repo_keys = KeyPairRepo::new(&config.clone());
+    let mut new_configuration = config.env_vars().clone();
+
+    let ganache_params = vec!["-l 100000000".to_string()];
+    let ganache = Ganache::new()
+        .mnemonic(MNEMONIC_TEST)
+        .args(ganache_params)
+        .spawn();
+
+    let contract_owner_secret: &str =
+        "4f3edf983ac636a65a842ce7c78d9aa706d3b113bce9c46f30d7d21715b23b1d"; // example fake secret key
+    let key_id = config.env_vars().kms_key_id().unwrap();
+
+    let block_chains_repo = BlockchainRepo::new(&config.clone());
+    let contracts_repo = ContractRepo::new(&config.clone());
+
+    let blochain_id = "ganache".to_string();
+    let ganache_entity = Blockchain::new(
+        blochain_id.to_owned(),
+        Url::parse(ganache.endpoint().as_str()).unwrap().clone(),
+        "no-api-key".to_string(),
+        0,
+        Url::parse("http://localhost/explorer").unwrap().clone(),
+        "no-api-key-explorer".to_string(),
+        Some(ChainFamily::Evm),
+    );
+    block_chains_repo.add(&ganache_entity).await?;
+
+    let contact_id = 2;
+
+    //proxy, implementation and proxy admin, all owned by the owner key
+    let deployment =
+        DeploymentService::new(contracts_repo.clone(), block_chains_repo.clone(), &config);
+    let owner = ContractOwner::Secret {
+        secret: Secret::from(contract_owner_secret.to_string()),
+        key_id: key_id.clone(),
+    };
+    let deployed = deployment
+        .deploy_evm_proxy(
+            &contact_id,
+            &blochain_id,
+            &owner,
+            &Some("no-details".to_string()),
+        )
+        .await?;
+    assert!(deployed.block_number.is_some());
+    assert!(deployed.cost.is_some());
+    let contract_entity = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(*contract_entity.status(), ContractStatus::Enabled);
+    assert!(contract_entity.proxy_admin().is_some());
+    let proxy_address = contract_entity.address().clone().unwrap();
+    let first_version = contract_entity.current_version().unwrap().clone();
+    assert_eq!(first_version.version, 1);
+    assert_ne!(first_version.code_address, proxy_address);
+
+    new_configuration.set_contract_id(contact_id);
+    config.set_env_vars(&new_configuration);
+
+    //mints go to the proxy address
+    let blockchain = nfts_backend(&contact_id, &config, &contracts_repo, &block_chains_repo)
+        .await
+        .unwrap();
+    let nft_service = NFTsService::new(
+        blockchain.clone_box(),
+        repo_keys.clone(),
+        tx_service.clone(),
+        config.to_owned(),
+    );
+
+    let asset_price: u64 = 2000;
+    let asset_id = registered(&tx_service).await?;
+    let mint_op = nft_service
+        .try_mint(
+            &asset_id,
+            &"user1".to_string(),
+            &Some(asset_price),
+            &"hash".to_string(),
+            &"md5".to_string(),
+            &0,
+        )
+        .await;
+    assert_that!(&mint_op).is_ok();
+
+    //new implementation behind the same address, the state stays at the proxy
+    let upgrades = UpgradeService::new(contracts_repo.clone(), block_chains_repo.clone(), &config);
+    let upgraded = upgrades
+        .upgrade_evm_proxy(&contact_id, &EvmArtifact::light_nft_upgradeable())
+        .await?;
+    assert!(upgraded.block_number.is_some());
+    assert!(upgraded.cost.is_some());
+
+    let contract_entity = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(contract_entity.versions().len(), 2);
+    let second_version = contract_entity.current_version().unwrap();
+    assert_eq!(second_version.version, 2);
+    assert_ne!(second_version.code_address, first_version.code_address);
+    assert_eq!(contract_entity.address().clone(), Some(proxy_address));
+
+    let check_op = nft_service.get(&asset_id).await;
+    assert_that!(&check_op).is_ok();
+    let content = check_op.unwrap();
+    assert_eq!(content.hash_file, "hash".to_string());
+    assert_eq!(content.price.unwrap(), asset_price);
+    assert_eq!(content.state, NTFState::Active);
+
+    //nothing recorded, nothing to upgrade
+    let upgrade = upgrades
+        .upgrade_evm_proxy(&3, &EvmArtifact::light_nft_upgradeable())
+        .await;
+    assert_that!(&upgrade).is_err();
+
     Ok(())
 }
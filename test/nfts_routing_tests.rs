@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lib_blockchain::blockchains::chain::{
+    CloneBoxNFTsRepository, ContentState, ContractContentInfo, NFTsRepository,
+};
+use lib_blockchain::blockchains::mirror::MirrorSet;
+use lib_blockchain::blockchains::router::{ContractRouter, RoutingPolicy};
+use lib_blockchain::errors::blockchain::BlockchainBackendError;
+use lib_blockchain::errors::nft::TokenNotAnchoredOnRequiredChainsError;
+use lib_blockchain::models::amount::{Amount, Unit};
+use lib_blockchain::models::block_tx::{BlockchainTx, BlockchainTxBuilder, MintingStatus};
+use lib_blockchain::models::keypair::{ChainFamily, KeyPair};
+use lib_blockchain::models::secret::Secret;
+use lib_blockchain::repositories::block_tx::BlockchainTxRepo;
+use lib_blockchain::repositories::contract::ContractRepo;
+use lib_blockchain::repositories::keypairs::KeyPairRepo;
+use lib_blockchain::repositories::schema_block_tx::BlockTxSchema;
+use lib_blockchain::repositories::schema_keypairs::KeyPairSchema;
+use lib_blockchain::services::block_tx::{BlockchainTxManipulation, BlockchainTxService};
+use lib_blockchain::services::cost_report::{CostReportService, CostReporting};
+use lib_blockchain::services::nfts::{NFTsManipulation, NFTsService, NTFState};
+use lib_blockchain::services::reconciliation::{ReconciliationManipulation, ReconciliationService};
+use lib_config::config::Config;
+use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
+use lib_config::infra::build_local_stack_connection;
+use lib_config::schema::Schema;
+use spectral::{assert_that, result::ResultAssertions};
+use testcontainers::*;
+use uuid::Uuid;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+const ENV_VAR_AWS_REGION: &str = "AWS_REGION";
+const TEST_AWS_REGION: &str = "eu-central-1";
+
+/// A chain that keeps what it mints in memory, and can be taken down.
+#[derive(Clone)]
+pub struct RecordingChain {
+    contract_id: u16,
+    family: ChainFamily,
+    failing: Arc<AtomicBool>,
+    minted: Arc<Mutex<Vec<Uuid>>>,
+}
+
+impl RecordingChain {
+    pub fn new(contract_id: u16, family: ChainFamily) -> RecordingChain {
+        RecordingChain {
+            contract_id,
+            family,
+            failing: Arc::new(AtomicBool::new(false)),
+            minted: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst)
+    }
+    pub fn minted(&self) -> Vec<Uuid> {
+        self.minted.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl NFTsRepository for RecordingChain {
+    async fn add(
+        &self,
+        asset_id: &Uuid,
+        user_key: &KeyPair,
+        _: &String,
+        _: &String,
+        _: &Option<u64>,
+        _: &u64,
+    ) -> ResultE<BlockchainTx> {
+        if self.failing.load(Ordering::SeqCst) {
+            let mssag = format!("contract {} unreachable", self.contract_id);
+            return Err(BlockchainBackendError(mssag).into());
+        }
+        self.minted.lock().unwrap().push(asset_id.to_owned());
+        let mut tx = BlockchainTxBuilder::new()
+            .asset_id(asset_id.to_owned())
+            .mint_status(MintingStatus::CompletedSuccessfully)
+            .build();
+        tx.set_contract_id(&self.contract_id);
+        tx.set_tx(&format!("0x{:04x}{}", self.contract_id, asset_id.simple()));
+        tx.set_to(user_key.address());
+        let unit = match self.family {
+            ChainFamily::Evm => Unit::Wei,
+            ChainFamily::Sui => Unit::Mist,
+        };
+        tx.set_cost(&Amount::new(1000, unit));
+        Ok(tx)
+    }
+    async fn get(&self, token: &String) -> ResultE<ContractContentInfo> {
+        let asset_id = Uuid::parse_str(token)?;
+        if !self.minted().contains(&asset_id) {
+            let mssag = format!("{} isn't on contract {}", token, self.contract_id);
+            return Err(BlockchainBackendError(mssag).into());
+        }
+        Ok(ContractContentInfo {
+            hashFile: "hash".to_string(),
+            hashAlgo: "md5".to_string(),
+            uri: None,
+            price: None,
+            state: Some(ContentState::Active),
+            token: Some(token.to_owned()),
+        })
+    }
+    fn token(&self, tx: &BlockchainTx) -> Option<String> {
+        Some(tx.asset_id().to_string())
+    }
+    async fn minted_asset_ids(&self) -> ResultE<Vec<String>> {
+        Ok(self.minted().iter().map(|id| id.to_string()).collect())
+    }
+    fn contract_id(&self) -> u16 {
+        self.contract_id
+    }
+    fn chain_family(&self) -> ChainFamily {
+        self.family.clone()
+    }
+    async fn create_keypair(&self, user_id: &String) -> ResultE<(KeyPair, bool)> {
+        let mut key = KeyPair::new();
+        key.set_user_id(user_id);
+        key.set_chain(&self.family);
+        key.set_address(&format!("{}-{}", self.family, user_id).to_lowercase());
+        key.set_public_key(&"public".to_string());
+        key.set_private_key(&Secret::from("cyphered".to_string()));
+        Ok((key, true))
+    }
+}
+
+// the repositories NFTsService works with, on a fresh localstack
+async fn local_services(host_port: u16) -> (Config, KeyPairRepo, BlockchainTxService) {
+    let shared_config = build_local_stack_connection(host_port).await;
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    let creation = KeyPairSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+    let creation = BlockTxSchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
+    let repo_keys = KeyPairRepo::new(&config);
+    let tx_service = BlockchainTxService::new(BlockchainTxRepo::new(&config));
+    (config, repo_keys, tx_service)
+}
+
+// an asset registered and waiting to be minted
+async fn registered(tx_service: &BlockchainTxService) -> ResultE<Uuid> {
+    let asset_id = Uuid::new_v4();
+    let btx = BlockchainTxBuilder::new()
+        .asset_id(asset_id)
+        .mint_status(MintingStatus::NeverMinted)
+        .build();
+    tx_service.add(&btx).await?;
+    Ok(asset_id)
+}
+
+#[tokio::test]
+async fn routed_mints_reach_the_picked_contract_test() -> ResultE<()> {
+    env::set_var(ENV_VAR_AWS_REGION, TEST_AWS_REGION);
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let docker = clients::Cli::default();
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("dynamodb");
+    let node = docker.run(local_stack);
+    let (config, repo_keys, tx_service) = local_services(node.get_host_port_ipv4(4566)).await;
+
+    let evm = RecordingChain::new(1, ChainFamily::Evm);
+    let sui = RecordingChain::new(2, ChainFamily::Sui);
+    let policy = RoutingPolicy::ByCategory {
+        categories: HashMap::from([("video".to_string(), 2)]),
+        default: 1,
+    };
+    let router = ContractRouter::new(vec![evm.clone_box(), sui.clone_box()], policy)?;
+    let nft_service = NFTsService::new(evm.clone_box(), repo_keys, tx_service.clone(), config)
+        .with_router(router);
+
+    let video = registered(&tx_service).await?;
+    let minted = nft_service
+        .try_mint_in_category(
+            &video,
+            &"user1".to_string(),
+            &Some("video".to_string()),
+            &None,
+            &"hash".to_string(),
+            &"md5".to_string(),
+            &0,
+        )
+        .await?;
+    assert_eq!(*minted.contract_id(), Some(2));
+    assert_eq!(minted.to().clone(), Some("sui-user1".to_string()));
+    assert_eq!(sui.minted(), vec![video]);
+    assert!(evm.minted().is_empty());
+
+    let image = registered(&tx_service).await?;
+    let minted = nft_service
+        .try_mint(
+            &image,
+            &"user1".to_string(),
+            &None,
+            &"hash".to_string(),
+            &"md5".to_string(),
+            &0,
+        )
+        .await?;
+    assert_eq!(*minted.contract_id(), Some(1));
+    assert_eq!(evm.minted(), vec![image]);
+
+    //the records and the reads follow the contract that minted
+    let stored = tx_service.get_by_asset_id(&video).await?;
+    assert_eq!(*stored.contract_id(), Some(2));
+    assert_eq!(stored.mint_status(), MintingStatus::CompletedSuccessfully);
+    let content = nft_service.get(&video).await?;
+    assert_eq!(content.hash_file, "hash");
+
+    //a failed mint is recorded and counts against the contract
+    sui.set_failing(true);
+    let failing = registered(&tx_service).await?;
+    let res = nft_service
+        .try_mint_in_category(
+            &failing,
+            &"user1".to_string(),
+            &Some("video".to_string()),
+            &None,
+            &"hash".to_string(),
+            &"md5".to_string(),
+            &0,
+        )
+        .await;
+    assert_that!(&res).is_err();
+    let stored = tx_service.get_by_asset_id(&failing).await?;
+    assert_eq!(stored.mint_status(), MintingStatus::Error);
+    assert_eq!(*stored.contract_id(), Some(2));
+    assert_eq!(sui.minted(), vec![video]);
+
+    Ok(())
+}
+
+fn anchor_status(tx: &BlockchainTx, contract_id: u16) -> MintingStatus {
+    tx.anchor(&contract_id).unwrap().mint_status()
+}
+
+#[tokio::test]
+async fn mirrored_mints_anchor_every_contract_test() -> ResultE<()> {
+    env::set_var(ENV_VAR_AWS_REGION, TEST_AWS_REGION);
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let docker = clients::Cli::default();
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("dynamodb");
+    let node = docker.run(local_stack);
+    let (config, repo_keys, tx_service) = local_services(node.get_host_port_ipv4(4566)).await;
+
+    //two required contracts and a best effort one
+    let evm = RecordingChain::new(1, ChainFamily::Evm);
+    let sui = RecordingChain::new(2, ChainFamily::Sui);
+    let spare = RecordingChain::new(3, ChainFamily::Evm);
+    let mirrors = MirrorSet::new(
+        vec![evm.clone_box(), sui.clone_box(), spare.clone_box()],
+        vec![1, 2],
+    )?;
+    let nft_service = NFTsService::new(
+        evm.clone_box(),
+        repo_keys,
+        tx_service.clone(),
+        config.clone(),
+    )
+    .with_mirrors(mirrors);
+    let mint = |asset_id: Uuid| {
+        let nft_service = nft_service.clone();
+        async move {
+            nft_service
+                .try_mint(
+                    &asset_id,
+                    &"user1".to_string(),
+                    &None,
+                    &"hash".to_string(),
+                    &"md5".to_string(),
+                    &0,
+                )
+                .await
+        }
+    };
+
+    //every mirror takes it
+    let everywhere = registered(&tx_service).await?;
+    let minted = mint(everywhere).await?;
+    assert_eq!(*minted.contract_id(), Some(1));
+    assert_eq!(minted.mint_status(), MintingStatus::CompletedSuccessfully);
+    assert_eq!(minted.anchors().len(), 3);
+    for chain in [&evm, &sui, &spare] {
+        assert_eq!(chain.minted(), vec![everywhere]);
+        assert_eq!(
+            anchor_status(&minted, chain.contract_id()),
+            MintingStatus::CompletedSuccessfully
+        );
+    }
+    let content = nft_service.get(&everywhere).await?;
+    assert_eq!(content.hash_file, "hash");
+    assert_eq!(content.anchors.len(), 3);
+    for anchor in &content.anchors {
+        assert_eq!(anchor.status, MintingStatus::CompletedSuccessfully);
+        assert!(anchor.tx.is_some());
+        assert_eq!(anchor.token, Some(everywhere.to_string()));
+        assert_eq!(anchor.hash_file, Some("hash".to_string()));
+        assert_eq!(anchor.state, Some(NTFState::Active));
+    }
+
+    //a required mirror down: recorded as failed, the others kept
+    sui.set_failing(true);
+    let halfway = registered(&tx_service).await?;
+    let res = mint(halfway).await;
+    assert_that!(&res).is_err();
+    assert!(res
+        .err()
+        .unwrap()
+        .is::<TokenNotAnchoredOnRequiredChainsError>());
+    let stored = tx_service.get_by_asset_id(&halfway).await?;
+    assert_eq!(stored.mint_status(), MintingStatus::Error);
+    assert_eq!(
+        anchor_status(&stored, 1),
+        MintingStatus::CompletedSuccessfully
+    );
+    assert_eq!(anchor_status(&stored, 2), MintingStatus::Error);
+    assert!(stored.anchor(&2).unwrap().tx_error().is_some());
+    assert_eq!(
+        anchor_status(&stored, 3),
+        MintingStatus::CompletedSuccessfully
+    );
+
+    //readable from what went through, the failed anchor unread
+    let content = nft_service.get(&halfway).await?;
+    let failed = content.anchors.iter().find(|a| a.contract_id == 2).unwrap();
+    assert_eq!(failed.status, MintingStatus::Error);
+    assert!(failed.token.is_none());
+    assert!(failed.hash_file.is_none());
+
+    //the retry only mints where it is missing
+    sui.set_failing(false);
+    let minted = mint(halfway).await?;
+    assert_eq!(minted.mint_status(), MintingStatus::CompletedSuccessfully);
+    assert_eq!(evm.minted(), vec![everywhere, halfway]);
+    assert_eq!(sui.minted(), vec![everywhere, halfway]);
+    assert_eq!(spare.minted(), vec![everywhere, halfway]);
+    let stored = tx_service.get_by_asset_id(&halfway).await?;
+    assert_eq!(stored.mint_status(), MintingStatus::CompletedSuccessfully);
+    assert_eq!(
+        anchor_status(&stored, 2),
+        MintingStatus::CompletedSuccessfully
+    );
+    let content = nft_service.get(&halfway).await?;
+    assert!(content
+        .anchors
+        .iter()
+        .all(|a| a.hash_file == Some("hash".to_string())));
+
+    //mirrors see their anchors of the rows kept under the first contract
+    let reconciliation = ReconciliationService::new(sui.clone_box(), tx_service.clone());
+    let report = reconciliation.reconcile().await?;
+    assert_eq!(report.checked, 2);
+    assert_eq!(report.skipped, 0);
+    assert!(report.is_clean());
+
+    let costs = CostReportService::new(tx_service.clone(), ContractRepo::new(&config));
+    let report = costs
+        .cost_report(
+            &(Utc::now() - chrono::Duration::hours(1)),
+            &Utc::now(),
+            &Some(2),
+        )
+        .await?;
+    assert_eq!(report.totals.len(), 1);
+    assert_eq!(report.totals[0].contract_id, 2);
+    assert_eq!(report.totals[0].transactions, 2);
+    assert_eq!(report.totals[0].unit, Unit::Mist);
+    assert_eq!(report.totals[0].total.value(), 2000);
+    let report = costs
+        .cost_report(
+            &(Utc::now() - chrono::Duration::hours(1)),
+            &Utc::now(),
+            &None,
+        )
+        .await?;
+    assert_eq!(report.totals.len(), 3);
+    assert!(report.totals.iter().all(|row| row.transactions == 2));
+
+    Ok(())
+}
@@ -1,23 +1,29 @@
-use chrono::Utc;
 use lib_blockchain::blockchains::chain::CloneBoxNFTsRepository;
-use lib_blockchain::blockchains::sui::SuiBlockChain;
+use lib_blockchain::blockchains::sui::{HashKeeperMode, SuiBlockChain, SuiPackage};
 use lib_blockchain::models::block_tx::MintingStatus;
 use lib_blockchain::models::blockchain::Blockchain;
-use lib_blockchain::models::contract::{Contract, ContractStatus};
+use lib_blockchain::models::contract::ContractStatus;
+use lib_blockchain::models::secret::Secret;
 use lib_blockchain::repositories::block_tx::BlockchainTxRepo;
-use lib_blockchain::repositories::blockchain::{BlockchainRepo, BlockchainRepository};
+use lib_blockchain::repositories::blockchain::BlockchainRepo;
 use lib_blockchain::repositories::contract::{ContractRepo, ContractRepository};
-use lib_blockchain::repositories::keypairs::KeyPairRepo;
+use lib_blockchain::models::keypair::ChainFamily;
+use lib_blockchain::repositories::keypairs::{KeyPairRepo, KeyPairRepository};
 use lib_blockchain::repositories::schema_block_tx::BlockTxSchema;
 use lib_blockchain::repositories::schema_contract::ContractSchema;
 use lib_blockchain::repositories::schema_keypairs::KeyPairSchema;
+use lib_blockchain::repositories::schema_user_data_keys::UserDataKeySchema;
 use lib_blockchain::services::block_tx::{BlockchainTxManipulation, BlockchainTxService};
+use lib_blockchain::services::deployment::{
+    ContractOwner, DeploymentManipulation, DeploymentService,
+};
 use lib_blockchain::services::nfts::{NFTsManipulation, NFTsService};
+use lib_blockchain::services::owner_backup::{OwnerBackupManipulation, OwnerBackupService};
+use lib_blockchain::services::upgrade::{UpgradeManipulation, UpgradeService};
 use lib_config::config::Config;
 use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
 use lib_config::infra::{
     build_local_stack_connection, create_key, create_secret_manager_with_values,
-    cypher_with_secret_key,
 };
 
 use lib_blockchain::repositories::schema_blockchain::BlockchainSchema;
@@ -85,6 +91,9 @@ async fn create_contract_and_mint_nft_test_sync_sui(
     let creation = KeyPairSchema::create_schema(&config).await;
     assert_that(&creation).is_ok();
 
+    let creation = UserDataKeySchema::create_schema(&config).await;
+    assert_that(&creation).is_ok();
+
     let creation = BlockTxSchema::create_schema(&config).await;
     assert_that(&creation).is_ok();
 
@@ -106,8 +115,6 @@ async fn create_contract_and_mint_nft_test_sync_sui(
     let contract_owner_keystore: Vec<u8> = bincode::serialize(&keystore).unwrap();
     let contract_owner_secret_base64 =
         general_purpose::STANDARD_NO_PAD.encode(&contract_owner_keystore);
-    let contract_owner_secret_cyphered =
-        cypher_with_secret_key(&contract_owner_secret_base64, &new_key_id, &config).await?;
     //create blockchain object and contract
     let block_chains_repo = BlockchainRepo::new(&config.clone());
     let contracts_repo = ContractRepo::new(&config.clone());
@@ -116,10 +123,6 @@ async fn create_contract_and_mint_nft_test_sync_sui(
     //create contract and deploy to blockchain
     let url = "http://127.0.0.1:9000".to_string();
 
-    // it needs to be taken from the manual contrat deployment
-    let contract_address =
-        "0x5dd2881df4e9f44495a4d44dc6d24ec486c7f2c13b0701b68462b34f79530f57".to_string();
-
     let confirmations = 0;
     let blochain_id = "sui".to_string();
 
@@ -132,34 +135,68 @@ async fn create_contract_and_mint_nft_test_sync_sui(
             .unwrap()
             .clone(),
         "no-api-key-explorer".to_string(),
+        Some(ChainFamily::Sui),
     );
-    block_chains_repo.add(&blockchain_entity).await?;
 
     let contact_id = 1;
 
-    let contract_entity = Contract::new_c(
-        contact_id,
-        Utc::now(),
-        blochain_id.to_owned(),
-        Some(contract_address),
-        Some(contract_owner_address),
-        Some(contract_owner_secret_cyphered),
-        Some(coin_address),
-        Some("sui blockchain".to_string()),
-        ContractStatus::Enabled,
+    //publish the hasher package signed by the owner and register it
+    let deployment =
+        DeploymentService::new(contracts_repo.clone(), block_chains_repo.clone(), &config);
+    let owner = ContractOwner::Secret {
+        secret: Secret::from(contract_owner_secret_base64.clone()),
+        key_id: new_key_id.clone(),
+    };
+    let deployed = deployment
+        .setup_sui_network(
+            &blockchain_entity,
+            &contact_id,
+            &owner,
+            &Some("sui blockchain".to_string()),
+        )
+        .await?;
+    assert!(deployed.cost.is_some());
+    let contract_entity = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(*contract_entity.status(), ContractStatus::Enabled);
+    assert_eq!(contract_entity.address(), deployed.contract.address());
+    assert!(contract_entity.upgrade_cap().is_some());
+    assert_eq!(*contract_entity.owner_cash(), Some(coin_address));
+    assert_eq!(
+        *contract_entity.owner_address(),
+        Some(contract_owner_address)
+    );
+    assert_eq!(contract_entity.current_version().unwrap().version, 1);
+
+    //the built modules as a new version: the id and address stay, mints go to it
+    let upgrades = UpgradeService::new(contracts_repo.clone(), block_chains_repo.clone(), &config);
+    let upgraded = upgrades
+        .upgrade_sui_package(&contact_id, &SuiPackage::truly()?)
+        .await?;
+    let contract_entity = contracts_repo.get_by_id(&contact_id).await?;
+    assert_eq!(contract_entity.versions().len(), 2);
+    assert_eq!(contract_entity.current_version().unwrap().version, 2);
+    assert_eq!(
+        contract_entity.current_version().unwrap().interface,
+        "hasher"
+    );
+    assert_eq!(contract_entity.address(), deployed.contract.address());
+    assert_eq!(
+        contract_entity.code_address(),
+        upgraded.contract.code_address()
     );
-    contracts_repo.add(&contract_entity).await?;
+    assert_ne!(contract_entity.code_address(), *contract_entity.address());
 
     new_configuration.set_contract_id(contact_id);
     config.set_env_vars(&new_configuration);
 
     let blockchain = SuiBlockChain::new(&config.clone(), &contracts_repo, &block_chains_repo)
         .await
-        .unwrap();
+        .unwrap()
+        .with_keeper_mode(HashKeeperMode::TransferToUser);
 
     let nft_service = NFTsService::new(
         blockchain.clone_box(),
-        repo_keys,
+        repo_keys.clone(),
         tx_service.clone(),
         config.to_owned(),
     );
@@ -192,6 +229,37 @@ async fn create_contract_and_mint_nft_test_sync_sui(
     let txs_op = tx_service.get_by_asset_id(&asset_id).await;
     assert_that!(&txs_op).is_ok();
 
+    //the keeper belongs to the user's own sui address
+    let user_key = repo_keys
+        .get_by_id(&"user1".to_string(), &ChainFamily::Sui)
+        .await?
+        .unwrap();
+    assert_eq!(tx_in_chain.to().clone(), Some(user_key.address().clone()));
+
+    //the owner keystore split among three custodians, any two bring it back
+    let backup = OwnerBackupService::new(contracts_repo.clone(), &config);
+    let owner_backup = backup.backup_owner_secret(&contact_id, 2, 3).await?;
+    assert_eq!(owner_backup.shares.len(), 3);
+    let two_shares = vec![
+        owner_backup.shares[0].clone(),
+        owner_backup.shares[2].clone(),
+    ];
+    let recovered = backup
+        .recover_owner_secret(&contact_id, &ChainFamily::Sui, &two_shares, &new_key_id)
+        .await?;
+    assert_eq!(
+        recovered.owner_address().clone(),
+        contract_entity.owner_address().clone()
+    );
+    let contract = contracts_repo.get_by_id(&contact_id).await?;
+    let owner_secret = contract.owner_secret().clone().unwrap();
+    let owner_secret = owner_secret.uncypher(&new_key_id, &config).await?;
+    assert_eq!(owner_secret.expose(), &contract_owner_secret_base64);
+    let wrong_chain = backup
+        .recover_owner_secret(&contact_id, &ChainFamily::Evm, &two_shares, &new_key_id)
+        .await;
+    assert_that!(&wrong_chain).is_err();
+
     Ok(())
 }
 
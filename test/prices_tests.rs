@@ -0,0 +1,106 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+use chrono::Utc;
+use lib_blockchain::models::amount::{Amount, Currency, Unit};
+use lib_blockchain::models::fiat::{FiatRate, FiatValuation};
+use lib_blockchain::repositories::prices::{HttpPriceRepo, PriceRepository, StaticPriceRepo};
+use spectral::{assert_that, result::ResultAssertions};
+use url::Url;
+
+#[test]
+fn fiat_valuation_test() {
+    let rate = FiatRate::new(
+        Currency::Eth,
+        &"eur".to_string(),
+        &"1843.27".to_string(),
+        &"test".to_string(),
+        Utc::now(),
+    )
+    .unwrap();
+    assert_eq!(rate.fiat(), "EUR");
+
+    //21000 gas at 1 gwei
+    let cost = Amount::new(21_000, Unit::Gwei);
+    let valuation = rate.value_of(&cost).unwrap();
+    assert_eq!(valuation.value(), "0.03870867");
+    assert_eq!(valuation.value_units().unwrap(), 38_708_670);
+
+    //half up at the 9th decimal
+    let dust = rate.value_of(&Amount::wei(1_000_000)).unwrap();
+    assert_eq!(dust.value(), "0.000000002");
+
+    assert_that!(&rate.value_of(&Amount::mist(1))).is_err();
+    assert_eq!(FiatValuation::format_value(3_000_000), "0.003");
+}
+
+#[tokio::test]
+async fn static_price_source_test() {
+    let prices = StaticPriceRepo::from_json(
+        &r#"{ "ETH": { "EUR": "1843.27" }, "SUI": { "EUR": 0.52 } }"#.to_string(),
+    )
+    .unwrap();
+
+    let sui = prices.rate(&Currency::Sui, &"eur".to_string()).await.unwrap();
+    assert_eq!(sui.rate(), "0.52");
+    let valuation = sui.value_of(&Amount::mist(3_000_000)).unwrap();
+    assert_eq!(valuation.value(), "0.00156");
+
+    let missing = prices.rate(&Currency::Eth, &"USD".to_string()).await;
+    assert_that!(&missing).is_err();
+}
+
+// answers a single request with `status` and `body`, handing back the request line
+fn one_shot_server(status: &str, body: &str) -> (Url, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = Url::parse(&format!("http://{}/rates", listener.local_addr().unwrap())).unwrap();
+    let response = format!(
+        "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut header = String::new();
+        while reader.read_line(&mut header).unwrap() > 2 {
+            header.clear();
+        }
+        stream.write_all(response.as_bytes()).unwrap();
+        request_line.trim_end().to_string()
+    });
+    (url, server)
+}
+
+#[tokio::test]
+async fn http_price_source_test() {
+    let (url, server) = one_shot_server(
+        "200 OK",
+        r#"{ "rate": "1843.27", "quoted_at": "2023-07-20T10:00:00Z" }"#,
+    );
+    let eth = HttpPriceRepo::new(&url)
+        .rate(&Currency::Eth, &"eur".to_string())
+        .await
+        .unwrap();
+    assert_eq!(server.join().unwrap(), "GET /rates/ETH/EUR HTTP/1.1");
+    assert_eq!(eth.rate(), "1843.27");
+    assert_eq!(eth.quoted_at().to_rfc3339(), "2023-07-20T10:00:00+00:00");
+
+    let (url, server) = one_shot_server("503 Service Unavailable", r#"{ "error": "busy" }"#);
+    let unavailable = HttpPriceRepo::new(&url)
+        .rate(&Currency::Eth, &"EUR".to_string())
+        .await;
+    server.join().unwrap();
+    assert_that!(&unavailable).is_err();
+
+    let (url, server) = one_shot_server("200 OK", r#"{ "rate": ["1843.27"] }"#);
+    let malformed = HttpPriceRepo::new(&url)
+        .rate(&Currency::Sui, &"EUR".to_string())
+        .await;
+    server.join().unwrap();
+    assert_that!(&malformed).is_err();
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use lib_blockchain::blockchains::chain::{ContractContentInfo, NFTsRepository};
+use lib_blockchain::blockchains::router::{ContractRouter, RoutingPolicy};
+use lib_blockchain::errors::blockchain::BlockchainBackendError;
+use lib_blockchain::errors::contract::ContractNotWritableError;
+use lib_blockchain::models::block_tx::BlockchainTx;
+use lib_blockchain::models::fiat::FiatValuation;
+use lib_blockchain::models::keypair::{ChainFamily, KeyPair};
+use spectral::{assert_that, result::ResultAssertions};
+use uuid::Uuid;
+
+type ResultE<T> = std::result::Result<T, Box<dyn std::error::Error + Sync + Send>>;
+
+#[derive(Clone)]
+pub struct FakeChain {
+    pub contract_id: u16,
+    pub family: ChainFamily,
+}
+
+#[async_trait]
+impl NFTsRepository for FakeChain {
+    async fn add(
+        &self,
+        _: &Uuid,
+        _: &KeyPair,
+        _: &String,
+        _: &String,
+        _: &Option<u64>,
+        _: &u64,
+    ) -> ResultE<BlockchainTx> {
+        Err(BlockchainBackendError("fake".to_string()).into())
+    }
+    async fn get(&self, _: &String) -> ResultE<ContractContentInfo> {
+        Err(BlockchainBackendError("fake".to_string()).into())
+    }
+    fn token(&self, tx: &BlockchainTx) -> Option<String> {
+        Some(tx.asset_id().to_string())
+    }
+    async fn minted_asset_ids(&self) -> ResultE<Vec<String>> {
+        Ok(Vec::new())
+    }
+    fn contract_id(&self) -> u16 {
+        self.contract_id
+    }
+    fn chain_family(&self) -> ChainFamily {
+        self.family.clone()
+    }
+    async fn create_keypair(&self, _: &String) -> ResultE<(KeyPair, bool)> {
+        Err(BlockchainBackendError("fake".to_string()).into())
+    }
+}
+
+pub fn backends() -> Vec<Box<dyn NFTsRepository + Sync + Send>> {
+    vec![
+        Box::new(FakeChain {
+            contract_id: 1,
+            family: ChainFamily::Evm,
+        }),
+        Box::new(FakeChain {
+            contract_id: 2,
+            family: ChainFamily::Sui,
+        }),
+    ]
+}
+
+fn picked(router: &ContractRouter, category: Option<&str>) -> u16 {
+    let category = category.map(|cat| cat.to_string());
+    router.pick(&category).unwrap().contract_id()
+}
+
+fn minted(contract_id: u16, fiat_value: &str) -> BlockchainTx {
+    let mut tx = BlockchainTx::default();
+    tx.set_contract_id(&contract_id);
+    tx.set_fiat_cost(&FiatValuation::new(
+        &"EUR".to_string(),
+        &"1".to_string(),
+        &fiat_value.to_string(),
+        &"test".to_string(),
+        Utc::now(),
+    ));
+    tx
+}
+
+#[test]
+fn router_policies_test() {
+    let chain_down = BlockchainBackendError("node unreachable".to_string());
+
+    //round robin, skipping a contract that keeps failing
+    let router = ContractRouter::new(backends(), RoutingPolicy::RoundRobin).unwrap();
+    assert_eq!(picked(&router, None), 1);
+    assert_eq!(picked(&router, None), 2);
+    assert_eq!(picked(&router, None), 1);
+    for _ in 0..3 {
+        router.report_failure(&1, &chain_down);
+    }
+    assert!(!router.is_up(&1));
+    assert_eq!(picked(&router, None), 2);
+    assert_eq!(picked(&router, None), 2);
+    router.report_failure(&2, &ContractNotWritableError("read only".to_string()));
+    assert_that!(router.pick(&None)).is_err();
+
+    //failover: back to the first one once its cooldown is over
+    let router = ContractRouter::new(backends(), RoutingPolicy::Failover(vec![2, 1]))
+        .unwrap()
+        .with_cooldown(Duration::ZERO);
+    assert_eq!(picked(&router, None), 2);
+    router.report_failure(&2, &ContractNotWritableError("read only".to_string()));
+    assert!(router.is_up(&2));
+    let router = ContractRouter::new(backends(), RoutingPolicy::Failover(vec![2, 1])).unwrap();
+    router.report_failure(&2, &ContractNotWritableError("read only".to_string()));
+    assert_eq!(picked(&router, None), 1);
+
+    //by category, no falling over to another contract
+    let categories = HashMap::from([("video".to_string(), 2)]);
+    let policy = RoutingPolicy::ByCategory {
+        categories,
+        default: 1,
+    };
+    let router = ContractRouter::new(backends(), policy).unwrap();
+    assert_eq!(picked(&router, Some("video")), 2);
+    assert_eq!(picked(&router, Some("image")), 1);
+    assert_eq!(picked(&router, None), 1);
+    router.report_failure(&2, &ContractNotWritableError("read only".to_string()));
+    assert_that!(router.pick(&Some("video".to_string()))).is_err();
+
+    //least cost: the unmeasured first, then the cheapest
+    let router = ContractRouter::new(backends(), RoutingPolicy::LeastCost).unwrap();
+    assert_eq!(picked(&router, None), 1);
+    router.report_success(&minted(1, "0.50"));
+    assert_eq!(picked(&router, None), 2);
+    router.report_success(&minted(2, "0.01"));
+    assert_eq!(picked(&router, None), 2);
+    router.report_success(&minted(2, "4.00"));
+    assert_eq!(picked(&router, None), 1);
+
+    //misconfigurations are refused upfront
+    let mut twice = backends();
+    twice.append(&mut backends());
+    assert_that!(ContractRouter::new(twice, RoutingPolicy::RoundRobin)).is_err();
+    assert_that!(ContractRouter::new(Vec::new(), RoutingPolicy::RoundRobin)).is_err();
+    let unknown = RoutingPolicy::Failover(vec![1, 3]);
+    assert_that!(ContractRouter::new(backends(), unknown)).is_err();
+}
@@ -0,0 +1,105 @@
+use base64::{engine::general_purpose, Engine};
+use lib_blockchain::models::contract::Contract;
+use lib_blockchain::models::keypair::KeyPair;
+use lib_blockchain::models::secret::Secret;
+use lib_config::config::Config;
+use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
+use lib_config::infra::{build_local_stack_connection, create_key, cypher_with_secret_key};
+use spectral::{assert_that, result::ResultAssertions};
+use std::env;
+use testcontainers::*;
+
+#[test]
+fn secret_is_redacted_test() {
+    let secret = Secret::from(
+        "4f3edf983ac636a65a842ce7c78d9aa706d3b113bce9c46f30d7d21715b23b1d".to_string(),
+    );
+
+    assert_eq!(format!("{:?}", secret), "[REDACTED]");
+    assert_eq!(format!("{}", secret), "[REDACTED]");
+    assert_that!(&serde_json::to_string(&secret)).is_err();
+    assert!(secret.expose().starts_with("4f3edf98"));
+
+    let mut keypair = KeyPair::new();
+    keypair.set_user_id(&"user1".to_string());
+    keypair.set_private_key(&secret);
+    let debug = format!("{:?}", keypair);
+    assert!(!debug.contains("4f3edf98"));
+    let json = serde_json::to_string(&keypair).unwrap();
+    assert!(!json.contains("4f3edf98"));
+    assert!(!json.contains("private_key"));
+
+    let mut contract = Contract::new();
+    contract.set_owner_secret(&secret);
+    assert!(!format!("{}", contract).contains("4f3edf98"));
+    assert!(!format!("{:?}", contract).contains("4f3edf98"));
+}
+
+#[tokio::test]
+async fn secret_envelope_test() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env::set_var("RUST_LOG", "debug");
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+
+    let docker = clients::Cli::default();
+
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("kms");
+    let node = docker.run(local_stack);
+    let host_port = node.get_host_port_ipv4(4566);
+
+    let shared_config = build_local_stack_connection(host_port).await;
+
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    let key_id = create_key(&config).await?;
+    let secret = Secret::from("a secret longer than kms would ever take ".repeat(200));
+
+    let cyphered = secret.cypher(&key_id, &config).await?;
+    assert!(cyphered.is_envelope());
+    assert!(cyphered.expose().starts_with("env1:"));
+    assert!(!cyphered.expose().contains("a secret"));
+    let plain = cyphered.uncypher(&key_id, &config).await?;
+    assert_eq!(plain.expose(), secret.expose());
+
+    //fresh nonce every time
+    let again = secret.cypher(&key_id, &config).await?;
+    assert_ne!(again.expose(), cyphered.expose());
+
+    //items cyphered before envelopes still open
+    let legacy = Secret::from(cypher_with_secret_key("legacy", key_id.as_str(), &config).await?);
+    assert!(!legacy.is_envelope());
+    assert_eq!(legacy.uncypher(&key_id, &config).await?.expose(), "legacy");
+
+    //tampered or truncated envelopes don't
+    let mut tampered = cyphered.expose().to_owned();
+    let last = tampered.pop().unwrap();
+    tampered.push(if last == 'A' { 'B' } else { 'A' });
+    assert_that!(&Secret::from(tampered).uncypher(&key_id, &config).await).is_err();
+    let truncated = cyphered
+        .expose()
+        .rsplitn(2, ':')
+        .last()
+        .unwrap()
+        .to_string();
+    assert_that!(&Secret::from(truncated).uncypher(&key_id, &config).await).is_err();
+
+    Ok(())
+}
+
+#[test]
+fn secret_seal_test() {
+    let data_key = Secret::from(general_purpose::STANDARD.encode([7u8; 32]));
+    let other_key = Secret::from(general_purpose::STANDARD.encode([8u8; 32]));
+    let secret = Secret::from("user private key".to_string());
+
+    let sealed = secret.seal(&data_key).unwrap();
+    assert!(sealed.is_user_sealed());
+    assert!(!sealed.is_envelope());
+    assert_eq!(sealed.open(&data_key).unwrap().expose(), "user private key");
+
+    //once the data key is gone, so is the secret
+    assert_that!(&sealed.open(&other_key)).is_err();
+    assert_that!(&secret.open(&data_key)).is_err();
+}
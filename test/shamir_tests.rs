@@ -0,0 +1,51 @@
+use lib_blockchain::blockchains::shamir::{combine_shares, split_secret};
+use lib_blockchain::errors::backup::BackupShareError;
+use lib_blockchain::models::secret::Secret;
+use spectral::{assert_that, result::ResultAssertions};
+
+#[test]
+fn shamir_split_and_combine_test() {
+    let secret = Secret::from(
+        "4f3edf983ac636a65a842ce7c78d9aa706d3b113bce9c46f30d7d21715b23b1d".to_string(),
+    );
+    let label = "contract-1".to_string();
+
+    let shares = split_secret(&secret, &label, 3, 5).unwrap();
+    assert_eq!(shares.len(), 5);
+    for share in &shares {
+        assert!(!share.expose().contains("4f3edf98"));
+        assert_eq!(format!("{:?}", share), "[REDACTED]");
+    }
+
+    //any three of them
+    for picked in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+        let subset: Vec<Secret> = picked.iter().map(|i| shares[*i].clone()).collect();
+        let rebuilt = combine_shares(&label, &subset).unwrap();
+        assert_eq!(rebuilt.expose(), secret.expose());
+    }
+
+    //two aren't enough, the same share twice doesn't count as two
+    let res = combine_shares(&label, &shares[0..2]);
+    assert_that!(res).is_err();
+    let twice = vec![shares[0].clone(), shares[0].clone(), shares[1].clone()];
+    let res = combine_shares(&label, &twice);
+    assert_that!(res).is_err();
+    assert!(res.err().unwrap().is::<BackupShareError>());
+
+    //a typo is caught by the checksum
+    let mut typo = shares[1].expose().to_owned();
+    let at = typo.len() - 20;
+    let replaced = if &typo[at..at + 1] == "0" { "1" } else { "0" };
+    typo.replace_range(at..at + 1, replaced);
+    let subset = vec![shares[0].clone(), Secret::from(typo), shares[2].clone()];
+    assert_that!(combine_shares(&label, &subset)).is_err();
+
+    //shares of another split or another contract don't mix
+    let other = split_secret(&secret, &label, 3, 5).unwrap();
+    let mixed = vec![shares[0].clone(), shares[1].clone(), other[2].clone()];
+    assert_that!(combine_shares(&label, &mixed)).is_err();
+    assert_that!(combine_shares(&"contract-2".to_string(), &shares[0..3])).is_err();
+
+    assert_that!(split_secret(&secret, &label, 1, 5)).is_err();
+    assert_that!(split_secret(&secret, &label, 6, 5)).is_err();
+}
@@ -0,0 +1,168 @@
+use crate::nfts_tests::MNEMONIC_TEST;
+use aws_sdk_kms::types::{KeySpec, KeyUsageType};
+use ethers::utils::Ganache;
+use lib_blockchain::blockchains::hd_wallet::derive_from_mnemonic;
+use lib_blockchain::blockchains::signer::{
+    sign_evm_transaction, KmsSigner, LocalKeySigner, Signer,
+};
+use lib_blockchain::models::keypair::ChainFamily;
+use lib_blockchain::models::secret::Secret;
+use lib_config::config::Config;
+use lib_config::environment::{DEV_ENV, ENV_VAR_ENVIRONMENT};
+use lib_config::infra::build_local_stack_connection;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use secp256k1::{Message, Secp256k1};
+use spectral::{assert_that, result::ResultAssertions};
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+use testcontainers::*;
+use web3::signing::keccak256;
+use web3::types::{Address, TransactionParameters, U256, U64};
+
+async fn create_signing_key(config: &Config) -> String {
+    let client = aws_sdk_kms::Client::new(config.aws_config());
+    let output = client
+        .create_key()
+        .key_spec(KeySpec::EccSecgP256K1)
+        .key_usage(KeyUsageType::SignVerify)
+        .send()
+        .await
+        .unwrap();
+    output.key_metadata().unwrap().key_id().unwrap().to_string()
+}
+
+fn recover_address(digest: &[u8; 32], signature: &Vec<u8>) -> String {
+    let recovery_id = RecoveryId::from_i32(signature[64] as i32).unwrap();
+    let recoverable = RecoverableSignature::from_compact(&signature[0..64], recovery_id).unwrap();
+    let public_key = Secp256k1::new()
+        .recover_ecdsa(&Message::from_slice(digest).unwrap(), &recoverable)
+        .unwrap();
+    let uncompressed = public_key.serialize_uncompressed();
+    format!(
+        "{:?}",
+        Address::from_slice(&keccak256(&uncompressed[1..])[12..])
+    )
+}
+
+#[tokio::test]
+async fn local_signer_test() {
+    let secret = derive_from_mnemonic(
+        &Secret::from(MNEMONIC_TEST.to_string()),
+        &ChainFamily::Evm,
+        0,
+    )
+    .unwrap()
+    .secret()
+    .to_owned();
+    let signer = LocalKeySigner::new(&ChainFamily::Evm, &secret).unwrap();
+    assert_eq!(
+        signer.address(),
+        "0x90f8bf6a479f320ead074411a4b0e7944ea8c9c1"
+    );
+
+    let digest = keccak256(b"truly local signer");
+    let signature = signer.sign_digest(&digest).await.unwrap();
+    assert_eq!(signature.len(), 65);
+    assert_eq!(recover_address(&digest, &signature), *signer.address());
+
+    let wrong = LocalKeySigner::new(&ChainFamily::Evm, &Secret::from("not a key".to_string()));
+    assert_that!(&wrong).is_err();
+}
+
+#[tokio::test]
+async fn kms_signer_test() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    env::set_var("RUST_LOG", "debug");
+    env::set_var(ENV_VAR_ENVIRONMENT, DEV_ENV);
+
+    let docker = clients::Cli::default();
+
+    let mut local_stack = images::local_stack::LocalStack::default();
+    local_stack.set_services("kms");
+    let node = docker.run(local_stack);
+    let host_port = node.get_host_port_ipv4(4566);
+
+    let shared_config = build_local_stack_connection(host_port).await;
+
+    let mut config = Config::new();
+    config.setup().await;
+    config.set_aws_config(&shared_config);
+
+    let key_id = create_signing_key(&config).await;
+    let signer = KmsSigner::new(&key_id, &ChainFamily::Evm, &config).await?;
+
+    //kms gives no recovery id, it must be worked out right
+    for msg in ["first", "second", "third", "fourth"] {
+        let digest = keccak256(msg.as_bytes());
+        let signature = signer.sign_digest(&digest).await?;
+        assert_eq!(signature.len(), 65);
+        assert_eq!(recover_address(&digest, &signature), *signer.address());
+    }
+
+    let sui_signer = KmsSigner::new(&key_id, &ChainFamily::Sui, &config).await?;
+    let sui_signature = sui_signer.sign_digest(&keccak256(b"sui")).await?;
+    assert_eq!(sui_signature.len(), 1 + 64 + 33);
+    assert_eq!(sui_signature[0], 0x01);
+    assert_eq!(sui_signer.address().len(), 66);
+
+    //symmetric keys can't sign
+    let client = aws_sdk_kms::Client::new(config.aws_config());
+    let symmetric = client.create_key().send().await?;
+    let symmetric_id = symmetric
+        .key_metadata()
+        .unwrap()
+        .key_id()
+        .unwrap()
+        .to_string();
+    let not_signing = KmsSigner::new(&symmetric_id, &ChainFamily::Evm, &config).await;
+    assert_that!(&not_signing).is_err();
+
+    //the key never leaves kms, still it pays for its own transactions
+    let ganache = Ganache::new().mnemonic(MNEMONIC_TEST).spawn();
+    let transport = web3::transports::Http::new(ganache.endpoint().as_str())?;
+    let web3 = web3::Web3::new(transport);
+    let chain_id = web3.eth().chain_id().await?.as_u64();
+    let gas_price = web3.eth().gas_price().await?;
+
+    let secret = derive_from_mnemonic(
+        &Secret::from(MNEMONIC_TEST.to_string()),
+        &ChainFamily::Evm,
+        0,
+    )?
+    .secret()
+    .to_owned();
+    let funder = LocalKeySigner::new(&ChainFamily::Evm, &secret)?;
+    let kms_address = Address::from_str(signer.address().as_str())?;
+    let funder_address = Address::from_str(funder.address().as_str())?;
+
+    let funding = TransactionParameters {
+        nonce: Some(web3.eth().transaction_count(funder_address, None).await?),
+        to: Some(kms_address),
+        gas: U256::from(21000),
+        gas_price: Some(gas_price),
+        value: U256::exp10(18),
+        chain_id: Some(chain_id),
+        ..Default::default()
+    };
+    let signed = sign_evm_transaction(&web3, &funder, funding).await?;
+    web3.send_raw_transaction_with_confirmation(signed.raw_transaction, Duration::from_secs(1), 0)
+        .await?;
+
+    let payback = TransactionParameters {
+        nonce: Some(web3.eth().transaction_count(kms_address, None).await?),
+        to: Some(funder_address),
+        gas: U256::from(21000),
+        gas_price: Some(gas_price),
+        value: U256::from(1000),
+        chain_id: Some(chain_id),
+        ..Default::default()
+    };
+    let signed = sign_evm_transaction(&web3, &signer, payback).await?;
+    let receipt = web3
+        .send_raw_transaction_with_confirmation(signed.raw_transaction, Duration::from_secs(1), 0)
+        .await?;
+    assert_eq!(receipt.status, Some(U64::from(1)));
+    assert_eq!(receipt.from, kms_address);
+
+    Ok(())
+}